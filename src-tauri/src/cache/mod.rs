@@ -179,12 +179,15 @@ impl Cache {
         glossary: &[crate::project::GlossaryEntry],
         target_language: &str,
         style_prompt: &str,
+        provider_id: &str,
     ) -> Result<String, String> {
         let mut hasher = Sha256::new();
         hasher.update(serde_json::to_string(segments).map_err(|e| e.to_string())?);
         hasher.update(serde_json::to_string(glossary).map_err(|e| e.to_string())?);
         hasher.update(target_language);
         hasher.update(style_prompt);
+        // Провайдер и модель: перевод другой моделью не должен браться из кэша
+        hasher.update(provider_id);
         Ok(format!("{:x}", hasher.finalize()))
     }
 }
\ No newline at end of file
@@ -1,21 +1,49 @@
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use crate::cache::Cache;
-use crate::project::{SubtitleSegment, GlossaryEntry};
+use crate::project::{Project, SubtitleSegment, SubtitleWord, GlossaryEntry, Timestamp};
 use keyring::Entry;
 use crate::project::glossary::apply_glossary;
+use crate::segmentation::{self, resegment_transcript, SegmentationOptions};
 use tokio::sync::mpsc;
 use tauri::Emitter;
 use std::collections::{HashMap, HashSet};
 use std::sync::OnceLock;
 use tokio::process::Command;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use std::sync::Arc;
+use crate::commands::audio::media_duration_seconds;
+use crate::transcription::{
+    LocalBackend, OpenAiBackend, Transcript, TranscriptionBackend, TranscriptionEngine, TranscriptionRequest,
+    TranscriptionSettings,
+};
+use crate::translation::{
+    AuthScheme, ChatCompletionsProvider, ChatRequest, DeepLProvider, TranslationProvider, TranslationProviderKind,
+    TranslationRequest, TranslationSettings,
+};
 
 /// Макс. длина вывода промптов/ответов в терминал (UTF-8 символы).
 const DEBUG_LOG_MAX_CHARS: usize = 24_000;
-const WHISPER_MAX_UPLOAD_BYTES: u64 = 25 * 1024 * 1024;
-const WHISPER_TARGET_UPLOAD_BYTES: u64 = 24 * 1024 * 1024;
-
-fn log_debug_block(title: &str, body: &str) {
+/// Номинальная длина куска при распознавании длинных файлов, с
+const WHISPER_CHUNK_SECONDS: f64 = 600.0;
+/// Насколько граница куска может отойти от номинальной в поисках паузы, с
+const WHISPER_CHUNK_SEARCH_SECONDS: f64 = 60.0;
+/// Перекрытие соседних кусков, с: слово на стыке целиком попадает хотя бы в один
+const WHISPER_CHUNK_OVERLAP_SECONDS: f64 = 2.0;
+/// Одновременно распознаваемых кусков
+const WHISPER_CHUNK_CONCURRENCY: usize = 3;
+/// Попыток распознать один кусок, прежде чем вся транскрибация завершится ошибкой
+const WHISPER_CHUNK_ATTEMPTS: u32 = 3;
+/// Пауза перед повтором куска, с; растёт с номером попытки
+const WHISPER_CHUNK_RETRY_DELAY_SECONDS: u64 = 2;
+/// 10 минут моно при 48 кбит/с — около 3.5 МБ, распознавание от сжатия не страдает
+const WHISPER_CHUNK_BITRATE_KBPS: u32 = 48;
+/// Порог и минимальная длина паузы для `silencedetect`
+const WHISPER_SILENCE_NOISE_DB: i32 = -35;
+const WHISPER_SILENCE_MIN_SECONDS: f64 = 0.3;
+
+pub(crate) fn log_debug_block(title: &str, body: &str) {
     let count = body.chars().count();
     let shown: String = body.chars().take(DEBUG_LOG_MAX_CHARS).collect();
     println!("\n========== {title} ==========");
@@ -27,77 +55,167 @@ fn log_debug_block(title: &str, body: &str) {
     }
 }
 
-fn infer_audio_mime(path: &Path) -> &'static str {
-    match path
-        .extension()
-        .and_then(|s| s.to_str())
-        .unwrap_or("")
-        .to_ascii_lowercase()
-        .as_str()
-    {
-        "mp3" => "audio/mpeg",
-        "m4a" | "mp4" => "audio/mp4",
-        "wav" => "audio/wav",
-        "ogg" => "audio/ogg",
-        "webm" => "audio/webm",
-        "flac" => "audio/flac",
-        _ => "application/octet-stream",
-    }
+/// Кусок длинного файла. В Whisper уходит `[start, end]` с перекрытием, а в результат —
+/// только слова, начавшиеся в `[keep_from, keep_until)`: эти границы проходят по паузам.
+#[derive(Debug, Clone)]
+struct AudioChunk {
+    start: f64,
+    end: f64,
+    keep_from: Timestamp,
+    /// `None` у последнего куска
+    keep_until: Option<Timestamp>,
 }
 
-async fn transcode_for_whisper_limit(input_path: &Path) -> Result<(Vec<u8>, String, String), String> {
-    let temp_dir = std::env::temp_dir();
-    let bitrates_kbps = [24, 16, 12, 8];
+/// Паузы по фильтру ffmpeg `silencedetect`: интервалы `(начало, конец)` в секундах
+async fn detect_silences(input_path: &Path) -> Result<Vec<(f64, f64)>, String> {
+    let output = Command::new("ffmpeg")
+        .arg("-hide_banner")
+        .arg("-nostats")
+        .arg("-i")
+        .arg(input_path)
+        .arg("-vn")
+        .arg("-af")
+        .arg(format!("silencedetect=noise={WHISPER_SILENCE_NOISE_DB}dB:d={WHISPER_SILENCE_MIN_SECONDS}"))
+        .arg("-f")
+        .arg("null")
+        .arg("-")
+        .output()
+        .await
+        .map_err(|e| format!("Не удалось запустить ffmpeg для поиска пауз: {}", e))?;
 
-    for bitrate in bitrates_kbps {
-        let out_name = format!(
-            "subtitle_studio_whisper_{}_{}k.m4a",
-            uuid::Uuid::new_v4(),
-            bitrate
-        );
-        let out_path = temp_dir.join(out_name);
-
-        let output = Command::new("ffmpeg")
-            .arg("-y")
-            .arg("-i")
-            .arg(input_path)
-            .arg("-vn")
-            .arg("-ac")
-            .arg("1")
-            .arg("-ar")
-            .arg("16000")
-            .arg("-c:a")
-            .arg("aac")
-            .arg("-b:a")
-            .arg(format!("{bitrate}k"))
-            .arg(&out_path)
-            .output()
-            .await
-            .map_err(|e| format!("Не удалось запустить ffmpeg для сжатия аудио: {}", e))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            let _ = std::fs::remove_file(&out_path);
-            return Err(format!("ffmpeg сжатие аудио завершилось с ошибкой: {}", stderr));
-        }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(format!("ffmpeg поиск пауз завершился с ошибкой: {}", stderr));
+    }
 
-        let meta = std::fs::metadata(&out_path)
-            .map_err(|e| format!("Не удалось получить размер сжатого аудио: {}", e))?;
-        let out_size = meta.len();
-        if out_size <= WHISPER_MAX_UPLOAD_BYTES {
-            let data = std::fs::read(&out_path)
-                .map_err(|e| format!("Ошибка чтения сжатого аудио: {}", e))?;
-            let _ = std::fs::remove_file(&out_path);
-            return Ok((data, out_size.to_string(), "audio/mp4".to_string()));
+    let value_after = |line: &str, key: &str| -> Option<f64> {
+        line.split(key).nth(1)?.split_whitespace().next()?.parse().ok()
+    };
+    let mut silences = Vec::new();
+    let mut open: Option<f64> = None;
+    for line in stderr.lines() {
+        if let Some(start) = value_after(line, "silence_start: ") {
+            open = Some(start.max(0.0));
+        } else if let Some(end) = value_after(line, "silence_end: ") {
+            if let Some(start) = open.take() {
+                silences.push((start, end));
+            }
         }
+    }
+    Ok(silences)
+}
+
+/// Граница каждого куска — середина самой длинной паузы в окне вокруг номинальной
+/// границы; если пауз нет, режем по номинальной.
+fn plan_chunks(duration: f64, silences: &[(f64, f64)]) -> Vec<AudioChunk> {
+    let mut bounds = vec![0.0];
+    let mut last = 0.0;
+    while duration - last > WHISPER_CHUNK_SECONDS + WHISPER_CHUNK_SEARCH_SECONDS {
+        let target = last + WHISPER_CHUNK_SECONDS;
+        let (from, to) = (target - WHISPER_CHUNK_SEARCH_SECONDS, target + WHISPER_CHUNK_SEARCH_SECONDS);
+        let cut = silences
+            .iter()
+            .filter(|&&(start, end)| end > from && start < to)
+            .map(|&(start, end)| (start.max(from), end.min(to)))
+            .max_by(|a, b| (a.1 - a.0).total_cmp(&(b.1 - b.0)))
+            .map(|(start, end)| (start + end) / 2.0)
+            .unwrap_or(target);
+        bounds.push(cut);
+        last = cut;
+    }
+    bounds.push(duration);
 
+    let count = bounds.len() - 1;
+    bounds
+        .windows(2)
+        .enumerate()
+        .map(|(i, w)| AudioChunk {
+            start: (w[0] - WHISPER_CHUNK_OVERLAP_SECONDS).max(0.0),
+            end: (w[1] + WHISPER_CHUNK_OVERLAP_SECONDS).min(duration),
+            keep_from: Timestamp::from_seconds(w[0]),
+            keep_until: (i + 1 < count).then(|| Timestamp::from_seconds(w[1])),
+        })
+        .collect()
+}
+
+/// Вырезает кусок во временный файл (моно 16 кГц AAC)
+async fn encode_chunk(input_path: &Path, chunk: &AudioChunk, dir: &Path) -> Result<PathBuf, String> {
+    let out_path = dir.join(format!("{}.m4a", uuid::Uuid::new_v4()));
+
+    let output = Command::new("ffmpeg")
+        .arg("-y")
+        .arg("-ss")
+        .arg(format!("{:.3}", chunk.start))
+        .arg("-t")
+        .arg(format!("{:.3}", chunk.end - chunk.start))
+        .arg("-i")
+        .arg(input_path)
+        .arg("-vn")
+        .arg("-ac")
+        .arg("1")
+        .arg("-ar")
+        .arg("16000")
+        .arg("-c:a")
+        .arg("aac")
+        .arg("-b:a")
+        .arg(format!("{WHISPER_CHUNK_BITRATE_KBPS}k"))
+        .arg(&out_path)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("Не удалось запустить ffmpeg для нарезки аудио: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
         let _ = std::fs::remove_file(&out_path);
+        return Err(format!("ffmpeg нарезка аудио завершилась с ошибкой: {}", stderr));
+    }
+
+    Ok(out_path)
+}
+
+/// Временная папка кусков длинной записи. Удаляется со всем содержимым при выходе из
+/// `transcribe_in_chunks`, в том числе когда оставшиеся задачи прерваны после ошибки.
+struct ChunkDir(PathBuf);
+
+impl ChunkDir {
+    fn create() -> Result<Self, String> {
+        let path = std::env::temp_dir().join(format!("subtitle_studio_whisper_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).map_err(|e| format!("Не удалось создать временную папку: {}", e))?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for ChunkDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
     }
+}
 
-    Err(
-        "Аудио слишком большое даже после автоматического сжатия. Сократите длительность файла или разбейте его на части."
-            .to_string(),
-    )
+/// Слова одного куска на общей шкале времени, только из его зоны `[keep_from, keep_until)`
+fn chunk_words(transcript: Transcript, chunk: &AudioChunk) -> Vec<SubtitleWord> {
+    let segments = sanitize_whisper_segments(transcript.segments);
+    let offset = Timestamp::from_seconds(chunk.start);
+    segmentation::transcript_words(&segments, &transcript.words)
+        .into_iter()
+        .map(|mut word| {
+            word.start += offset;
+            word.end += offset;
+            word
+        })
+        .filter(|word| word.start >= chunk.keep_from && chunk.keep_until.is_none_or(|until| word.start < until))
+        .collect()
+}
+
+/// Склейка кусков по порядку. Слово, начавшееся раньше конца последнего уже взятого,
+/// — то же место записи, распознанное повторно в перекрытии: его отбрасываем.
+fn stitch_chunk_words(chunks: Vec<Vec<SubtitleWord>>) -> Vec<SubtitleWord> {
+    let mut words: Vec<SubtitleWord> = Vec::new();
+    for chunk in chunks {
+        let tail = words.last().map(|w| w.end);
+        words.extend(chunk.into_iter().filter(|w| tail.is_none_or(|end| w.start >= end)));
+    }
+    words
 }
 
 #[tauri::command]
@@ -132,6 +250,15 @@ pub async fn get_api_key_status() -> Result<bool, String> {
     }
 }
 
+fn transcription_backend(settings: &TranscriptionSettings) -> Result<Arc<dyn TranscriptionBackend>, String> {
+    Ok(match settings.engine {
+        TranscriptionEngine::OpenAi => Arc::new(OpenAiBackend::new(get_api_key()?)),
+        TranscriptionEngine::WhisperCpp | TranscriptionEngine::FasterWhisper => {
+            Arc::new(LocalBackend::new(settings)?)
+        }
+    })
+}
+
 fn get_api_key() -> Result<String, String> {
     let entry = Entry::new(KEYRING_SERVICE, KEYRING_USER)
         .map_err(|e| e.to_string())?;
@@ -140,12 +267,69 @@ fn get_api_key() -> Result<String, String> {
         .map_err(|e| format!("Ключ не найден или ошибка доступа: {}", e))
 }
 
+/// Ключ провайдера перевода; у OpenAI — тот же, что для Whisper
+#[tauri::command]
+pub async fn save_provider_api_key(provider: TranslationProviderKind, key: String) -> Result<(), String> {
+    let user = match provider {
+        TranslationProviderKind::OpenAi => return save_api_key(key).await,
+        TranslationProviderKind::OpenAiCompatible => KEYRING_USER_COMPATIBLE,
+        TranslationProviderKind::DeepL => KEYRING_USER_DEEPL,
+    };
+    if key.trim().is_empty() {
+        return Err("API ключ не может быть пустым".to_string());
+    }
+
+    let entry = Entry::new(KEYRING_SERVICE, user)
+        .map_err(|e| format!("Ошибка инициализации хранилища: {}", e))?;
+    entry.set_password(key.trim())
+        .map_err(|e| format!("Ошибка сохранения ключа: {}", e))?;
+
+    println!("🔑 API ключ {:?} сохранён в системном хранилище", provider);
+    Ok(())
+}
+
+fn provider_api_key(provider: TranslationProviderKind) -> Result<String, String> {
+    let user = match provider {
+        TranslationProviderKind::OpenAi => return get_api_key(),
+        TranslationProviderKind::OpenAiCompatible => KEYRING_USER_COMPATIBLE,
+        TranslationProviderKind::DeepL => KEYRING_USER_DEEPL,
+    };
+    let entry = Entry::new(KEYRING_SERVICE, user)
+        .map_err(|e| e.to_string())?;
+
+    entry.get_password()
+        .map_err(|e| format!("Ключ {:?} не найден или ошибка доступа: {}", provider, e))
+}
+
+/// Провайдер перевода из настроек проекта; без проекта — OpenAI
+fn translation_provider(
+    project_path: Option<&str>,
+    app_handle: &tauri::AppHandle,
+) -> Result<Box<dyn TranslationProvider>, String> {
+    let settings = match project_path {
+        Some(path) => Project::load_from_file(Path::new(path), app_handle)?.translation,
+        None => TranslationSettings::default(),
+    };
+    let api_key = match settings.auth_scheme() {
+        AuthScheme::NoAuth => None,
+        _ => Some(provider_api_key(settings.provider)?),
+    };
+    Ok(match settings.provider {
+        TranslationProviderKind::OpenAi | TranslationProviderKind::OpenAiCompatible => {
+            Box::new(ChatCompletionsProvider::new(&settings, api_key)?)
+        }
+        TranslationProviderKind::DeepL => Box::new(DeepLProvider::new(&settings, api_key)),
+    })
+}
+
 #[tauri::command]
 pub async fn transcribe_audio(
     file_path: String,
     language: Option<String>,
     prompt: Option<String>,
     app_handle: tauri::AppHandle,
+    segmentation: Option<SegmentationOptions>,
+    project_path: Option<String>,
     _cache: tauri::State<'_, Cache>,
 ) -> Result<Vec<SubtitleSegment>, String> {
     println!("Транскрибация файла: {}", file_path);
@@ -153,8 +337,12 @@ pub async fn transcribe_audio(
     let file_path_buf = Path::new(&file_path);
     let file_hash = Cache::calculate_file_hash(file_path_buf)?;
 
-    // Получаем API-ключ
-    let api_key = get_api_key()?;
+    // Движок из настроек проекта; без проекта — OpenAI
+    let settings = match &project_path {
+        Some(path) => Project::load_from_file(Path::new(path), &app_handle)?.transcription,
+        None => TranscriptionSettings::default(),
+    };
+    let backend = transcription_backend(&settings)?;
     
     // Создаём канал для отправки прогресса
     let (progress_tx, mut progress_rx) = mpsc::channel::<ProgressEvent>(10);
@@ -179,7 +367,7 @@ pub async fn transcribe_audio(
         description: "Подготовка файла".to_string() 
     }).await;
 
-    // Читаем файл / при необходимости автоматически сжимаем под лимит Whisper
+    // Файл больше лимита движка распознаём по кускам
     let _ = progress_tx.send(ProgressEvent::InProgress { 
         step: 1, 
         progress: 0.25, 
@@ -189,119 +377,63 @@ pub async fn transcribe_audio(
     let source_meta = std::fs::metadata(file_path_buf)
         .map_err(|e| format!("Ошибка чтения метаданных аудиофайла: {}", e))?;
     let source_size = source_meta.len();
+    let language_code = language.unwrap_or_else(|| "en".to_string());
+    let segmentation = segmentation.unwrap_or_default();
+
+    let segments = if backend.max_upload_bytes().is_none_or(|limit| source_size <= limit) {
+        let _ = progress_tx.send(ProgressEvent::InProgress { 
+            step: 2, 
+            progress: 0.5, 
+            description: format!("Распознавание: {}", backend.name()) 
+        }).await;
+
+        let transcript = backend
+            .transcribe(TranscriptionRequest {
+                audio_path: file_path_buf,
+                language: &language_code,
+                prompt: prompt.as_deref(),
+            })
+            .await?;
 
-    let (file_data, file_size_bytes, file_mime): (Vec<u8>, u64, String) =
-        if source_size <= WHISPER_TARGET_UPLOAD_BYTES {
-            let data = std::fs::read(&file_path)
-                .map_err(|e| format!("Ошибка чтения файла: {}", e))?;
-            (
-                data,
-                source_size,
-                infer_audio_mime(file_path_buf).to_string(),
-            )
-        } else {
-            println!(
-                "Whisper upload: исходный файл {} байт > лимита, запускаем автосжатие",
-                source_size
-            );
-            let (data, compressed_size_str, mime) = transcode_for_whisper_limit(file_path_buf).await?;
-            let compressed_size = compressed_size_str.parse::<u64>().unwrap_or(data.len() as u64);
-            (data, compressed_size, mime)
-        };
+        let _ = progress_tx.send(ProgressEvent::InProgress { 
+            step: 4, 
+            progress: 0.9, 
+            description: "Обработка результата".to_string() 
+        }).await;
 
-    // Подготавливаем запрос
-    let _ = progress_tx.send(ProgressEvent::InProgress { 
-        step: 2, 
-        progress: 0.5, 
-        description: "Отправка в OpenAI".to_string() 
-    }).await;
-    
-    let client = reqwest::Client::new();
-    
-    use reqwest::multipart;
-    
-    let file_part = multipart::Part::bytes(file_data)
-        .file_name("audio_upload")
-        .mime_str(&file_mime)
-        .map_err(|e| e.to_string())?;
-    
-    let language_code = language.clone().unwrap_or_else(|| "en".to_string());
-
-    let mut form = multipart::Form::new()
-        .text("model", "whisper-1")
-        .text("language", language_code.clone())
-        // 0 — детерминированнее, ниже шанс «фантазий» в тишине (см. доку OpenAI /temperature)
-        .text("temperature", "0")
-        .text("response_format", "verbose_json")
-        .text("timestamp_granularities[]", "segment")
-        .text("timestamp_granularities[]", "word")
-        .part("file", file_part);
-
-    let whisper_prompt_log: Option<String> = if let Some(ref prompt_text) = prompt {
-        if prompt_text.trim().is_empty() {
-            None
-        } else {
-            form = form.text("prompt", prompt_text.clone());
-            Some(prompt_text.clone())
-        }
+        let segments = sanitize_whisper_segments(transcript.segments);
+        // Сегменты Whisper бывают по 15 секунд — режем по словам на читаемые реплики
+        resegment_transcript(&segments, &transcript.words, &segmentation)
     } else {
-        None
-    };
-
-    log_debug_block(
-        "whisper: параметры и prompt",
-        &format!(
-            "model: whisper-1\n\
-language: {language_code}\n\
-temperature: 0\n\
-response_format: verbose_json\n\
-timestamp_granularities: segment, word\n\
-file: (бинарное содержимое аудио, размер {} байт)\n\
-mime: {}\n\
-\n\
-prompt (опционально):\n{}",
-            file_size_bytes,
-            file_mime,
-            whisper_prompt_log
-                .as_deref()
-                .unwrap_or("(не задан)")
-        ),
-    );
-
-    // Отправляем запрос БЕЗ обработки ошибок сети (пока упрощаем)
-    let _ = progress_tx.send(ProgressEvent::InProgress { 
-        step: 3, 
-        progress: 0.75, 
-        description: "Ожидание ответа от OpenAI".to_string() 
-    }).await;
-    
-    let res = client
-        .post("https://api.openai.com/v1/audio/transcriptions")
-        .bearer_auth(&api_key)
-        .multipart(form)
-        .send()
-        .await
-        .map_err(|e| format!("Ошибка запроса к OpenAI: {}", e))?;
-
-    if !res.status().is_success() {
-        let status = res.status();
-        let error_text = res.text().await.unwrap_or_else(|_| "Неизвестная ошибка".to_string());
-        return Err(format!("OpenAI ошибка ({}): {}", status, error_text));
-    }
+        // Сжимать весь файл под лимит нельзя: на низком битрейте распознавание разваливается
+        println!(
+            "{}: исходный файл {} байт > лимита, распознаём по кускам",
+            backend.name(),
+            source_size
+        );
+        let _ = progress_tx.send(ProgressEvent::InProgress { 
+            step: 2, 
+            progress: 0.1, 
+            description: "Поиск пауз для разбиения".to_string() 
+        }).await;
+
+        let words = transcribe_in_chunks(
+            backend.clone(),
+            file_path_buf,
+            &language_code,
+            prompt.as_deref(),
+            &progress_tx,
+        )
+        .await?;
 
-    // Парсим ответ
-    let _ = progress_tx.send(ProgressEvent::InProgress { 
-        step: 4, 
-        progress: 0.9, 
-        description: "Обработка результата".to_string() 
-    }).await;
-    
-    let response: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
-    let response_pretty =
-        serde_json::to_string_pretty(&response).unwrap_or_else(|e| e.to_string());
-    log_debug_block("whisper: ответ API (verbose_json)", &response_pretty);
+        let _ = progress_tx.send(ProgressEvent::InProgress { 
+            step: 4, 
+            progress: 0.9, 
+            description: "Обработка результата".to_string() 
+        }).await;
 
-    let segments = sanitize_whisper_segments(parse_whisper_response(response)?);
+        segmentation::resegment(&words, &segmentation)
+    };
     
     // Отправляем завершение
     let _ = progress_tx.send(ProgressEvent::Completed { 
@@ -312,107 +444,129 @@ prompt (опционально):\n{}",
     Ok(segments)
 }
 
-/// Сегментов за один запрос: иначе ответ упирается в лимит completion-токенов и JSON обрезается (EOF while parsing).
-const TRANSLATION_CHUNK_SIZE: usize = 40;
-const TRANSLATION_MAX_TOKENS: u32 = 16384;
-
-async fn translate_segments_chunk(
-    client: &reqwest::Client,
-    api_key: &str,
-    prompt: &str,
-    chunk: &[SubtitleSegment],
-    log_label: &str,
-) -> Result<Vec<crate::types::TranslationResult>, String> {
-    let segments_text = serde_json::json!({
-        "segments": chunk.iter().map(|s| {
-            serde_json::json!({
-                "id": s.id,
-                "text": s.text,
-                "start": s.start,
-                "end": s.end
-            })
-        }).collect::<Vec<_>>()
-    });
-
-    let user_content = serde_json::to_string(&segments_text).map_err(|e| e.to_string())?;
-
-    log_debug_block(
-        &format!("перевод [{log_label}]: запрос"),
-        &format!(
-            "model: gpt-5.4-mini\n\
-temperature: 0.3\n\
-max_completion_tokens: {TRANSLATION_MAX_TOKENS}\n\
-response_format: json_object\n\
-\n\
---- system ---\n\
-{prompt}\n\
-\n\
---- user (JSON, {} симв.) ---\n\
-{user_content}",
-            user_content.len()
-        ),
+/// Длинная запись: паузы → куски с перекрытием → параллельные запросы (не больше
+/// `WHISPER_CHUNK_CONCURRENCY` одновременно, до `WHISPER_CHUNK_ATTEMPTS` попыток на кусок)
+/// → слова на общей шкале времени
+async fn transcribe_in_chunks(
+    backend: Arc<dyn TranscriptionBackend>,
+    input_path: &Path,
+    language_code: &str,
+    prompt: Option<&str>,
+    progress_tx: &mpsc::Sender<ProgressEvent>,
+) -> Result<Vec<SubtitleWord>, String> {
+    let duration = media_duration_seconds(input_path).await?;
+    let silences = detect_silences(input_path).await?;
+    let chunks = plan_chunks(duration, &silences);
+    let total = chunks.len();
+    println!(
+        "{}: файл {:.0} с разбит на {} кусков (пауз найдено: {})",
+        backend.name(),
+        duration,
+        total,
+        silences.len()
     );
 
-    let res = client
-        .post("https://api.openai.com/v1/chat/completions")
-        .bearer_auth(api_key)
-        .json(&serde_json::json!({
-            "model": "gpt-5.4-mini",
-            "messages": [
-                { "role": "system", "content": prompt },
-                { "role": "user", "content": user_content }
-            ],
-            "response_format": { "type": "json_object" },
-            "temperature": 0.3,
-            "max_completion_tokens": TRANSLATION_MAX_TOKENS
-        }))
-        .send()
-        .await
-        .map_err(|e| format!("Ошибка запроса к OpenAI: {}", e))?;
+    // Объявлена раньше JoinSet: при выходе сначала прерываются задачи, потом удаляется папка
+    let chunk_dir = ChunkDir::create()?;
+    let semaphore = Arc::new(Semaphore::new(WHISPER_CHUNK_CONCURRENCY));
+    let mut tasks = JoinSet::new();
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        let semaphore = semaphore.clone();
+        let backend = backend.clone();
+        let input_path = input_path.to_path_buf();
+        let dir = chunk_dir.0.clone();
+        let language_code = language_code.to_string();
+        let prompt = prompt.map(str::to_string);
+        tasks.spawn(async move {
+            let result = async {
+                let _permit = semaphore.acquire().await.map_err(|e| e.to_string())?;
+                let chunk_path = encode_chunk(&input_path, &chunk, &dir).await?;
+                let mut attempt = 1;
+                let transcript = loop {
+                    let transcript = backend
+                        .transcribe(TranscriptionRequest {
+                            audio_path: &chunk_path,
+                            language: &language_code,
+                            prompt: prompt.as_deref(),
+                        })
+                        .await;
+                    match transcript {
+                        Err(e) if attempt < WHISPER_CHUNK_ATTEMPTS => {
+                            println!(
+                                "{}: кусок {}, попытка {} из {} не удалась: {}",
+                                backend.name(),
+                                index + 1,
+                                attempt,
+                                WHISPER_CHUNK_ATTEMPTS,
+                                e
+                            );
+                            let delay = WHISPER_CHUNK_RETRY_DELAY_SECONDS * attempt as u64;
+                            tokio::time::sleep(std::time::Duration::from_secs(delay)).await;
+                            attempt += 1;
+                        }
+                        transcript => break transcript,
+                    }
+                };
+                let _ = std::fs::remove_file(&chunk_path);
+                Ok::<_, String>(chunk_words(transcript?, &chunk))
+            }
+            .await;
+            (index, result)
+        });
+    }
 
-    if !res.status().is_success() {
-        let status = res.status();
-        let error_text = res.text().await.unwrap_or_else(|_| "Неизвестная ошибка".to_string());
-        return Err(format!("OpenAI ошибка ({}): {}", status, error_text));
+    let mut results: Vec<Option<Vec<SubtitleWord>>> = vec![None; total];
+    let mut done = 0usize;
+    while let Some(joined) = tasks.join_next().await {
+        let (index, result) = joined.map_err(|e| format!("Ошибка задачи распознавания: {}", e))?;
+        // Кусок не распознан и после повторов — остальные задачи прерываются вместе с JoinSet,
+        // их файлы удаляются вместе с `chunk_dir`
+        let words = result.map_err(|e| format!("Кусок {} из {}: {}", index + 1, total, e))?;
+        results[index] = Some(words);
+        done += 1;
+        let _ = progress_tx.send(ProgressEvent::InProgress {
+            step: 2,
+            progress: 0.1 + 0.8 * done as f64 / total as f64,
+            description: format!("Распознано кусков: {} из {}", done, total),
+        }).await;
     }
 
-    let response: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
-    let pretty = serde_json::to_string_pretty(&response).unwrap_or_else(|e| e.to_string());
-    log_debug_block(
-        &format!("перевод [{log_label}]: ответ OpenAI"),
-        &pretty,
-    );
-    parse_translation_response(response)
+    Ok(stitch_chunk_words(results.into_iter().flatten().collect()))
 }
 
+/// Сегментов за один запрос: иначе ответ упирается в лимит completion-токенов и JSON обрезается (EOF while parsing).
+const TRANSLATION_CHUNK_SIZE: usize = 40;
+
 #[tauri::command]
 pub async fn translate_batch(
     segments: Vec<SubtitleSegment>,
     target_language: String,
     glossary: Vec<GlossaryEntry>,
     style_prompt: String,
+    project_path: Option<String>,
     app_handle: tauri::AppHandle,
     cache: tauri::State<'_, Cache>,
 ) -> Result<Vec<crate::types::TranslationResult>, String> {
     println!("Перевод {} сегментов на {}...", segments.len(), target_language);
-    
+
+    let provider = translation_provider(project_path.as_deref(), &app_handle)?;
     let cache_key = Cache::generate_translation_cache_key(
         &segments,
         &glossary,
         &target_language,
         &style_prompt,
+        &provider.cache_id(),
     )?;
     
     // Проверяем кэш
     if let Some(cached) = cache.get_translation(&cache_key).await? {
         println!(
-            "Найдено в кэше перевода ({} сегментов) — запросы к OpenAI не выполняются",
-            cached.len()
+            "Найдено в кэше перевода ({} сегментов) — запросы к {} не выполняются",
+            cached.len(),
+            provider.name()
         );
         return Ok(cached);
     }
-
-    let api_key = get_api_key()?;
     
     // Создаём канал для прогресса
     let (progress_tx, mut progress_rx) = mpsc::channel::<ProgressEvent>(10);
@@ -478,7 +632,6 @@ pub async fn translate_batch(
         style_prompt
     );
 
-    let client = reqwest::Client::new();
     let chunks: Vec<&[SubtitleSegment]> = segments.chunks(TRANSLATION_CHUNK_SIZE).collect();
     let total_chunks = chunks.len().max(1);
 
@@ -494,20 +647,20 @@ pub async fn translate_batch(
             })
             .await;
 
-        let batch = translate_segments_chunk(
-            &client,
-            &api_key,
-            &prompt,
-            chunk,
-            &format!(
-                "основной {} из {}, id {}–{}",
-                i + 1,
-                total_chunks,
-                chunk.first().map(|s| s.id).unwrap_or(0),
-                chunk.last().map(|s| s.id).unwrap_or(0)
-            ),
-        )
-        .await?;
+        let batch = provider
+            .translate(TranslationRequest {
+                system_prompt: &prompt,
+                target_language: &target_language,
+                segments: chunk,
+                log_label: &format!(
+                    "основной {} из {}, id {}–{}",
+                    i + 1,
+                    total_chunks,
+                    chunk.first().map(|s| s.id).unwrap_or(0),
+                    chunk.last().map(|s| s.id).unwrap_or(0)
+                ),
+            })
+            .await?;
         for t in batch {
             merged_by_id.entry(t.id).or_insert(t.translated_text);
         }
@@ -537,21 +690,21 @@ pub async fn translate_batch(
         let sub_total = (missing.len() + actual_sz - 1) / actual_sz;
 
         for (j, subchunk) in missing.chunks(actual_sz).enumerate() {
-            let batch = translate_segments_chunk(
-                &client,
-                &api_key,
-                &prompt,
-                subchunk,
-                &format!(
-                    "добор волна{} подпакет {}/{} id {}–{}",
-                    wave_idx + 1,
-                    j + 1,
-                    sub_total,
-                    subchunk.first().map(|s| s.id).unwrap_or(0),
-                    subchunk.last().map(|s| s.id).unwrap_or(0)
-                ),
-            )
-            .await?;
+            let batch = provider
+                .translate(TranslationRequest {
+                    system_prompt: &prompt,
+                    target_language: &target_language,
+                    segments: subchunk,
+                    log_label: &format!(
+                        "добор волна{} подпакет {}/{} id {}–{}",
+                        wave_idx + 1,
+                        j + 1,
+                        sub_total,
+                        subchunk.first().map(|s| s.id).unwrap_or(0),
+                        subchunk.last().map(|s| s.id).unwrap_or(0)
+                    ),
+                })
+                .await?;
             for t in batch {
                 merged_by_id.entry(t.id).or_insert(t.translated_text);
             }
@@ -628,55 +781,6 @@ pub enum ProgressEvent {
     Error { message: String },
 }
 
-fn json_seconds(v: &serde_json::Value) -> f64 {
-    v.as_f64()
-        .or_else(|| v.as_u64().map(|n| n as f64))
-        .or_else(|| v.as_i64().map(|n| n as f64))
-        .unwrap_or(0.0)
-}
-
-fn parse_whisper_response(response: serde_json::Value) -> Result<Vec<SubtitleSegment>, String> {
-    let segments = response["segments"]
-        .as_array()
-        .ok_or("Нет сегментов в ответе".to_string())?;
-    
-    let result: Vec<SubtitleSegment> = segments
-        .iter()
-        .enumerate()
-        .map(|(i, seg)| {
-            let id = (i + 1) as u32;
-            let mut start = json_seconds(&seg["start"]);
-            let mut end = json_seconds(&seg["end"]);
-            let text = seg["text"].as_str().unwrap_or("").trim().to_string();
-
-            if let Some(words) = seg["words"].as_array() {
-                if let (Some(first), Some(last)) = (words.first(), words.last()) {
-                    let ws = json_seconds(&first["start"]);
-                    let we = json_seconds(&last["end"]);
-                    if we > ws {
-                        start = ws;
-                        end = we;
-                    }
-                }
-            }
-
-            let duration = (end - start).max(0.0);
-            
-            SubtitleSegment {
-                id,
-                start,
-                end,
-                duration,
-                text,
-                translation: None,
-                flags: None,
-            }
-        })
-        .collect();
-    
-    Ok(result)
-}
-
 /// Нормализация текста для сравнения подряд идущих сегментов (галлюцинации с повтором одной фразы).
 fn whisper_segment_dedup_key(text: &str) -> String {
     text.trim()
@@ -716,15 +820,17 @@ fn sanitize_whisper_segments(segments: Vec<SubtitleSegment>) -> Vec<SubtitleSegm
                 id: 0,
                 start: first.start,
                 end: last.end,
-                duration: (last.end - first.start).max(0.0),
+                duration: (last.end - first.start).max(Timestamp::ZERO),
                 text: first.text.clone(),
                 translation: None,
                 flags: None,
+                words: None,
+                format_meta: None,
             });
         } else {
             for k in i..j {
                 let mut s = segments[k].clone();
-                s.duration = (s.end - s.start).max(0.0);
+                s.duration = (s.end - s.start).max(Timestamp::ZERO);
                 merged.push(s);
             }
         }
@@ -737,149 +843,6 @@ fn sanitize_whisper_segments(segments: Vec<SubtitleSegment>) -> Vec<SubtitleSegm
     merged
 }
 
-fn parse_translation_response(
-    response: serde_json::Value,
-) -> Result<Vec<crate::types::TranslationResult>, String> {
-    let content = response["choices"][0]["message"]["content"]
-        .as_str()
-        .ok_or("Нет контента в ответе".to_string())?;
-
-    let normalized_content = normalize_json_text(content);
-    let parsed: serde_json::Value = serde_json::from_str(&normalized_content)
-        .map_err(|e| format!("Ошибка парсинга JSON: {}", e))?;
-
-    // 1) Пробуем извлечь напрямую/по известным ключам или рекурсивно в глубину
-    if let Some(candidate_array) = find_translation_array(&parsed) {
-        let mut results = parse_translation_items(candidate_array);
-        if !results.is_empty() {
-            results.sort_by_key(|item| item.id);
-            return Ok(results);
-        }
-    }
-
-    // 2) Fallback: объект формата { "1": "text", "2": "text" } где угодно во вложенности
-    if let Some(map_obj) = find_id_text_map_object(&parsed) {
-        let mut results = map_obj
-            .iter()
-            .filter_map(|(key, value)| {
-                let id = key.parse::<u32>().ok()?;
-                let translated_text = value.as_str()?.trim().to_string();
-                if translated_text.is_empty() {
-                    return None;
-                }
-                Some(crate::types::TranslationResult { id, translated_text })
-            })
-            .collect::<Vec<_>>();
-
-        if !results.is_empty() {
-            results.sort_by_key(|item| item.id);
-            return Ok(results);
-        }
-    }
-
-    Err(format!(
-        "Не удалось распознать формат перевода от OpenAI. Ответ: {}",
-        normalized_content.chars().take(400).collect::<String>()
-    ))
-}
-
-fn json_u32_from_value(v: &serde_json::Value) -> Option<u32> {
-    if let Some(n) = v.as_u64() {
-        return u32::try_from(n).ok();
-    }
-    if let Some(n) = v.as_i64() {
-        return u32::try_from(n).ok();
-    }
-    v.as_str().and_then(|s| s.trim().parse().ok())
-}
-
-fn normalize_json_text(content: &str) -> String {
-    let trimmed = content.trim();
-    if trimmed.starts_with("```") {
-        let without_fence = trimmed
-            .trim_start_matches("```json")
-            .trim_start_matches("```")
-            .trim_end_matches("```")
-            .trim();
-        without_fence.to_string()
-    } else {
-        trimmed.to_string()
-    }
-}
-
-fn parse_translation_items(items: &[serde_json::Value]) -> Vec<crate::types::TranslationResult> {
-    items
-        .iter()
-        .filter_map(|item| {
-            let id = item.get("id").and_then(json_u32_from_value).unwrap_or(0);
-            let translated_text = item
-                .get("translated_text")
-                .or_else(|| item.get("translatedText"))
-                .or_else(|| item.get("translation"))
-                .or_else(|| item.get("translated"))
-                .or_else(|| item.get("text"))
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .trim()
-                .to_string();
-
-            if id == 0 || translated_text.is_empty() {
-                None
-            } else {
-                Some(crate::types::TranslationResult { id, translated_text })
-            }
-        })
-        .collect()
-}
-
-fn find_translation_array(value: &serde_json::Value) -> Option<&Vec<serde_json::Value>> {
-    if let Some(arr) = value.as_array() {
-        return Some(arr);
-    }
-
-    if let Some(obj) = value.as_object() {
-        let priority_keys = ["translations", "results", "items", "data", "output", "response"];
-        for key in priority_keys {
-            if let Some(v) = obj.get(key) {
-                if let Some(arr) = v.as_array() {
-                    return Some(arr);
-                }
-                if let Some(found) = find_translation_array(v) {
-                    return Some(found);
-                }
-            }
-        }
-
-        for v in obj.values() {
-            if let Some(found) = find_translation_array(v) {
-                return Some(found);
-            }
-        }
-    }
-
-    None
-}
-
-fn find_id_text_map_object(
-    value: &serde_json::Value,
-) -> Option<&serde_json::Map<String, serde_json::Value>> {
-    if let Some(obj) = value.as_object() {
-        let maybe_map = obj
-            .iter()
-            .all(|(k, v)| k.parse::<u32>().is_ok() && v.as_str().is_some());
-        if maybe_map && !obj.is_empty() {
-            return Some(obj);
-        }
-
-        for v in obj.values() {
-            if let Some(found) = find_id_text_map_object(v) {
-                return Some(found);
-            }
-        }
-    }
-    None
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GlossaryTerm {
     pub source: String,
@@ -979,8 +942,7 @@ fn looks_untranslated_term(source: &str, target: &str) -> bool {
 }
 
 async fn localize_untranslated_glossary_terms(
-    client: &reqwest::Client,
-    api_key: &str,
+    provider: &dyn TranslationProvider,
     target_lang: &str,
     terms: &[GlossaryTerm],
 ) -> Result<HashMap<String, String>, String> {
@@ -1011,37 +973,16 @@ async fn localize_untranslated_glossary_terms(
         target_lang
     );
 
-    let res = client
-        .post("https://api.openai.com/v1/chat/completions")
-        .bearer_auth(api_key)
-        .json(&serde_json::json!({
-            "model": "gpt-5.4-mini",
-            "messages": [
-                { "role": "system", "content": system_prompt },
-                { "role": "user", "content": user_content }
-            ],
-            "response_format": { "type": "json_object" },
-            "temperature": 0.1,
-            "max_completion_tokens": 4096
-        }))
-        .send()
+    let content = provider
+        .complete_json(ChatRequest {
+            system_prompt: &system_prompt,
+            user_content: &user_content,
+            temperature: 0.1,
+            max_tokens: 4096,
+        })
         .await
-        .map_err(|e| format!("Ошибка запроса к OpenAI (локализация глоссария): {}", e))?;
-
-    if !res.status().is_success() {
-        let status = res.status();
-        let error_text = res.text().await.unwrap_or_else(|_| "Неизвестная ошибка".to_string());
-        return Err(format!(
-            "OpenAI ошибка при локализации глоссария ({}): {}",
-            status, error_text
-        ));
-    }
-
-    let response: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
-    let content = response["choices"][0]["message"]["content"]
-        .as_str()
-        .ok_or("Нет контента в ответе локализации глоссария".to_string())?;
-    let parsed: serde_json::Value = serde_json::from_str(content)
+        .map_err(|e| format!("Ошибка локализации глоссария: {}", e))?;
+    let parsed: serde_json::Value = serde_json::from_str(&content)
         .map_err(|e| format!("Ошибка парсинга JSON локализации глоссария: {}", e))?;
 
     let arr_ref = parsed
@@ -1065,7 +1006,8 @@ async fn localize_untranslated_glossary_terms(
 pub async fn auto_generate_glossary(
     segments: Vec<SubtitleSegment>,
     options: Option<AutoGlossaryOptions>,
-    _app_handle: tauri::AppHandle,
+    project_path: Option<String>,
+    app_handle: tauri::AppHandle,
 ) -> Result<Vec<GlossaryTerm>, String> {
     println!("Автоматическое создание глоссария из {} сегментов", segments.len());
 
@@ -1086,7 +1028,7 @@ pub async fn auto_generate_glossary(
         return Ok(Vec::new());
     }
 
-    let api_key = get_api_key()?;
+    let provider = translation_provider(project_path.as_deref(), &app_handle)?;
 
     let max_terms = options.max_terms.clamp(5, 80);
     let target_lang = options.target_language.trim();
@@ -1143,32 +1085,15 @@ pub async fn auto_generate_glossary(
         )
     };
 
-    let client = reqwest::Client::new();
-    let res = client
-        .post("https://api.openai.com/v1/chat/completions")
-        .bearer_auth(&api_key)
-        .json(&serde_json::json!({
-            "model": "gpt-5.4-mini",
-            "messages": [
-                { "role": "system", "content": system_prompt },
-                { "role": "user", "content": user_content }
-            ],
-            "response_format": { "type": "json_object" },
-            "temperature": 0.2,
-            "max_completion_tokens": 8192
-        }))
-        .send()
-        .await
-        .map_err(|e| format!("Ошибка запроса к OpenAI: {}", e))?;
-
-    if !res.status().is_success() {
-        let status = res.status();
-        let error_text = res.text().await.unwrap_or_else(|_| "Неизвестная ошибка".to_string());
-        return Err(format!("OpenAI ошибка ({}): {}", status, error_text));
-    }
-
-    let response: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
-    let mut glossary_terms = parse_glossary_response(response)?;
+    let content = provider
+        .complete_json(ChatRequest {
+            system_prompt: &system_prompt,
+            user_content: &user_content,
+            temperature: 0.2,
+            max_tokens: 8192,
+        })
+        .await?;
+    let mut glossary_terms = parse_glossary_response(&content)?;
 
     let untranslated: Vec<GlossaryTerm> = glossary_terms
         .iter()
@@ -1180,7 +1105,7 @@ pub async fn auto_generate_glossary(
             "[auto_glossary] до-локализация {} терминов с одинаковыми source/target",
             untranslated.len()
         );
-        match localize_untranslated_glossary_terms(&client, &api_key, target_lang, &untranslated).await {
+        match localize_untranslated_glossary_terms(provider.as_ref(), target_lang, &untranslated).await {
             Ok(fixes) => {
                 for term in &mut glossary_terms {
                     if let Some(new_target) = fixes.get(&term.source) {
@@ -1205,11 +1130,7 @@ pub async fn auto_generate_glossary(
     Ok(glossary_terms)
 }
 
-fn parse_glossary_response(response: serde_json::Value) -> Result<Vec<GlossaryTerm>, String> {
-    let content = response["choices"][0]["message"]["content"]
-        .as_str()
-        .ok_or("Нет контента в ответе".to_string())?;
-    
+fn parse_glossary_response(content: &str) -> Result<Vec<GlossaryTerm>, String> {
     let parsed: serde_json::Value = serde_json::from_str(content)
         .map_err(|e| format!("Ошибка парсинга JSON: {}", e))?;
     
@@ -1242,4 +1163,6 @@ fn parse_glossary_response(response: serde_json::Value) -> Result<Vec<GlossaryTe
 }
 
 const KEYRING_SERVICE: &str = "subtitle-studio";
-const KEYRING_USER: &str = "openai-api-key";
\ No newline at end of file
+const KEYRING_USER: &str = "openai-api-key";
+const KEYRING_USER_COMPATIBLE: &str = "openai-compatible-api-key";
+const KEYRING_USER_DEEPL: &str = "deepl-api-key";
\ No newline at end of file
@@ -2,7 +2,8 @@ use tauri::Manager;
 use std::path::Path;
 use std::fs;
 use crate::commands::audio::media_duration_seconds;
-use crate::project::{Project, ProjectFile, ProjectType, SubtitleSegment};
+use crate::commands::media::{self, video_frame_rate};
+use crate::project::{Project, ProjectFile, ProjectType, SubtitleSegment, SegmentFormatMeta, SubtitleFileMeta, Timestamp};
 use crate::cache::Cache;
 use crate::types::RecentProject;
 use crate::subtitle_parser;
@@ -91,6 +92,11 @@ pub async fn import_media(
     } else {
         None
     };
+    let frame_rate = if matches!(file_type, ProjectType::Video) {
+        video_frame_rate(&dest_path).await.ok()
+    } else {
+        None
+    };
 
     let new_id = uuid::Uuid::new_v4().to_string();
     let project_file = ProjectFile {
@@ -101,6 +107,8 @@ pub async fn import_media(
         duration,
         subtitle_segments: None,
         linked_file_id: None,
+        subtitle_meta: None,
+        frame_rate,
         created_at: chrono::Utc::now().to_rfc3339(),
         updated_at: chrono::Utc::now().to_rfc3339(),
     };
@@ -134,14 +142,245 @@ pub async fn import_media(
         .ok_or_else(|| "Внутренняя ошибка: импортированный файл не найден в проекте".to_string())
 }
 
+/// Разбор извлечённой из видео дорожки; дорожка без реплик — ошибка.
+fn parse_extracted_subtitles(
+    path: &Path,
+    frame_rate: Option<subtitle_parser::framerate::FrameRate>,
+) -> Result<subtitle_parser::ParsedSubtitles, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Ошибка чтения файла: {}", e))?;
+    let decoded = subtitle_parser::encoding::decode(&bytes, None)?;
+    let format = subtitle_parser::detect_format(path)?;
+    let parsed = subtitle_parser::parse_subtitles(&decoded.text, format, frame_rate)?;
+    if parsed.segments.is_empty() {
+        return Err("Дорожка субтитров не содержит реплик".to_string());
+    }
+    Ok(parsed)
+}
+
+/// Извлекает встроенную текстовую дорожку субтитров из видео проекта, разбирает её
+/// и добавляет в проект файлом субтитров, связанным с этим видео.
+#[tauri::command]
+pub async fn import_embedded_subtitles(
+    project_path: String,
+    video_file_id: String,
+    stream_index: i32,
+    app_handle: tauri::AppHandle,
+) -> Result<ProjectFile, String> {
+    let project_path_buf = Path::new(&project_path);
+    let mut project = Project::load_from_file(project_path_buf, &app_handle)?;
+
+    let video = project
+        .files
+        .iter()
+        .find(|f| f.id == video_file_id && f.file_type == ProjectType::Video)
+        .ok_or("Видео не найдено в проекте")?;
+    let video_path = project_path_buf.join(&video.path);
+    let frame_rate = video.frame_rate;
+
+    let tracks = media::list_subtitle_tracks(video_path.to_string_lossy().to_string()).await?;
+    let track = tracks
+        .iter()
+        .find(|t| t.index == stream_index)
+        .ok_or_else(|| format!("В видео нет дорожки субтитров #{}", stream_index))?;
+    let (extension, ffmpeg_codec) = media::subtitle_extraction_target(&track.codec_name).ok_or_else(|| {
+        format!(
+            "Дорожка #{} ({}) — не текстовые субтитры, извлечь её нельзя",
+            stream_index, track.codec_name
+        )
+    })?;
+
+    // episode.eng.3.srt — язык и номер дорожки, чтобы несколько дорожек не перезаписывали друг друга
+    let stem = Path::new(&video.name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "subtitles".to_string());
+    let base_name = match &track.language {
+        Some(lang) => format!("{}.{}.{}", stem, lang, stream_index),
+        None => format!("{}.{}", stem, stream_index),
+    };
+    // Повторный импорт той же дорожки — episode.eng.3_2.srt, а не перезапись файла,
+    // на который уже ссылается проект
+    let subtitles_dir = project_path_buf.join("subtitles");
+    let mut file_name = format!("{}.{}", base_name, extension);
+    let mut n = 2;
+    while subtitles_dir.join(&file_name).exists()
+        || project.files.iter().any(|f| f.path == format!("subtitles/{}", file_name))
+    {
+        file_name = format!("{}_{}.{}", base_name, n, extension);
+        n += 1;
+    }
+    let dest_path = subtitles_dir.join(&file_name);
+
+    let extracted = match media::extract_subtitle_stream(&video_path, stream_index, ffmpeg_codec, &dest_path).await {
+        Ok(()) => parse_extracted_subtitles(&dest_path, frame_rate),
+        Err(e) => Err(e),
+    };
+    // Файл, который не попадёт в проект, не остаётся в папке субтитров
+    let parsed = extracted.inspect_err(|_| {
+        let _ = fs::remove_file(&dest_path);
+    })?;
+    println!(
+        "Из дорожки #{} извлечено {} сегментов, замечаний парсера: {}",
+        stream_index,
+        parsed.segments.len(),
+        parsed.diagnostics.len()
+    );
+
+    let new_id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+    project.files.push(ProjectFile {
+        id: new_id.clone(),
+        name: file_name.clone(),
+        file_type: ProjectType::Subtitle,
+        path: format!("subtitles/{}", file_name),
+        duration: None,
+        subtitle_segments: Some(parsed.segments),
+        linked_file_id: None,
+        subtitle_meta: parsed.metadata,
+        frame_rate,
+        created_at: now.clone(),
+        updated_at: now.clone(),
+    });
+
+    // Прежний партнёр видео остаётся в проекте, но без связи
+    let previous = project
+        .files
+        .iter()
+        .find(|f| f.id == video_file_id)
+        .and_then(|f| f.linked_file_id.clone());
+    if let Some(previous) = previous {
+        if let Some(partner) = project.files.iter_mut().find(|f| f.id == previous) {
+            partner.linked_file_id = None;
+            partner.updated_at = now.clone();
+        }
+    }
+    link_video_subtitle(&mut project, &video_file_id, &new_id);
+    project.updated_at = now;
+    project.save_to_file(&app_handle)?;
+
+    println!("Субтитры '{}' извлечены из видео и добавлены в проект", file_name);
+    project
+        .files
+        .iter()
+        .find(|f| f.id == new_id)
+        .cloned()
+        .ok_or_else(|| "Внутренняя ошибка: импортированный файл не найден в проекте".to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ExportOptions {
+    /// Кодировка выходного файла (`utf-8`, `utf-8-bom`, `windows-1251`, `utf-16le`…); по умолчанию UTF-8
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// Двуязычный экспорт: оригинал и перевод в одном файле
+    #[serde(default)]
+    pub bilingual: Option<BilingualOptions>,
+    /// Какой текст выгружать: оригинал, перевод или перевод с подстановкой оригинала
+    #[serde(default)]
+    pub text_source: TextSource,
+    /// Разрешить экспорт с подстановкой оригинала, если у части реплик нет перевода
+    #[serde(default)]
+    pub allow_untranslated: bool,
+    /// Для `Translation`: текст вместо отсутствующего перевода (например `[нет перевода]`);
+    /// без него экспорт с непереведёнными репликами завершается ошибкой
+    #[serde(default)]
+    pub untranslated_marker: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextSource {
+    /// Только исходный текст
+    Original,
+    /// Только перевод
+    Translation,
+    /// Перевод, а где его нет — исходный текст
+    #[default]
+    Fallback,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportResult {
+    pub output_path: String,
+    /// Реплики без перевода, выгруженные с исходным текстом или пометкой
+    pub fallback_segment_ids: Vec<u32>,
+    /// Реплики SCC, выведенные позже своего начала
+    pub delayed_segments: Vec<DelayedSegment>,
+    /// Реплики EBU STL, символы которых не нашлись в кодовой таблице
+    pub unmapped_segments: Vec<UnmappedSegment>,
+}
+
+/// Реплика, которую формат не смог вывести вовремя (в SCC эфир занят предыдущим титром).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DelayedSegment {
+    pub id: u32,
+    /// Задержка в кадрах 29.97
+    pub delay_frames: i64,
+}
+
+/// Реплика, в которой символы вне кодовой таблицы формата заменены на `?`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UnmappedSegment {
+    pub id: u32,
+    /// Заменённые символы, без повторов
+    pub characters: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BilingualLayout {
+    /// Обе строки в одной реплике
+    #[default]
+    SingleCue,
+    /// Две дорожки со своими стилями в одном ASS-файле
+    SeparateTracks,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BilingualOrder {
+    /// Оригинал сверху, перевод под ним
+    #[default]
+    OriginalFirst,
+    TranslationFirst,
+}
+
+/// Оформление одного языка в двуязычном экспорте (SRT, WebVTT, ASS).
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BilingualStyle {
+    #[serde(default)]
+    pub font_name: Option<String>,
+    #[serde(default)]
+    pub font_size: Option<f64>,
+    /// Цвет `#RRGGBB`
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub italic: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BilingualOptions {
+    #[serde(default)]
+    pub layout: BilingualLayout,
+    #[serde(default)]
+    pub order: BilingualOrder,
+    #[serde(default)]
+    pub original_style: BilingualStyle,
+    #[serde(default)]
+    pub translation_style: BilingualStyle,
+}
+
+/// Экспорт файла субтитров проекта в заданный формат. В ASS, импортированном из ASS/SSA,
+/// перевод пишется после начальных тегов исходной строки: теги внутри строки (курсив
+/// отдельного слова и т.п.) в перевод не переносятся.
 #[tauri::command]
 pub async fn export_subtitles(
     project_path: String,
     file_id: String,
     format: String,
     output_path: String,
+    options: Option<ExportOptions>,
     _app_handle: tauri::AppHandle,
-) -> Result<String, String> {
+) -> Result<ExportResult, String> {
+    let options = options.unwrap_or_default();
     let project_path_buf = Path::new(&project_path);
     let project = Project::load_from_file(project_path_buf, &_app_handle)?;
     
@@ -150,25 +389,347 @@ pub async fn export_subtitles(
         .find(|f| f.id == file_id)
         .ok_or("Файл не найден в проекте")?;
     
+    export_project_file(&project, file, &format, output_path, &options)
+}
+
+/// Записывает один файл субтитров проекта в заданном формате.
+pub(crate) fn export_project_file(
+    project: &Project,
+    file: &ProjectFile,
+    format: &str,
+    output_path: String,
+    options: &ExportOptions,
+) -> Result<ExportResult, String> {
     let segments = file.subtitle_segments
         .as_ref()
         .ok_or("Сегменты субтитров отсутствуют")?;
     
-    let content = match format.as_str() {
-        "srt" => generate_srt(segments),
-        "vtt" => generate_vtt(segments),
-        "txt" => generate_txt(segments),
-        _ => return Err(format!("Неподдерживаемый формат: {}", format)),
+    if options.bilingual.is_some() && options.text_source == TextSource::Original {
+        return Err("Двуязычный экспорт несовместим с выгрузкой только оригинала".to_string());
+    }
+    let (resolved_segments, fallback_segment_ids) = resolve_text_source(segments, options)?;
+    let segments = &resolved_segments;
+    
+    // Двуязычный экспорт для всех форматов, кроме ASS, — перевод заменяется парой строк
+    let bilingual_segments;
+    let segments = match &options.bilingual {
+        Some(bilingual) if format != "ass" => {
+            if bilingual.layout == BilingualLayout::SeparateTracks {
+                return Err("Две дорожки в одном файле поддерживаются только для ASS".to_string());
+            }
+            bilingual_segments = combine_bilingual(segments, bilingual, format);
+            &bilingual_segments
+        }
+        _ => segments,
+    };
+    let vtt_meta = match (&options.bilingual, format) {
+        (Some(bilingual), "vtt") => Some(bilingual_vtt_header(file.subtitle_meta.as_ref(), bilingual)),
+        _ => file.subtitle_meta.clone(),
+    };
+    
+    let mut delayed_segments = Vec::new();
+    let mut unmapped_segments = Vec::new();
+    // EBU STL — двоичный формат со своими кодовыми таблицами, кодировка экспорта к нему не применяется
+    let bytes = if format == "stl" {
+        let (bytes, unmapped) = generate_stl(segments, file.subtitle_meta.as_ref(), project);
+        unmapped_segments = unmapped;
+        bytes
+    } else {
+        let content = match format {
+            "srt" => generate_srt(segments),
+            "vtt" => generate_vtt(segments, vtt_meta.as_ref()),
+            "txt" => generate_txt(segments),
+            "sub" => generate_microdvd(segments, project_frame_rate(project, file)),
+            "scc" => {
+                let (content, delayed) = generate_scc(segments)?;
+                delayed_segments = delayed;
+                content
+            }
+            "ass" => generate_ass(segments, file.subtitle_meta.as_ref(), &file.name, options.bilingual.as_ref()),
+            "ttml" => generate_ttml(segments, file.subtitle_meta.as_ref(), &project.target_language, false),
+            "ebu-tt-d" => generate_ttml(segments, file.subtitle_meta.as_ref(), &project.target_language, true),
+            _ => return Err(format!("Неподдерживаемый формат: {}", format)),
+        };
+        
+        match options.encoding.as_deref() {
+            Some(label) => subtitle_parser::encoding::encode(&content, label)?,
+            None => content.into_bytes(),
+        }
     };
     
     if let Some(parent) = Path::new(&output_path).parent() {
         fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
     
-    fs::write(&output_path, content).map_err(|e| e.to_string())?;
+    fs::write(&output_path, bytes).map_err(|e| e.to_string())?;
     
-    println!("Субтитры экспортированы: {}", output_path);
-    Ok(output_path)
+    if fallback_segment_ids.is_empty() {
+        println!("Субтитры экспортированы: {}", output_path);
+    } else {
+        println!(
+            "Субтитры экспортированы: {} (без перевода: {} реплик)",
+            output_path,
+            fallback_segment_ids.len()
+        );
+    }
+    if !delayed_segments.is_empty() {
+        println!(
+            "SCC: {} реплик выведены позже начала — эфир занят предыдущим титром",
+            delayed_segments.len()
+        );
+    }
+    if !unmapped_segments.is_empty() {
+        println!(
+            "EBU STL: в {} репликах символы вне кодовой таблицы заменены на «?»",
+            unmapped_segments.len()
+        );
+    }
+    Ok(ExportResult { output_path, fallback_segment_ids, delayed_segments, unmapped_segments })
+}
+
+const DEFAULT_BATCH_TEMPLATE: &str = "{project}_{video_stem}_{lang}.{ext}";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchExportOptions {
+    /// Форматы, как в `export_subtitles`: `srt`, `vtt`, `ass`, `ttml`, …
+    pub formats: Vec<String>,
+    /// Шаблон имени: `{project}`, `{file}`, `{video_stem}`, `{lang}`, `{format}`, `{ext}`;
+    /// по умолчанию `{project}_{video_stem}_{lang}.{ext}`. Если имя не оканчивается расширением
+    /// формата, оно дописывается.
+    #[serde(default)]
+    pub name_template: Option<String>,
+    /// Параметры экспорта для каждого файла (кодировка, источник текста и т.п.)
+    #[serde(default)]
+    pub export: Option<ExportOptions>,
+    /// Упаковать всё в один zip-архив вместо отдельных файлов
+    #[serde(default)]
+    pub zip: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchExportItem {
+    pub file_id: String,
+    pub file_name: String,
+    pub format: String,
+    /// Путь к файлу (внутри архива — имя записи)
+    pub output_path: Option<String>,
+    pub error: Option<String>,
+    pub fallback_segment_ids: Vec<u32>,
+    pub delayed_segments: Vec<DelayedSegment>,
+    pub unmapped_segments: Vec<UnmappedSegment>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchExportResult {
+    pub items: Vec<BatchExportItem>,
+    pub archive_path: Option<String>,
+}
+
+/// Экспорт всех файлов субтитров проекта во все указанные форматы. Ошибка одного файла
+/// не останавливает остальные — она возвращается в его элементе результата.
+#[tauri::command]
+pub async fn batch_export_subtitles(
+    project_path: String,
+    output_dir: String,
+    options: BatchExportOptions,
+    app_handle: tauri::AppHandle,
+) -> Result<BatchExportResult, String> {
+    if options.formats.is_empty() {
+        return Err("Не выбрано ни одного формата".to_string());
+    }
+    let project = Project::load_from_file(Path::new(&project_path), &app_handle)?;
+    let export_options = options.export.unwrap_or_default();
+    let template = options.name_template.as_deref().unwrap_or(DEFAULT_BATCH_TEMPLATE);
+
+    // Для архива файлы сначала пишутся во временную папку рядом с ним
+    let output_dir = Path::new(&output_dir);
+    let staging_dir = if options.zip {
+        output_dir.join(format!(".{}_export_{}", sanitize_file_name(&project.name), uuid::Uuid::new_v4()))
+    } else {
+        output_dir.to_path_buf()
+    };
+    fs::create_dir_all(&staging_dir).map_err(|e| e.to_string())?;
+
+    let mut used_names = std::collections::HashSet::new();
+    let mut items = Vec::new();
+    for file in project.files.iter().filter(|f| f.file_type == ProjectType::Subtitle) {
+        for format in &options.formats {
+            let format = format.to_lowercase();
+            let base_name = batch_file_name(template, &project, file, &format, &export_options);
+            let base_path = Path::new(&base_name);
+            let stem = base_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            let ext = base_path.extension().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            // Одинаковые имена (например, шаблон без {file}) не должны перезаписывать друг друга,
+            // а без архива — и файлы, уже лежащие в папке
+            let mut name = base_name;
+            let mut n = 2;
+            while !used_names.insert(name.clone()) || (!options.zip && staging_dir.join(&name).exists()) {
+                name = format!("{}_{}.{}", stem, n, ext);
+                n += 1;
+            }
+
+            let output_path = staging_dir.join(&name).to_string_lossy().to_string();
+            let item = match export_project_file(&project, file, &format, output_path, &export_options) {
+                Ok(result) => BatchExportItem {
+                    file_id: file.id.clone(),
+                    file_name: file.name.clone(),
+                    format,
+                    output_path: Some(if options.zip { name } else { result.output_path }),
+                    error: None,
+                    fallback_segment_ids: result.fallback_segment_ids,
+                    delayed_segments: result.delayed_segments,
+                    unmapped_segments: result.unmapped_segments,
+                },
+                Err(error) => {
+                    println!("Экспорт '{}' в {} не удался: {}", file.name, format, error);
+                    BatchExportItem {
+                        file_id: file.id.clone(),
+                        file_name: file.name.clone(),
+                        format,
+                        output_path: None,
+                        error: Some(error),
+                        fallback_segment_ids: Vec::new(),
+                        delayed_segments: Vec::new(),
+                        unmapped_segments: Vec::new(),
+                    }
+                }
+            };
+            items.push(item);
+        }
+    }
+
+    let mut archive_path = None;
+    if options.zip {
+        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        let archive = output_dir.join(format!("{}_subtitles_{}.zip", sanitize_file_name(&project.name), timestamp));
+        let result = zip_directory(&staging_dir, &archive);
+        let _ = fs::remove_dir_all(&staging_dir);
+        result?;
+        archive_path = Some(archive.to_string_lossy().to_string());
+    }
+
+    let failed = items.iter().filter(|i| i.error.is_some()).count();
+    println!(
+        "Пакетный экспорт: {} файлов записано, {} с ошибкой",
+        items.len() - failed,
+        failed
+    );
+    Ok(BatchExportResult { items, archive_path })
+}
+
+fn zip_directory(dir: &Path, archive: &Path) -> Result<(), String> {
+    let archive_file = std::fs::File::create(archive)
+        .map_err(|e| format!("Ошибка создания архива: {}", e))?;
+    let mut zip = zip::ZipWriter::new(archive_file);
+    let zip_opts = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    add_directory_to_zip(&mut zip, dir, dir, &zip_opts, true)?;
+    zip.finish().map_err(|e| format!("Ошибка завершения архива: {}", e))?;
+    Ok(())
+}
+
+/// Имя выходного файла по шаблону пакетного экспорта.
+fn batch_file_name(
+    template: &str,
+    project: &Project,
+    file: &ProjectFile,
+    format: &str,
+    options: &ExportOptions,
+) -> String {
+    let stem = |name: &str| {
+        Path::new(name)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| name.to_string())
+    };
+    let file_stem = stem(&file.name);
+    let video_stem = file
+        .linked_file_id
+        .as_ref()
+        .and_then(|id| project.files.iter().find(|f| &f.id == id))
+        .map(|video| stem(&video.name))
+        .unwrap_or_else(|| file_stem.clone());
+    let lang = match options.text_source {
+        TextSource::Original => "orig".to_string(),
+        _ => project.target_language.clone(),
+    };
+    let ext = match format {
+        "ttml" | "ebu-tt-d" => "xml",
+        other => other,
+    };
+
+    let name = template
+        .replace("{project}", &project.name)
+        .replace("{file}", &file_stem)
+        .replace("{video_stem}", &video_stem)
+        .replace("{lang}", &lang)
+        .replace("{format}", format)
+        .replace("{ext}", ext);
+    // Шаблон без `{ext}` — расширение всё равно нужно, иначе файлы разных форматов не различить
+    let mut name = sanitize_file_name(&name);
+    if !name.to_lowercase().ends_with(&format!(".{}", ext)) {
+        name.push('.');
+        name.push_str(ext);
+    }
+    name
+}
+
+/// Убирает символы, недопустимые в именах файлов (в том числе разделители путей).
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '_' } else { c })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Подготавливает поле `translation` под выбранный источник текста, чтобы генераторы
+/// форматов могли по-прежнему брать `translation`, а при его отсутствии — `text`.
+/// Возвращает ID реплик, у которых перевода нет.
+fn resolve_text_source(
+    segments: &[SubtitleSegment],
+    options: &ExportOptions,
+) -> Result<(Vec<SubtitleSegment>, Vec<u32>), String> {
+    let has_translation = |seg: &SubtitleSegment| seg.translation.as_deref().is_some_and(|t| !t.trim().is_empty());
+    let untranslated: Vec<u32> = segments.iter().filter(|s| !has_translation(s)).map(|s| s.id).collect();
+
+    let describe = |ids: &[u32]| {
+        let shown: Vec<String> = ids.iter().take(10).map(|id| id.to_string()).collect();
+        let more = if ids.len() > 10 { "…" } else { "" };
+        format!("{} реплик без перевода (№ {}{})", ids.len(), shown.join(", "), more)
+    };
+
+    let mut resolved = segments.to_vec();
+    match options.text_source {
+        TextSource::Original => {
+            for seg in &mut resolved {
+                seg.translation = None;
+            }
+            Ok((resolved, Vec::new()))
+        }
+        TextSource::Translation if untranslated.is_empty() => Ok((resolved, Vec::new())),
+        TextSource::Translation => {
+            let marker = options
+                .untranslated_marker
+                .as_ref()
+                .ok_or_else(|| format!("Экспорт только перевода невозможен: {}", describe(&untranslated)))?;
+            for seg in resolved.iter_mut().filter(|s| untranslated.contains(&s.id)) {
+                seg.translation = Some(marker.clone());
+            }
+            Ok((resolved, untranslated))
+        }
+        TextSource::Fallback => {
+            if !untranslated.is_empty() && !options.allow_untranslated {
+                return Err(format!(
+                    "{}. Разрешите экспорт с исходным текстом или выберите выгрузку оригинала",
+                    describe(&untranslated)
+                ));
+            }
+            for seg in resolved.iter_mut().filter(|s| untranslated.contains(&s.id)) {
+                seg.translation = None;
+            }
+            Ok((resolved, untranslated))
+        }
+    }
 }
 
 #[tauri::command]
@@ -238,7 +799,7 @@ fn is_video_file(path: &Path) -> bool {
 
 fn is_subtitle_file(path: &Path) -> bool {
     let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
-    matches!(ext.to_lowercase().as_str(), "srt" | "vtt" | "ass" | "ssa")
+    matches!(ext.to_lowercase().as_str(), "srt" | "vtt" | "ass" | "ssa" | "ttml" | "dfxp" | "stl" | "sub" | "scc")
 }
 
 fn video_has_subtitle_partner(project: &Project, video_id: &str) -> bool {
@@ -280,7 +841,7 @@ fn link_video_subtitle(project: &mut Project, video_id: &str, subtitle_id: &str)
 }
 
 // Функции генерации субтитров
-fn generate_srt(segments: &[SubtitleSegment]) -> String {
+pub(crate) fn generate_srt(segments: &[SubtitleSegment]) -> String {
     let mut result = String::new();
     for (i, seg) in segments.iter().enumerate() {
         let index = i + 1;
@@ -294,15 +855,57 @@ fn generate_srt(segments: &[SubtitleSegment]) -> String {
     result
 }
 
-fn generate_vtt(segments: &[SubtitleSegment]) -> String {
-    let mut result = "WEBVTT\n\n".to_string();
+pub(crate) fn generate_vtt(segments: &[SubtitleSegment], meta: Option<&SubtitleFileMeta>) -> String {
+    let header = match meta {
+        Some(SubtitleFileMeta::Vtt(header)) => Some(header),
+        _ => None,
+    };
+
+    let mut result = "WEBVTT".to_string();
+    if let Some(h) = header {
+        result.push_str(&h.header_text);
+        for line in &h.header_lines {
+            result.push('\n');
+            result.push_str(line);
+        }
+    }
+    result.push_str("\n\n");
+    if let Some(h) = header {
+        for block in &h.blocks {
+            result.push_str(&format!("{}\n\n", block));
+        }
+    }
+
     for seg in segments {
         let start = format_time_vtt(seg.start);
         let end = format_time_vtt(seg.end);
-        
-        result.push_str(&format!("{} --> {}\n", start, end));
+
+        let cue = match &seg.format_meta {
+            Some(SegmentFormatMeta::Vtt(cue)) => Some(cue),
+            _ => None,
+        };
+        if let Some(cue) = cue {
+            for note in &cue.notes_before {
+                result.push_str(&format!("{}\n\n", note));
+            }
+            if let Some(identifier) = &cue.identifier {
+                result.push_str(&format!("{}\n", identifier));
+            }
+        }
+
+        result.push_str(&format!("{} --> {}", start, end));
+        if let Some(settings) = cue.and_then(|c| c.settings.as_ref()) {
+            result.push_str(&format!(" {}", settings));
+        }
+        result.push('\n');
         result.push_str(&format!("{}\n\n", seg.translation.as_ref().unwrap_or(&seg.text)));
     }
+
+    if let Some(h) = header {
+        for note in &h.trailing_notes {
+            result.push_str(&format!("{}\n\n", note));
+        }
+    }
     result
 }
 
@@ -319,26 +922,623 @@ fn generate_txt(segments: &[SubtitleSegment]) -> String {
         .collect()
 }
 
-fn format_time_srt(seconds: f64) -> String {
-    let hours = (seconds / 3600.0) as u32;
-    let minutes = ((seconds % 3600.0) / 60.0) as u32;
-    let secs = (seconds % 60.0) as u32;
-    let millis = ((seconds % 1.0) * 1000.0) as u32;
+/// ASS с секцией `[V4+ Styles]`. Если файл был импортирован из ASS/SSA — возвращаем
+/// исходный заголовок, стили и теги строк, подставляя переведённый текст.
+/// При `bilingual` оригинал и перевод идут либо одной строкой `Dialogue`, либо двумя
+/// со стилями `Original`/`Translation` (рендерер ставит их друг над другом).
+/// Перевод получает только начальные теги строки; включённые ими `\i1`/`\b1`/`\u1`/`\s1`
+/// закрываются в конце перевода, а теги из середины исходной строки теряются.
+pub(crate) fn generate_ass(
+    segments: &[SubtitleSegment],
+    meta: Option<&SubtitleFileMeta>,
+    title: &str,
+    bilingual: Option<&BilingualOptions>,
+) -> String {
+    use crate::subtitle_parser::ass::{self, AssScript, AssStyle};
+
+    let default_script;
+    let script: &AssScript = match meta {
+        Some(SubtitleFileMeta::Ass(script)) => script,
+        _ => {
+            default_script = AssScript {
+                script_info: vec![
+                    format!("Title: {}", title),
+                    "ScriptType: v4.00+".to_string(),
+                    "WrapStyle: 0".to_string(),
+                    "ScaledBorderAndShadow: yes".to_string(),
+                    "PlayResX: 1920".to_string(),
+                    "PlayResY: 1080".to_string(),
+                ],
+                styles: vec![AssStyle::default()],
+                ..Default::default()
+            };
+            &default_script
+        }
+    };
+
+    let mut result = "[Script Info]\n".to_string();
+    for line in &script.script_info {
+        // Стили всегда пишем в V4+, поэтому SSA-заголовок приводим к ASS
+        if line.to_lowercase().starts_with("scripttype") {
+            result.push_str("ScriptType: v4.00+\n");
+        } else {
+            result.push_str(line);
+            result.push('\n');
+        }
+    }
+
+    result.push_str("\n[V4+ Styles]\n");
+    result.push_str(ass::V4PLUS_STYLE_FORMAT);
+    result.push('\n');
+    if script.styles.is_empty() {
+        result.push_str(&AssStyle::default().to_v4plus_line());
+        result.push('\n');
+    }
+    for style in &script.styles {
+        result.push_str(&style.to_v4plus_line());
+        result.push('\n');
+    }
+    if let Some(b) = bilingual.filter(|b| b.layout == BilingualLayout::SeparateTracks) {
+        let base = script.styles.iter().find(|st| st.name == "Default").or(script.styles.first()).cloned().unwrap_or_default();
+        for (name, style) in [(ASS_ORIGINAL_STYLE, &b.original_style), (ASS_TRANSLATION_STYLE, &b.translation_style)] {
+            let mut derived = base.clone();
+            derived.name = name.to_string();
+            if let Some(font) = &style.font_name {
+                derived.fontname = font.clone();
+            }
+            if let Some(size) = style.font_size {
+                derived.fontsize = size;
+            }
+            if let Some(colour) = style.color.as_deref().and_then(ass_colour) {
+                derived.primary_colour = format!("&H00{}", colour);
+            }
+            derived.italic = derived.italic || style.italic;
+            result.push_str(&derived.to_v4plus_line());
+            result.push('\n');
+        }
+    }
+
+    result.push_str("\n[Events]\n");
+    result.push_str(ass::V4PLUS_EVENT_FORMAT);
+    result.push('\n');
+    for line in &script.comment_events {
+        result.push_str(line);
+        result.push('\n');
+    }
+
+    for seg in segments {
+        let start = format_time_ass(seg.start);
+        let end = format_time_ass(seg.end);
+        let ass_meta = match &seg.format_meta {
+            Some(SegmentFormatMeta::Ass(m)) => Some(m),
+            _ => None,
+        };
+        let leading = ass_meta.map(|m| m.leading_tags.as_str()).unwrap_or("");
+        let closing = ass::closing_overrides(leading);
+        // Текст не редактировали — отдаём строку как была, со всеми тегами
+        let original_body = match ass_meta {
+            Some(m) if seg.text == ass::plain_text(&m.raw_text) => {
+                m.raw_text.strip_prefix(leading).unwrap_or(&m.raw_text).to_string()
+            }
+            _ => ass::escape_text(&seg.text),
+        };
+        let translation_body = seg.translation.as_deref().map(ass::escape_text);
+
+        let dialogue = |style: &str, text: &str| match ass_meta {
+            Some(m) => format!(
+                "Dialogue: {},{},{},{},{},{},{},{},{},{}\n",
+                m.layer, start, end, style, m.actor, m.margin_l, m.margin_r, m.margin_v, m.effect, text
+            ),
+            None => format!("Dialogue: 0,{},{},{},,0,0,0,,{}\n", start, end, style, text),
+        };
+        let own_style = ass_meta.map(|m| m.style.as_str()).unwrap_or("Default");
+
+        match (bilingual, translation_body) {
+            (None, Some(translation)) => {
+                result.push_str(&dialogue(own_style, &format!("{}{}{}", leading, translation, closing)))
+            }
+            (Some(b), Some(translation)) if b.layout == BilingualLayout::SingleCue => {
+                let parts = [
+                    (original_body.as_str(), &b.original_style),
+                    (translation.as_str(), &b.translation_style),
+                ];
+                let [first, second] = match b.order {
+                    BilingualOrder::OriginalFirst => parts,
+                    BilingualOrder::TranslationFirst => [parts[1], parts[0]],
+                };
+                let text = format!(
+                    "{}{}{}\\N{{\\r}}{}{}",
+                    leading,
+                    ass_overrides(first.1),
+                    first.0,
+                    ass_overrides(second.1),
+                    second.0
+                );
+                result.push_str(&dialogue(own_style, &text));
+            }
+            (Some(b), Some(translation)) => {
+                // При наложении ASS-рендерер поднимает более позднюю строку над ранней
+                let original = dialogue(ASS_ORIGINAL_STYLE, &format!("{}{}", leading, original_body));
+                let translated = dialogue(ASS_TRANSLATION_STYLE, &format!("{}{}{}", leading, translation, closing));
+                match b.order {
+                    BilingualOrder::OriginalFirst => {
+                        result.push_str(&translated);
+                        result.push_str(&original);
+                    }
+                    BilingualOrder::TranslationFirst => {
+                        result.push_str(&original);
+                        result.push_str(&translated);
+                    }
+                }
+            }
+            (bilingual, None) => {
+                let style = match bilingual {
+                    Some(b) if b.layout == BilingualLayout::SeparateTracks => ASS_ORIGINAL_STYLE,
+                    _ => own_style,
+                };
+                result.push_str(&dialogue(style, &format!("{}{}", leading, original_body)));
+            }
+        }
+    }
+
+    for section in &script.extra_sections {
+        result.push_str(&format!("\n[{}]\n", section.name));
+        for line in &section.lines {
+            result.push_str(line);
+            result.push('\n');
+        }
+    }
+
+    result
+}
+
+const ASS_ORIGINAL_STYLE: &str = "Original";
+const ASS_TRANSLATION_STYLE: &str = "Translation";
+
+/// `#RRGGBB` → `BBGGRR` (порядок цветов в ASS)
+fn ass_colour(color: &str) -> Option<String> {
+    let hex = color.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(format!("{}{}{}", &hex[4..6], &hex[2..4], &hex[0..2]).to_uppercase())
+}
+
+/// Теги `{\fn…\fs…\c…\i1}` для части двуязычной строки
+fn ass_overrides(style: &BilingualStyle) -> String {
+    let mut tags = String::new();
+    if let Some(font) = &style.font_name {
+        tags.push_str(&format!("\\fn{}", font));
+    }
+    if let Some(size) = style.font_size {
+        tags.push_str(&format!("\\fs{}", size));
+    }
+    if let Some(colour) = style.color.as_deref().and_then(ass_colour) {
+        tags.push_str(&format!("\\c&H{}&", colour));
+    }
+    if style.italic {
+        tags.push_str("\\i1");
+    }
+    if tags.is_empty() { tags } else { format!("{{{}}}", tags) }
+}
+
+/// Оригинал и перевод в одной реплике (поле `translation`), в заданном порядке.
+/// SRT и WebVTT получают разметку шрифта/цвета/курсива, остальные форматы — простой текст.
+fn combine_bilingual(segments: &[SubtitleSegment], options: &BilingualOptions, format: &str) -> Vec<SubtitleSegment> {
+    let styled = |text: &str, style: &BilingualStyle, class: &str| match format {
+        "srt" => {
+            let mut attributes = String::new();
+            if let Some(font) = &style.font_name {
+                attributes.push_str(&format!(" face=\"{}\"", font));
+            }
+            if let Some(size) = style.font_size {
+                attributes.push_str(&format!(" size=\"{}\"", size));
+            }
+            if let Some(color) = &style.color {
+                attributes.push_str(&format!(" color=\"{}\"", color));
+            }
+            let mut text = text.to_string();
+            if style.italic {
+                text = format!("<i>{}</i>", text);
+            }
+            if attributes.is_empty() { text } else { format!("<font{}>{}</font>", attributes, text) }
+        }
+        "vtt" => {
+            let text = if style.italic { format!("<i>{}</i>", text) } else { text.to_string() };
+            format!("<c.{}>{}</c>", class, text)
+        }
+        _ => text.to_string(),
+    };
+
+    segments
+        .iter()
+        .map(|seg| {
+            let mut seg = seg.clone();
+            if let Some(translation) = seg.translation.as_deref().filter(|t| !t.trim().is_empty()) {
+                let original = styled(&seg.text, &options.original_style, "original");
+                let translated = styled(translation, &options.translation_style, "translation");
+                seg.translation = Some(match options.order {
+                    BilingualOrder::OriginalFirst => format!("{}\n{}", original, translated),
+                    BilingualOrder::TranslationFirst => format!("{}\n{}", translated, original),
+                });
+            }
+            seg
+        })
+        .collect()
+}
+
+/// Заголовок WebVTT с блоком `STYLE` для классов `original`/`translation`.
+fn bilingual_vtt_header(meta: Option<&SubtitleFileMeta>, options: &BilingualOptions) -> SubtitleFileMeta {
+    let mut header = match meta {
+        Some(SubtitleFileMeta::Vtt(header)) => header.clone(),
+        _ => Default::default(),
+    };
+    let rule = |class: &str, style: &BilingualStyle| {
+        let mut declarations = Vec::new();
+        if let Some(font) = &style.font_name {
+            declarations.push(format!("font-family: \"{}\";", font));
+        }
+        if let Some(size) = style.font_size {
+            declarations.push(format!("font-size: {}px;", size));
+        }
+        if let Some(color) = &style.color {
+            declarations.push(format!("color: {};", color));
+        }
+        (!declarations.is_empty()).then(|| format!("::cue(.{}) {{ {} }}", class, declarations.join(" ")))
+    };
+    let rules: Vec<String> = [
+        rule("original", &options.original_style),
+        rule("translation", &options.translation_style),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if !rules.is_empty() {
+        header.blocks.push(format!("STYLE\n{}", rules.join("\n")));
+    }
+    SubtitleFileMeta::Vtt(header)
+}
+
+/// TTML по профилю IMSC1 Text или EBU-TT-D. Если файл был импортирован из TTML —
+/// сохраняем исходные `<head>` (стили, регионы), атрибуты `<p>` и обёртку `<span>`.
+fn generate_ttml(
+    segments: &[SubtitleSegment],
+    meta: Option<&SubtitleFileMeta>,
+    target_language: &str,
+    ebu_tt_d: bool,
+) -> String {
+    use crate::subtitle_parser::ttml::{self, TtmlDocument};
+
+    let source = match meta {
+        Some(SubtitleFileMeta::Ttml(doc)) => Some(doc),
+        _ => None,
+    };
+    let has_translation = segments.iter().any(|s| s.translation.is_some());
+    // Язык документа — язык того текста, который попадёт в файл
+    let lang = match source.and_then(|d| d.lang.as_deref()) {
+        Some(source_lang) if !has_translation => source_lang.to_string(),
+        _ => target_language.to_string(),
+    };
+
+    let mut result = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".to_string();
+
+    match source {
+        // В EBU-TT-D корневые атрибуты и заголовок задаются профилем, исходные не переносим
+        Some(TtmlDocument { root_start_tag, head_xml, .. }) if !ebu_tt_d => {
+            result.push_str(&replace_xml_lang(root_start_tag, &lang));
+            result.push('\n');
+            if let Some(head) = head_xml {
+                result.push_str(head);
+                result.push('\n');
+            }
+        }
+        _ if ebu_tt_d => {
+            result.push_str(&format!(
+                "<tt xmlns=\"http://www.w3.org/ns/ttml\" xmlns:ttp=\"http://www.w3.org/ns/ttml#parameter\" \
+xmlns:tts=\"http://www.w3.org/ns/ttml#styling\" xmlns:ttm=\"http://www.w3.org/ns/ttml#metadata\" \
+xmlns:ebuttm=\"urn:ebu:tt:metadata\" xmlns:ebutts=\"urn:ebu:tt:style\" \
+ttp:timeBase=\"media\" ttp:cellResolution=\"50 30\" xml:lang=\"{}\">\n",
+                ttml::escape_xml(&lang)
+            ));
+            result.push_str(
+                "<head>\n\
+<metadata><ebuttm:documentMetadata><ebuttm:conformsToStandard>urn:ebu:tt:distribution:2014-01</ebuttm:conformsToStandard></ebuttm:documentMetadata></metadata>\n\
+<styling><style xml:id=\"s1\" tts:fontFamily=\"proportionalSansSerif\" tts:fontSize=\"100%\" tts:lineHeight=\"normal\" \
+tts:textAlign=\"center\" tts:color=\"#FFFFFF\" tts:backgroundColor=\"#000000C2\"/></styling>\n\
+<layout><region xml:id=\"bottom\" tts:origin=\"10% 10%\" tts:extent=\"80% 80%\" tts:displayAlign=\"after\"/></layout>\n\
+</head>\n",
+            );
+        }
+        _ => {
+            result.push_str(&format!(
+                "<tt xmlns=\"http://www.w3.org/ns/ttml\" xmlns:ttp=\"http://www.w3.org/ns/ttml#parameter\" \
+xmlns:tts=\"http://www.w3.org/ns/ttml#styling\" xmlns:ttm=\"http://www.w3.org/ns/ttml#metadata\" \
+ttp:profile=\"http://www.w3.org/ns/ttml/profile/imsc1/text\" xml:lang=\"{}\">\n",
+                ttml::escape_xml(&lang)
+            ));
+            result.push_str(
+                "<head>\n\
+<styling><style xml:id=\"s1\" tts:fontFamily=\"proportionalSansSerif\" tts:fontSize=\"100%\" \
+tts:textAlign=\"center\" tts:color=\"white\"/></styling>\n\
+<layout><region xml:id=\"bottom\" tts:origin=\"10% 10%\" tts:extent=\"80% 80%\" tts:displayAlign=\"after\"/></layout>\n\
+</head>\n",
+            );
+        }
+    }
+
+    let keep_source_body = source.is_some() && !ebu_tt_d;
+    // Элементы пишем с тем же префиксом, что в исходнике (`<tt:p>` в EBU-TT-D и т.п.)
+    let prefix = source.filter(|_| keep_source_body).and_then(|d| d.prefix());
+    let root_name = match source {
+        Some(doc) if keep_source_body => ttml::tag_name(&doc.root_start_tag).to_string(),
+        _ => "tt".to_string(),
+    };
+    let body_tag = match source.and_then(|d| d.body_start_tag.as_deref()) {
+        Some(tag) if keep_source_body => tag.to_string(),
+        _ => format!("<{} style=\"s1\">", ttml::qualified_name(prefix, "body")),
+    };
+    let div_tag = match source.and_then(|d| d.div_start_tag.as_deref()) {
+        Some(tag) if keep_source_body => tag.to_string(),
+        _ => format!("<{}>", ttml::qualified_name(prefix, "div")),
+    };
+    result.push_str(&body_tag);
+    result.push('\n');
+    result.push_str(&div_tag);
+    result.push('\n');
+    let p_name = ttml::qualified_name(prefix, "p");
+    let namespaces = source.map(TtmlDocument::namespace_declarations).unwrap_or_default();
+    // Регион `bottom` есть в нашем `<head>`; в исходном его может не быть — тогда новые
+    // реплики берут регион соседней импортированной, а без неё идут без региона
+    let source_regions: Vec<Option<&str>> = segments
+        .iter()
+        .map(|seg| match &seg.format_meta {
+            Some(SegmentFormatMeta::Ttml(m)) => m.region.as_deref(),
+            _ => None,
+        })
+        .collect();
+    let head_has_bottom = !keep_source_body
+        || source
+            .and_then(|d| d.head_xml.as_deref())
+            .is_some_and(|head| regex::Regex::new(r#"xml:id\s*=\s*["']bottom["']"#).is_ok_and(|re| re.is_match(head)));
+    let fallback_region = |index: usize| -> Option<&str> {
+        if head_has_bottom {
+            return Some("bottom");
+        }
+        let before = source_regions[..index].iter().rev().flatten().next();
+        let after = source_regions[index..].iter().flatten().next();
+        before.or(after).copied()
+    };
+
+    for (index, seg) in segments.iter().enumerate() {
+        let mut attributes = Vec::new();
+        let content = match &seg.format_meta {
+            Some(SegmentFormatMeta::Ttml(m)) if keep_source_body => {
+                if let Some(id) = &m.xml_id {
+                    attributes.push(format!("xml:id=\"{}\"", ttml::escape_xml(id)));
+                }
+                if let Some(region) = &m.region {
+                    attributes.push(format!("region=\"{}\"", ttml::escape_xml(region)));
+                }
+                if let Some(style) = &m.style {
+                    attributes.push(format!("style=\"{}\"", ttml::escape_xml(style)));
+                }
+                for attr in &m.inline_styles {
+                    attributes.push(format!("{}=\"{}\"", attr.name, ttml::escape_xml(&attr.value)));
+                }
+                let wrap = |text: &str| match &m.wrapper_span {
+                    Some(span) => format!("{}{}</{}>", span, ttml::text_to_content(text, prefix), ttml::tag_name(span)),
+                    None => ttml::text_to_content(text, prefix),
+                };
+                match &seg.translation {
+                    Some(translation) => {
+                        if m.lang.is_some() {
+                            attributes.push(format!("xml:lang=\"{}\"", ttml::escape_xml(target_language)));
+                        }
+                        wrap(translation)
+                    }
+                    None => {
+                        if let Some(l) = &m.lang {
+                            attributes.push(format!("xml:lang=\"{}\"", ttml::escape_xml(l)));
+                        }
+                        // Текст не редактировали — отдаём содержимое как было, со всеми `<span>`
+                        let unedited = match &m.source_text {
+                            Some(text) => *text == seg.text,
+                            None => ttml::content_to_text(&m.raw_content, &namespaces).as_deref() == Some(seg.text.as_str()),
+                        };
+                        if unedited {
+                            m.raw_content.clone()
+                        } else {
+                            wrap(&seg.text)
+                        }
+                    }
+                }
+            }
+            _ => {
+                attributes.push(format!("xml:id=\"sub{}\"", seg.id));
+                if let Some(region) = fallback_region(index) {
+                    attributes.push(format!("region=\"{}\"", ttml::escape_xml(region)));
+                }
+                ttml::text_to_content(seg.translation.as_ref().unwrap_or(&seg.text), prefix)
+            }
+        };
+
+        result.push_str(&format!(
+            "<{p} begin=\"{}\" end=\"{}\"{}>{}</{p}>\n",
+            ttml::format_time(seg.start),
+            ttml::format_time(seg.end),
+            attributes.iter().map(|a| format!(" {}", a)).collect::<String>(),
+            content,
+            p = p_name
+        ));
+    }
+
+    result.push_str(&format!(
+        "</{}>\n</{}>\n</{}>\n",
+        ttml::tag_name(&div_tag),
+        ttml::tag_name(&body_tag),
+        root_name
+    ));
+    result
+}
+
+/// Частота кадров для файла проекта: своя или связанного видео.
+fn project_frame_rate(project: &Project, file: &ProjectFile) -> Option<subtitle_parser::framerate::FrameRate> {
+    file.frame_rate.or_else(|| {
+        let linked = file.linked_file_id.as_ref()?;
+        project.files.iter().find(|f| &f.id == linked)?.frame_rate
+    })
+}
+
+/// MicroDVD: `{кадр}{кадр}текст`, первая строка `{1}{1}23.976` сообщает плееру частоту.
+/// Без известной частоты видео пишем для 23.976.
+fn generate_microdvd(
+    segments: &[SubtitleSegment],
+    frame_rate: Option<subtitle_parser::framerate::FrameRate>,
+) -> String {
+    use crate::subtitle_parser::microdvd;
+
+    let rate = frame_rate.unwrap_or_default();
+    let mut result = format!("{{1}}{{1}}{}\n", microdvd::format_frame_rate(rate));
+
+    for seg in segments {
+        let text = match &seg.format_meta {
+            Some(SegmentFormatMeta::MicroDvd(m)) => match &seg.translation {
+                Some(translation) => format!("{}{}", m.leading_codes, microdvd::escape_text(translation)),
+                None if seg.text == microdvd::plain_text(&m.raw_text) => m.raw_text.clone(),
+                None => format!("{}{}", m.leading_codes, microdvd::escape_text(&seg.text)),
+            },
+            _ => microdvd::escape_text(seg.translation.as_ref().unwrap_or(&seg.text)),
+        };
+        let start = rate.timestamp_to_frames(seg.start);
+        // Реплика длиной хотя бы в один кадр
+        let end = rate.timestamp_to_frames(seg.end).max(start + 1);
+        result.push_str(&format!("{{{}}}{{{}}}{}\n", start, end, text));
+    }
+
+    result
+}
+
+/// EBU STL. Если файл был импортирован из STL — берём его GSI (частота кадров, телетекст,
+/// TCP) и TTI-параметры реплик; название программы и язык — из проекта.
+fn generate_stl(
+    segments: &[SubtitleSegment],
+    meta: Option<&SubtitleFileMeta>,
+    project: &Project,
+) -> (Vec<u8>, Vec<UnmappedSegment>) {
+    use crate::subtitle_parser::stl::{self, StlCue, StlGsi};
+
+    let today = chrono::Local::now().format("%y%m%d").to_string();
+    let has_translation = segments.iter().any(|s| s.translation.is_some());
+
+    let mut gsi = match meta {
+        Some(SubtitleFileMeta::Stl(source)) => {
+            let mut gsi = source.as_ref().clone();
+            let revision = gsi.revision_number.trim().parse::<u32>().unwrap_or(0) + 1;
+            gsi.revision_number = format!("{:02}", revision % 100);
+            gsi
+        }
+        // Язык оригинала проект не хранит: он известен, только если файл пришёл из TTML
+        _ => StlGsi {
+            creation_date: today.clone(),
+            language_code: match meta {
+                Some(SubtitleFileMeta::Ttml(doc)) => doc.lang.as_deref().map_or("00", stl::language_code),
+                _ => "00",
+            }
+            .to_string(),
+            ..Default::default()
+        },
+    };
+    // Код языка — того текста, который попадёт в файл
+    if has_translation {
+        gsi.language_code = stl::language_code(&project.target_language).to_string();
+    }
+    gsi.revision_date = today;
+    gsi.original_programme_title = project.name.clone();
+
+    let cues: Vec<StlCue> = segments
+        .iter()
+        .map(|seg| StlCue {
+            start: seg.start.as_seconds(),
+            end: seg.end.as_seconds(),
+            text: seg.translation.as_deref().unwrap_or(&seg.text),
+            meta: match &seg.format_meta {
+                Some(SegmentFormatMeta::Stl(m)) => Some(m),
+                _ => None,
+            },
+        })
+        .collect();
+
+    let (bytes, unmapped) = stl::write(&cues, &gsi);
+    let unmapped = unmapped
+        .into_iter()
+        .map(|cue| UnmappedSegment { id: segments[cue.index].id, characters: cue.characters })
+        .collect();
+    (bytes, unmapped)
+}
+
+/// Scenarist SCC (CEA-608, pop-on). Расположение строк на экране берётся из импортированного
+/// SCC, если текст по-прежнему в него помещается; иначе — внизу по центру.
+fn generate_scc(segments: &[SubtitleSegment]) -> Result<(String, Vec<DelayedSegment>), String> {
+    use crate::subtitle_parser::scc::{self, SccCue};
+
+    let cues: Vec<SccCue> = segments
+        .iter()
+        .map(|seg| SccCue {
+            start: seg.start.as_seconds(),
+            end: seg.end.as_seconds(),
+            text: seg.translation.as_deref().unwrap_or(&seg.text),
+            meta: match &seg.format_meta {
+                Some(SegmentFormatMeta::Scc(m)) => Some(m),
+                _ => None,
+            },
+        })
+        .collect();
+
+    let (content, late) = scc::write(&cues)?;
+    let delayed = late
+        .iter()
+        .map(|cue| DelayedSegment { id: segments[cue.index].id, delay_frames: cue.delay_frames })
+        .collect();
+    Ok((content, delayed))
+}
+
+/// Подменяет (или добавляет) `xml:lang` в открывающем теге `<tt>`.
+fn replace_xml_lang(start_tag: &str, lang: &str) -> String {
+    let lang = crate::subtitle_parser::ttml::escape_xml(lang);
+    let Ok(re) = regex::Regex::new(r#"xml:lang\s*=\s*("[^"]*"|'[^']*')"#) else {
+        return start_tag.to_string();
+    };
+    if re.is_match(start_tag) {
+        re.replace(start_tag, format!("xml:lang=\"{}\"", lang).as_str()).into_owned()
+    } else {
+        let close = if start_tag.ends_with("/>") { start_tag.len() - 2 } else { start_tag.len() - 1 };
+        format!("{} xml:lang=\"{}\"{}", &start_tag[..close], lang, &start_tag[close..])
+    }
+}
+
+pub(crate) fn format_time_srt(time: Timestamp) -> String {
+    let (hours, minutes, secs, millis) = time.hmsm();
     format!("{:02}:{:02}:{:02},{:03}", hours, minutes, secs, millis)
 }
 
-fn format_time_vtt(seconds: f64) -> String {
-    let hours = (seconds / 3600.0) as u32;
-    let minutes = ((seconds % 3600.0) / 60.0) as u32;
-    let secs = (seconds % 60.0) as u32;
-    let millis = ((seconds % 1.0) * 1000.0) as u32;
+pub(crate) fn format_time_vtt(time: Timestamp) -> String {
+    let (hours, minutes, secs, millis) = time.hmsm();
     format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, secs, millis)
 }
 
-fn format_time_simple(seconds: f64) -> String {
-    let minutes = (seconds / 60.0) as u32;
-    let secs = (seconds % 60.0) as u32;
-    format!("{:02}:{:02}", minutes, secs)
+/// `H:MM:SS.cc` — ASS хранит время в сотых долях секунды (округление до ближайшей)
+pub(crate) fn format_time_ass(time: Timestamp) -> String {
+    let total_cs = (time.millis().max(0) + 5) / 10;
+    let hours = total_cs / 360_000;
+    let minutes = (total_cs % 360_000) / 6_000;
+    let secs = (total_cs % 6_000) / 100;
+    let cs = total_cs % 100;
+    format!("{}:{:02}:{:02}.{:02}", hours, minutes, secs, cs)
+}
+
+fn format_time_simple(time: Timestamp) -> String {
+    let total_secs = time.millis().max(0) / 1000;
+    format!("{:02}:{:02}", total_secs / 60, total_secs % 60)
 }
 
 #[tauri::command]
@@ -396,14 +1596,26 @@ pub async fn remove_file_from_project(
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubtitleImportResult {
+    pub segments: Vec<SubtitleSegment>,
+    /// Исправленные и пропущенные реплики с номерами строк
+    pub diagnostics: Vec<subtitle_parser::ParseDiagnostic>,
+    /// Кодировка, в которой был прочитан файл
+    pub encoding: String,
+    /// Формат файла и уверенность определения
+    pub detected_format: subtitle_parser::DetectedFormat,
+}
+
 #[tauri::command]
 pub async fn import_existing_subtitles(
     subtitle_path: String,
     format: Option<String>,
     project_path: String,
     file_id: String,
+    encoding: Option<String>,
     app_handle: tauri::AppHandle,
-) -> Result<Vec<SubtitleSegment>, String> {
+) -> Result<SubtitleImportResult, String> {
     println!("Импорт существующих субтитров: {}", subtitle_path);
     
     let subtitle_path_buf = Path::new(&subtitle_path);
@@ -411,38 +1623,91 @@ pub async fn import_existing_subtitles(
         return Err(format!("Файл субтитров не найден: {}", subtitle_path));
     }
     
-    // Определяем формат
-    let detected_format = if let Some(fmt) = format {
-        match fmt.to_lowercase().as_str() {
-            "srt" => subtitle_parser::SubtitleFormat::SRT,
-            "vtt" => subtitle_parser::SubtitleFormat::VTT,
-            "ass" => subtitle_parser::SubtitleFormat::ASS,
-            "ssa" => subtitle_parser::SubtitleFormat::SSA,
-            _ => return Err(format!("Неподдерживаемый формат: {}", fmt)),
-        }
-    } else {
-        subtitle_parser::detect_format(subtitle_path_buf)?
+    // Читаем содержимое файла и определяем кодировку (или берём заданную вручную)
+    let bytes = fs::read(subtitle_path_buf)
+        .map_err(|e| format!("Ошибка чтения файла: {}", e))?;
+    let project_path_buf = Path::new(&project_path);
+    let mut project = Project::load_from_file(project_path_buf, &app_handle)?;
+    let frame_rate = project
+        .files
+        .iter()
+        .find(|f| f.id == file_id)
+        .and_then(|file| project_frame_rate(&project, file));
+    
+    let forced_format = format
+        .as_deref()
+        .map(subtitle_parser::format_from_name)
+        .transpose()?;
+    
+    // EBU STL — двоичный формат со своими кодовыми таблицами, текстовое декодирование к нему не применяется
+    let is_stl = match forced_format {
+        Some(fmt) => fmt == subtitle_parser::SubtitleFormat::STL,
+        None => subtitle_parser::stl::looks_like_stl(&bytes),
     };
     
-    // Читаем содержимое файла
-    let content = fs::read_to_string(subtitle_path_buf)
-        .map_err(|e| format!("Ошибка чтения файла: {}", e))?;
+    let (parsed, file_encoding, detected) = if is_stl {
+        let parsed = subtitle_parser::parse_stl(&bytes)?;
+        let table = match &parsed.metadata {
+            Some(SubtitleFileMeta::Stl(gsi)) => subtitle_parser::stl::character_table_name(&gsi.character_code_table),
+            _ => "ISO 6937",
+        };
+        let detected = subtitle_parser::DetectedFormat {
+            format: subtitle_parser::SubtitleFormat::STL,
+            confidence: 1.0,
+        };
+        (parsed, table.to_string(), detected)
+    } else {
+        let decoded = subtitle_parser::encoding::decode(&bytes, encoding.as_deref())?;
+        println!(
+            "Кодировка файла: {}{}{}",
+            decoded.encoding,
+            if decoded.had_bom { " (BOM)" } else { "" },
+            if decoded.had_errors { ", есть некорректные байты" } else { "" }
+        );
+        
+        // Определяем формат: явно заданный или по содержимому файла
+        let detected = match forced_format {
+            Some(format) => subtitle_parser::DetectedFormat { format, confidence: 1.0 },
+            None => subtitle_parser::sniff_format(&decoded.text, subtitle_path_buf)?,
+        };
+        
+        let parsed = subtitle_parser::parse_subtitles(&decoded.text, detected.format, frame_rate)?;
+        (parsed, decoded.encoding, detected)
+    };
+    println!(
+        "Формат субтитров: {:?} (уверенность {:.2})",
+        detected.format, detected.confidence
+    );
     
-    // Парсим субтитры
-    let segments = subtitle_parser::parse_subtitles(&content, detected_format)?;
+    let segments = parsed.segments;
+    let diagnostics = parsed.diagnostics;
     
     if segments.is_empty() {
-        return Err("Не удалось распарсить субтитры".to_string());
+        let details = diagnostics
+            .iter()
+            .take(5)
+            .map(|d| format!("строка {}: {}", d.line, d.message))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if details.is_empty() {
+            return Err("Не удалось распарсить субтитры".to_string());
+        }
+        return Err(format!("Не удалось распарсить субтитры ({})", details));
     }
     
-    println!("Импортировано {} сегментов", segments.len());
+    println!(
+        "Импортировано {} сегментов, замечаний парсера: {}",
+        segments.len(),
+        diagnostics.len()
+    );
     
     // Обновляем файл в проекте
-    let project_path_buf = Path::new(&project_path);
-    let mut project = Project::load_from_file(project_path_buf, &app_handle)?;
-    
     if let Some(file) = project.files.iter_mut().find(|f| f.id == file_id) {
         file.subtitle_segments = Some(segments.clone());
+        file.subtitle_meta = parsed.metadata;
+        if parsed.frame_rate.is_some() {
+            file.frame_rate = parsed.frame_rate;
+        }
         file.updated_at = chrono::Utc::now().to_rfc3339();
         project.updated_at = chrono::Utc::now().to_rfc3339();
         
@@ -450,7 +1715,12 @@ pub async fn import_existing_subtitles(
         println!("Субтитры сохранены в проект");
     }
     
-    Ok(segments)
+    Ok(SubtitleImportResult {
+        segments,
+        diagnostics,
+        encoding: file_encoding,
+        detected_format: detected,
+    })
 }
 
 #[derive(Debug, Serialize, Deserialize)]
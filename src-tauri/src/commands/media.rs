@@ -1,6 +1,16 @@
 use std::path::Path;
 use std::process::Stdio;
 use tokio::process::Command;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tauri::Emitter;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio_util::sync::CancellationToken;
+use crate::commands::ai::{ProgressEvent, ProgressPayload};
+use crate::commands::audio::media_duration_seconds;
+use crate::commands::files::{export_project_file, ExportOptions, TextSource};
+use crate::project::{Project, ProjectFile, ProjectType, SubtitleFileMeta};
+use crate::subtitle_parser::framerate::FrameRate;
 
 #[tauri::command]
 pub async fn extract_audio_from_video(
@@ -77,6 +87,507 @@ pub async fn extract_audio_from_video(
     }
 }
 
+/// Дорожка для `mux_subtitles`: файл субтитров проекта и её параметры в контейнере.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct MuxTrack {
+    pub file_id: String,
+    /// Язык дорожки; по умолчанию — язык перевода проекта
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub default: bool,
+    #[serde(default)]
+    pub forced: bool,
+}
+
+/// Результат `mux_subtitles` и `burn_in_subtitles`.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct VideoSubtitlesResult {
+    pub output_path: String,
+    /// Реплики без перевода, выведенные с исходным текстом или пометкой, по файлам субтитров
+    pub untranslated: Vec<UntranslatedSegments>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct UntranslatedSegments {
+    pub file_id: String,
+    pub fallback_segment_ids: Vec<u32>,
+}
+
+/// Пишет файл субтитров проекта во временный файл для ffmpeg по тем же правилам, что
+/// и `export_subtitles`: реплики без перевода — только если это разрешено в `export`.
+fn write_temp_subtitles(
+    project: &Project,
+    file: &ProjectFile,
+    format: &str,
+    path: &Path,
+    export: &ExportOptions,
+) -> Result<UntranslatedSegments, String> {
+    let result = export_project_file(project, file, format, path.to_string_lossy().to_string(), export)
+        .map_err(|e| format!("Субтитры '{}': {}", file.name, e))?;
+    Ok(UntranslatedSegments {
+        file_id: file.id.clone(),
+        fallback_segment_ids: result.fallback_segment_ids,
+    })
+}
+
+/// Параметры экспорта для вшивания: ffmpeg читает субтитры в UTF-8.
+fn video_export_options(export: Option<ExportOptions>) -> ExportOptions {
+    ExportOptions { encoding: None, ..export.unwrap_or_default() }
+}
+
+/// Вшивает субтитры проекта в копию видео мягкими дорожками. Видео и аудио копируются
+/// без перекодирования (`-c copy`); кодек субтитров выбирается по контейнеру:
+/// MKV — ASS/SRT как есть, MP4/MOV — `mov_text`, WebM — WebVTT.
+/// Текст дорожек выбирается по `export_options`, как в `export_subtitles`.
+#[tauri::command]
+pub async fn mux_subtitles(
+    project_path: String,
+    video_file_id: String,
+    tracks: Vec<MuxTrack>,
+    output_path: String,
+    export_options: Option<ExportOptions>,
+    app_handle: tauri::AppHandle,
+) -> Result<VideoSubtitlesResult, String> {
+    let export = video_export_options(export_options);
+    if tracks.is_empty() {
+        return Err("Не выбрано ни одной дорожки субтитров".to_string());
+    }
+    let project_path_buf = Path::new(&project_path);
+    let project = Project::load_from_file(project_path_buf, &app_handle)?;
+
+    let video = project
+        .files
+        .iter()
+        .find(|f| f.id == video_file_id && f.file_type == ProjectType::Video)
+        .ok_or("Видео не найдено в проекте")?;
+    let video_path = project_path_buf.join(&video.path);
+    if !video_path.exists() {
+        return Err(format!("Видео файл не найден: {}", video_path.display()));
+    }
+
+    let container = Path::new(&output_path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let container_codec = match container.as_str() {
+        "mkv" => None,
+        "mp4" | "m4v" | "mov" => Some("mov_text"),
+        "webm" => Some("webvtt"),
+        _ => return Err(format!("Контейнер .{} не поддерживает мягкие субтитры", container)),
+    };
+
+    if !is_ffmpeg_available().await {
+        return Err("FFmpeg не установлен в системе".to_string());
+    }
+
+    // Субтитры пишем из сегментов проекта (с переводом) во временные файлы
+    let temp_dir = std::env::temp_dir().join(format!("subtitle_studio_mux_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&temp_dir).map_err(|e| e.to_string())?;
+
+    let mut inputs = Vec::new();
+    let mut codecs = Vec::new();
+    let mut untranslated = Vec::new();
+    for (i, track) in tracks.iter().enumerate() {
+        let file = project
+            .files
+            .iter()
+            .find(|f| f.id == track.file_id && f.file_type == ProjectType::Subtitle)
+            .ok_or_else(|| format!("Файл субтитров {} не найден в проекте", track.file_id));
+
+        // Стили ASS сохраняются только в MKV
+        let written = file.and_then(|file| {
+            let keep_ass = container_codec.is_none() && matches!(file.subtitle_meta, Some(SubtitleFileMeta::Ass(_)));
+            let extension = if keep_ass { "ass" } else { "srt" };
+            let input = temp_dir.join(format!("track_{}.{}", i, extension));
+            let track_untranslated = write_temp_subtitles(&project, file, extension, &input, &export)?;
+            Ok((input, extension, track_untranslated))
+        });
+        let (input, extension, track_untranslated) = match written {
+            Ok(written) => written,
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(&temp_dir);
+                return Err(e);
+            }
+        };
+        inputs.push(input);
+        codecs.push(container_codec.unwrap_or(extension));
+        untranslated.push(track_untranslated);
+    }
+
+    let mut cmd = Command::new("ffmpeg");
+    cmd.arg("-y").arg("-i").arg(&video_path);
+    for input in &inputs {
+        cmd.arg("-i").arg(input);
+    }
+    cmd.arg("-map").arg("0:v").arg("-map").arg("0:a?");
+    if container == "mkv" {
+        // Вложенные шрифты нужны для ASS
+        cmd.arg("-map").arg("0:t?");
+    }
+    for i in 0..inputs.len() {
+        cmd.arg("-map").arg(format!("{}:0", i + 1));
+    }
+    cmd.arg("-c:v").arg("copy").arg("-c:a").arg("copy");
+    if container == "mkv" {
+        cmd.arg("-c:t").arg("copy");
+    }
+
+    for (i, track) in tracks.iter().enumerate() {
+        // Оригинал — не на языке перевода, а язык исходника проект не хранит
+        let default_language = match export.text_source {
+            TextSource::Original => "und",
+            _ => project.target_language.as_str(),
+        };
+        let language = track.language.as_deref().unwrap_or(default_language);
+        let title = track.title.clone().unwrap_or_else(|| language_title(language));
+        let mut disposition = Vec::new();
+        if track.default {
+            disposition.push("default");
+        }
+        if track.forced {
+            disposition.push("forced");
+        }
+        let disposition = if disposition.is_empty() { "0".to_string() } else { disposition.join("+") };
+
+        cmd.arg(format!("-c:s:{}", i))
+            .arg(codecs[i])
+            .arg(format!("-metadata:s:s:{}", i))
+            .arg(format!("language={}", iso639_2(language)))
+            .arg(format!("-metadata:s:s:{}", i))
+            .arg(format!("title={}", title))
+            .arg(format!("-disposition:s:{}", i))
+            .arg(disposition);
+    }
+
+    if let Some(parent) = Path::new(&output_path).parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    cmd.arg(&output_path).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    println!(
+        "Вшивание {} дорожек субтитров в {} → {}",
+        tracks.len(),
+        video_path.display(),
+        output_path
+    );
+    let output = cmd.output().await.map_err(|e| format!("Ошибка запуска FFmpeg: {}", e));
+    let _ = std::fs::remove_dir_all(&temp_dir);
+    let output = output?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("FFmpeg не смог вшить субтитры: {}", stderr));
+    }
+    println!("Видео с субтитрами сохранено: {}", output_path);
+    Ok(VideoSubtitlesResult { output_path, untranslated })
+}
+
+/// Оформление субтитров без стилей ASS (SRT, VTT и т.п.) при вшивании в картинку.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Default)]
+pub struct BurnInOptions {
+    #[serde(default)]
+    pub font_name: Option<String>,
+    #[serde(default)]
+    pub font_size: Option<u32>,
+    /// Толщина обводки в пикселях
+    #[serde(default)]
+    pub outline: Option<f64>,
+    /// Качество x264 (CRF), по умолчанию 18
+    #[serde(default)]
+    pub crf: Option<u32>,
+}
+
+/// Рендеры, которые можно отменить: operation_id → токен отмены
+fn render_jobs() -> &'static Mutex<HashMap<String, CancellationToken>> {
+    static JOBS: OnceLock<Mutex<HashMap<String, CancellationToken>>> = OnceLock::new();
+    JOBS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Рендерит связанное видео с субтитрами, вшитыми в картинку. Стили берутся из ASS,
+/// если файл был импортирован из ASS, иначе — шрифт, размер и обводка из `options`.
+/// Прогресс идёт событиями `ai_progress` с operation_id `burn_in_<file_id>`;
+/// отмена — `cancel_burn_in` с тем же operation_id. Текст выбирается по `export_options`,
+/// как в `export_subtitles`.
+#[tauri::command]
+pub async fn burn_in_subtitles(
+    project_path: String,
+    subtitle_file_id: String,
+    output_path: String,
+    options: Option<BurnInOptions>,
+    export_options: Option<ExportOptions>,
+    app_handle: tauri::AppHandle,
+) -> Result<VideoSubtitlesResult, String> {
+    let options = options.unwrap_or_default();
+    let export = video_export_options(export_options);
+    let project_path_buf = Path::new(&project_path);
+    let project = Project::load_from_file(project_path_buf, &app_handle)?;
+
+    let file = project
+        .files
+        .iter()
+        .find(|f| f.id == subtitle_file_id && f.file_type == ProjectType::Subtitle)
+        .ok_or("Файл субтитров не найден в проекте")?;
+    let video = file
+        .linked_file_id
+        .as_ref()
+        .and_then(|id| project.files.iter().find(|f| &f.id == id))
+        .ok_or("К субтитрам не привязано видео")?;
+    let video_path = std::path::absolute(project_path_buf.join(&video.path)).map_err(|e| e.to_string())?;
+    if !video_path.exists() {
+        return Err(format!("Видео файл не найден: {}", video_path.display()));
+    }
+    let output_abs = std::path::absolute(&output_path).map_err(|e| e.to_string())?;
+    if let Some(parent) = output_abs.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    if !is_ffmpeg_available().await {
+        return Err("FFmpeg не установлен в системе".to_string());
+    }
+    let duration = match video.duration {
+        Some(d) if d > 0.0 => Some(d),
+        _ => media_duration_seconds(&video_path).await.ok(),
+    };
+
+    // ffmpeg запускаем из временной папки: имя файла в фильтре `subtitles` без путей
+    // не требует экранирования `:` и `\` (пути Windows)
+    let temp_dir = std::env::temp_dir().join(format!("subtitle_studio_burn_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&temp_dir).map_err(|e| e.to_string())?;
+    let is_ass = matches!(file.subtitle_meta, Some(SubtitleFileMeta::Ass(_)));
+    let untranslated = match write_temp_subtitles(
+        &project,
+        file,
+        if is_ass { "ass" } else { "srt" },
+        &temp_dir.join(if is_ass { "burn_in.ass" } else { "burn_in.srt" }),
+        &export,
+    ) {
+        Ok(untranslated) => untranslated,
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&temp_dir);
+            return Err(e);
+        }
+    };
+    let filter = if is_ass {
+        "subtitles=burn_in.ass".to_string()
+    } else {
+        let font = options.font_name.as_deref().unwrap_or("Arial").replace(['\'', ',', ':'], "");
+        format!(
+            "subtitles=burn_in.srt:force_style='FontName={},FontSize={},Outline={},BorderStyle=1'",
+            font,
+            options.font_size.unwrap_or(24),
+            options.outline.unwrap_or(2.0)
+        )
+    };
+
+    let operation_id = format!("burn_in_{}", subtitle_file_id);
+    let token = CancellationToken::new();
+    if let Ok(mut jobs) = render_jobs().lock() {
+        if jobs.contains_key(&operation_id) {
+            let _ = std::fs::remove_dir_all(&temp_dir);
+            return Err("Рендер этих субтитров уже выполняется".to_string());
+        }
+        jobs.insert(operation_id.clone(), token.clone());
+    }
+
+    let emit = |event: ProgressEvent| {
+        let _ = app_handle.emit("ai_progress", ProgressPayload {
+            operation_id: operation_id.clone(),
+            event,
+        });
+    };
+    emit(ProgressEvent::Started { total_steps: 1, description: "Рендер видео с субтитрами".to_string() });
+
+    let result = run_burn_in(&video_path, &filter, &output_abs, &temp_dir, options.crf.unwrap_or(18), duration, &token, &emit).await;
+
+    if let Ok(mut jobs) = render_jobs().lock() {
+        jobs.remove(&operation_id);
+    }
+    let _ = std::fs::remove_dir_all(&temp_dir);
+
+    match result {
+        Ok(()) => {
+            emit(ProgressEvent::Completed { result_count: 1 });
+            println!("Видео с вшитыми субтитрами сохранено: {}", output_path);
+            Ok(VideoSubtitlesResult { output_path, untranslated: vec![untranslated] })
+        }
+        Err(message) => {
+            // Недописанный файл не оставляем
+            let _ = std::fs::remove_file(&output_abs);
+            emit(ProgressEvent::Error { message: message.clone() });
+            Err(message)
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_burn_in(
+    video_path: &Path,
+    filter: &str,
+    output_path: &Path,
+    work_dir: &Path,
+    crf: u32,
+    duration: Option<f64>,
+    token: &CancellationToken,
+    emit: &impl Fn(ProgressEvent),
+) -> Result<(), String> {
+    println!("Рендер: ffmpeg -i {} -vf \"{}\" → {}", video_path.display(), filter, output_path.display());
+
+    let mut child = Command::new("ffmpeg")
+        .current_dir(work_dir)
+        .arg("-y")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-nostats")
+        .arg("-progress")
+        .arg("pipe:1")
+        .arg("-i")
+        .arg(video_path)
+        .arg("-vf")
+        .arg(filter)
+        .arg("-c:v")
+        .arg("libx264")
+        .arg("-preset")
+        .arg("medium")
+        .arg("-crf")
+        .arg(crf.to_string())
+        .arg("-c:a")
+        .arg("copy")
+        .arg(output_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Ошибка запуска FFmpeg: {}", e))?;
+
+    let stdout = child.stdout.take().ok_or("Нет вывода FFmpeg")?;
+    let mut stderr = child.stderr.take().ok_or("Нет вывода FFmpeg")?;
+    let stderr_task = tokio::spawn(async move {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text).await;
+        text
+    });
+
+    // `-progress` пишет блоки `ключ=значение`, каждый заканчивается строкой `progress=…`
+    let progress = async {
+        let mut lines = BufReader::new(stdout).lines();
+        let mut out_time = 0.0;
+        while let Ok(Some(line)) = lines.next_line().await {
+            let Some((key, value)) = line.split_once('=') else { continue };
+            match key {
+                "out_time_us" | "out_time_ms" => {
+                    // Оба ключа — в микросекундах (out_time_ms назван так исторически)
+                    if let Ok(us) = value.trim().parse::<f64>() {
+                        out_time = us / 1_000_000.0;
+                    }
+                }
+                "progress" => {
+                    let fraction = match duration {
+                        Some(total) if total > 0.0 => (out_time / total).clamp(0.0, 1.0),
+                        _ => 0.0,
+                    };
+                    emit(ProgressEvent::InProgress {
+                        step: 1,
+                        progress: fraction,
+                        description: format!("Рендер: {:.0}%", fraction * 100.0),
+                    });
+                }
+                _ => {}
+            }
+        }
+    };
+
+    let status = tokio::select! {
+        _ = token.cancelled() => {
+            let _ = child.kill().await;
+            return Err("Рендер отменён".to_string());
+        }
+        (status, _) = async { tokio::join!(child.wait(), progress) } => status,
+    };
+
+    let status = status.map_err(|e| format!("Ошибка FFmpeg: {}", e))?;
+    if !status.success() {
+        let stderr = stderr_task.await.unwrap_or_default();
+        return Err(format!("FFmpeg завершился с ошибкой: {}", stderr.trim()));
+    }
+    Ok(())
+}
+
+/// Отменяет рендер `burn_in_subtitles`. Возвращает false, если такого рендера нет.
+#[tauri::command]
+pub async fn cancel_burn_in(operation_id: String) -> Result<bool, String> {
+    let jobs = render_jobs().lock().map_err(|e| e.to_string())?;
+    match jobs.get(&operation_id) {
+        Some(token) => {
+            token.cancel();
+            println!("Рендер {} отменяется", operation_id);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Код языка ISO 639-2 для тега дорожки (MKV/MP4) из кода ISO 639-1 или названия языка.
+pub fn iso639_2(language: &str) -> String {
+    let language = language.trim().to_lowercase();
+    let primary = language.split(['-', '_']).next().unwrap_or("");
+    let code = match primary {
+        "en" | "english" => "eng",
+        "ru" | "russian" => "rus",
+        "es" | "spanish" => "spa",
+        "fr" | "french" => "fre",
+        "de" | "german" => "ger",
+        "it" | "italian" => "ita",
+        "pt" | "portuguese" => "por",
+        "uk" | "ukrainian" => "ukr",
+        "be" | "belarusian" => "bel",
+        "pl" | "polish" => "pol",
+        "cs" | "czech" => "cze",
+        "nl" | "dutch" => "dut",
+        "sv" | "swedish" => "swe",
+        "no" | "nb" | "nn" | "norwegian" => "nor",
+        "da" | "danish" => "dan",
+        "fi" | "finnish" => "fin",
+        "tr" | "turkish" => "tur",
+        "el" | "greek" => "gre",
+        "he" | "hebrew" => "heb",
+        "ar" | "arabic" => "ara",
+        "hi" | "hindi" => "hin",
+        "zh" | "chinese" => "chi",
+        "ja" | "japanese" => "jpn",
+        "ko" | "korean" => "kor",
+        "kk" | "kazakh" => "kaz",
+        "hu" | "hungarian" => "hun",
+        "ro" | "romanian" => "rum",
+        "bg" | "bulgarian" => "bul",
+        "sr" | "serbian" => "srp",
+        "hr" | "croatian" => "hrv",
+        "vi" | "vietnamese" => "vie",
+        "th" | "thai" => "tha",
+        "id" | "indonesian" => "ind",
+        code if code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()) => code,
+        _ => "und",
+    };
+    code.to_string()
+}
+
+/// Название дорожки по умолчанию
+fn language_title(language: &str) -> String {
+    match iso639_2(language).as_str() {
+        "eng" => "English",
+        "rus" => "Русский",
+        "spa" => "Español",
+        "fre" => "Français",
+        "ger" => "Deutsch",
+        "ita" => "Italiano",
+        _ => return language.to_string(),
+    }
+    .to_string()
+}
+
 /// Проверяет доступность FFmpeg в системе
 async fn is_ffmpeg_available() -> bool {
     let output = Command::new("ffmpeg")
@@ -159,9 +670,138 @@ pub struct FormatInfo {
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct StreamInfo {
+    /// Номер потока в контейнере (для `-map 0:N`)
+    #[serde(default)]
+    pub index: i32,
     pub codec_type: String,
+    /// У вложений MKV (шрифты) кодека может не быть
+    #[serde(default)]
     pub codec_name: String,
     pub duration: Option<String>,
     pub channels: Option<i32>,
     pub sample_rate: Option<String>,
+    #[serde(default)]
+    pub r_frame_rate: Option<String>,
+    #[serde(default)]
+    pub avg_frame_rate: Option<String>,
+    #[serde(default)]
+    pub tags: Option<StreamTags>,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct StreamTags {
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+/// Дорожка субтитров внутри MKV/MP4.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct EmbeddedSubtitleTrack {
+    pub index: i32,
+    pub codec_name: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    /// Текстовая дорожка, которую можно извлечь; PGS/VobSub — картинки, их так не импортировать
+    pub extractable: bool,
+}
+
+/// Расширение файла и кодек ffmpeg для извлечения текстовой дорожки.
+/// `mov_text` (MP4) в файл как есть не пишется — конвертируем в SRT.
+pub fn subtitle_extraction_target(codec_name: &str) -> Option<(&'static str, &'static str)> {
+    match codec_name {
+        "subrip" | "srt" | "mov_text" | "text" | "tx3g" => Some(("srt", "srt")),
+        "ass" | "ssa" => Some(("ass", "copy")),
+        "webvtt" => Some(("vtt", "webvtt")),
+        _ => None,
+    }
+}
+
+/// Список дорожек субтитров в видеофайле.
+#[tauri::command]
+pub async fn list_subtitle_tracks(video_path: String) -> Result<Vec<EmbeddedSubtitleTrack>, String> {
+    let info = get_media_info(video_path).await?;
+    let tracks = info
+        .streams
+        .into_iter()
+        .filter(|s| s.codec_type == "subtitle")
+        .map(|s| {
+            let tags = s.tags.unwrap_or_default();
+            EmbeddedSubtitleTrack {
+                index: s.index,
+                extractable: subtitle_extraction_target(&s.codec_name).is_some(),
+                codec_name: s.codec_name,
+                language: tags.language,
+                title: tags.title,
+            }
+        })
+        .collect();
+    Ok(tracks)
+}
+
+/// Извлекает поток субтитров `stream_index` в `output_path` через ffmpeg.
+pub async fn extract_subtitle_stream(
+    video_path: &Path,
+    stream_index: i32,
+    ffmpeg_codec: &str,
+    output_path: &Path,
+) -> Result<(), String> {
+    if !is_ffmpeg_available().await {
+        return Err("FFmpeg не установлен в системе".to_string());
+    }
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    println!(
+        "Извлечение субтитров: ffmpeg -i {} -map 0:{} -c:s {} -y {}",
+        video_path.display(),
+        stream_index,
+        ffmpeg_codec,
+        output_path.display()
+    );
+
+    let output = Command::new("ffmpeg")
+        .arg("-i")
+        .arg(video_path)
+        .arg("-map")
+        .arg(format!("0:{}", stream_index))
+        .arg("-c:s")
+        .arg(ffmpeg_codec)
+        .arg("-y")
+        .arg(output_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| format!("Ошибка запуска FFmpeg: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("FFmpeg не смог извлечь субтитры: {}", stderr));
+    }
+    let size = std::fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    if size == 0 {
+        return Err("Дорожка субтитров пуста".to_string());
+    }
+    Ok(())
+}
+
+/// Частота кадров первого видеопотока. Средняя частота надёжнее для VFR-файлов,
+/// `r_frame_rate` — запасной вариант (бывает `90000/1` у некоторых контейнеров).
+pub async fn video_frame_rate(video_path: &Path) -> Result<FrameRate, String> {
+    let info = get_media_info(video_path.to_string_lossy().to_string()).await?;
+    let stream = info
+        .streams
+        .iter()
+        .find(|s| s.codec_type == "video")
+        .ok_or("В файле нет видеопотока")?;
+
+    let rate = [&stream.avg_frame_rate, &stream.r_frame_rate]
+        .into_iter()
+        .flatten()
+        .filter_map(|value| FrameRate::parse(value))
+        .find(|rate| (1.0..=240.0).contains(&rate.fps()));
+    rate.ok_or_else(|| "Не удалось определить частоту кадров видео".to_string())
 }
\ No newline at end of file
@@ -1,6 +1,9 @@
 use tauri::Manager;
-use crate::project::{Project, GlossaryEntry, SubtitleSegment};
+use crate::project::{Project, GlossaryEntry, SubtitleSegment, Timestamp};
 use crate::cache::Cache;
+use crate::segmentation::{self, SegmentationOptions};
+use crate::transcription::TranscriptionSettings;
+use crate::translation::TranslationSettings;
 use crate::types::ProjectStructure;
 use std::path::Path;
 use serde::{Deserialize, Serialize};
@@ -94,6 +97,36 @@ pub async fn add_glossary_entry(
     Ok(())
 }
 
+/// Движок распознавания речи проекта (OpenAI или локальный)
+#[tauri::command]
+pub async fn update_transcription_settings(
+    project_path: String,
+    settings: TranscriptionSettings,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let project_path_buf = Path::new(&project_path);
+    let mut project = Project::load_from_file(project_path_buf, &app_handle)?;
+    project.transcription = settings;
+    project.updated_at = chrono::Utc::now().to_rfc3339();
+    project.save_to_file(&app_handle)?;
+    Ok(())
+}
+
+/// Провайдер машинного перевода проекта (OpenAI, совместимый сервер или DeepL)
+#[tauri::command]
+pub async fn update_translation_settings(
+    project_path: String,
+    settings: TranslationSettings,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let project_path_buf = Path::new(&project_path);
+    let mut project = Project::load_from_file(project_path_buf, &app_handle)?;
+    project.translation = settings;
+    project.updated_at = chrono::Utc::now().to_rfc3339();
+    project.save_to_file(&app_handle)?;
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn update_subtitle_segment(
     project_path: String,
@@ -109,11 +142,16 @@ pub async fn update_subtitle_segment(
         if let Some(segments) = file.subtitle_segments.as_mut() {
             if let Some(segment) = segments.iter_mut().find(|s| s.id == segment_id) {
                 if let Some(text) = &updates.text {
+                    // Слова распознавания относятся к старому тексту
+                    if segment.text != *text {
+                        segment.words = None;
+                    }
                     segment.text = text.clone();
                 }
                 if let Some(translation) = &updates.translation {
                     segment.translation = Some(translation.clone());
                 }
+                let (old_start, old_end) = (segment.start, segment.end);
                 if let Some(start) = updates.start {
                     segment.start = start;
                     segment.duration = segment.end - segment.start;
@@ -122,6 +160,17 @@ pub async fn update_subtitle_segment(
                     segment.end = end;
                     segment.duration = segment.end - segment.start;
                 }
+                // Реплику передвинули целиком — слова едут вместе с ней; если её растянули
+                // или обрезали, старые тайминги слов к ней больше не относятся
+                let shift = segment.start - old_start;
+                if segment.end - old_end == shift {
+                    for word in segment.words.iter_mut().flatten() {
+                        word.start = (word.start + shift).max(Timestamp::ZERO);
+                        word.end = (word.end + shift).max(word.start);
+                    }
+                } else {
+                    segment.words = None;
+                }
                 
                 file.updated_at = chrono::Utc::now().to_rfc3339();
                 project.updated_at = chrono::Utc::now().to_rfc3339();
@@ -151,7 +200,8 @@ pub async fn create_empty_segments(
             .as_ref()
             .and_then(|segs| segs.last())
             .map(|last| last.end)
-            .unwrap_or(0.0);
+            .unwrap_or(Timestamp::ZERO);
+        let step = Timestamp::from_seconds(duration_per_segment);
         
         let mut new_segments = Vec::new();
         
@@ -161,17 +211,19 @@ pub async fn create_empty_segments(
                 .map(|segs| segs.iter().map(|s| s.id).max().unwrap_or(0) + i + 1)
                 .unwrap_or(i + 1);
             
-            let start = start_time + (i as f64) * duration_per_segment;
-            let end = start + duration_per_segment;
+            let start = Timestamp::from_millis(start_time.millis() + i as i64 * step.millis());
+            let end = start + step;
             
             let segment = SubtitleSegment {
                 id: segment_id,
                 start,
                 end,
-                duration: duration_per_segment,
+                duration: step,
                 text: String::new(),
                 translation: None,
                 flags: None,
+                words: None,
+                format_meta: None,
             };
             
             new_segments.push(segment.clone());
@@ -217,10 +269,12 @@ pub async fn insert_subtitle_segment(
     if end <= start {
         return Err("Конец должен быть больше начала".to_string());
     }
-    let dur = end - start;
-    if dur < MIN_DUR {
+    if end - start < MIN_DUR {
         return Err(format!("Минимальная длительность сегмента — {} с", MIN_DUR));
     }
+    let start = Timestamp::from_seconds(start);
+    let end = Timestamp::from_seconds(end);
+    let dur = end - start;
 
     let project_path_buf = Path::new(&project_path);
     let mut project = Project::load_from_file(project_path_buf, &app_handle)?;
@@ -236,19 +290,16 @@ pub async fn insert_subtitle_segment(
             text: String::new(),
             translation: None,
             flags: None,
+            words: None,
+            format_meta: None,
         };
 
         segments.push(segment);
-        segments.sort_by(|a, b| {
-            a.start
-                .partial_cmp(&b.start)
-                .unwrap_or(std::cmp::Ordering::Equal)
-                .then_with(|| a.id.cmp(&b.id))
-        });
+        segments.sort_by(|a, b| a.start.cmp(&b.start).then_with(|| a.id.cmp(&b.id)));
 
         let inserted_pos = segments
             .iter()
-            .position(|s| (s.start - start).abs() < 1e-9 && (s.end - end).abs() < 1e-9)
+            .position(|s| s.start == start && s.end == end)
             .ok_or_else(|| "Не удалось сопоставить вставленный сегмент".to_string())?;
 
         /* Порядковые id 1..n по времени — номер субтитра совпадает с позицией после вставки */
@@ -318,6 +369,44 @@ pub async fn delete_subtitle_segment(
     }
 }
 
+/// Заново разбить файл на реплики по лимитам `options` (строки, длительность, скорость чтения).
+/// Берутся сохранённые тайминги слов, без них время слов оценивается по длине; перевод и разметка исходного формата
+/// при этом теряются, поэтому переведённые файлы не трогаем.
+#[tauri::command]
+pub async fn resegment_subtitles(
+    project_path: String,
+    file_id: String,
+    options: Option<SegmentationOptions>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<SubtitleSegment>, String> {
+    let project_path_buf = Path::new(&project_path);
+    let mut project = Project::load_from_file(project_path_buf, &app_handle)?;
+
+    let file = project
+        .files
+        .iter_mut()
+        .find(|f| f.id == file_id)
+        .ok_or_else(|| "Файл не найден в проекте".to_string())?;
+    let segments = file.subtitle_segments.take().unwrap_or_default();
+    if segments.iter().any(|s| s.translation.as_deref().is_some_and(|t| !t.trim().is_empty())) {
+        file.subtitle_segments = Some(segments);
+        return Err("В файле уже есть перевод — повторная разбивка его сбросит".to_string());
+    }
+
+    let words: Vec<_> = segments
+        .iter()
+        .flat_map(|s| segmentation::align_words(&s.text, s.start, s.end, s.words.as_deref().unwrap_or_default()))
+        .collect();
+    let resegmented = segmentation::resegment(&words, &options.unwrap_or_default());
+    println!("Разбивка на реплики: {} → {}", segments.len(), resegmented.len());
+
+    file.subtitle_segments = Some(resegmented.clone());
+    file.updated_at = chrono::Utc::now().to_rfc3339();
+    project.updated_at = chrono::Utc::now().to_rfc3339();
+    project.save_to_file(&app_handle)?;
+    Ok(resegmented)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProjectStatistics {
     pub total_segments: u32,
@@ -349,7 +438,7 @@ pub async fn get_project_statistics(
     for file in &project.files {
         if let Some(segments) = &file.subtitle_segments {
             total_segments += segments.len() as u32;
-            total_duration += segments.iter().map(|s| s.duration).sum::<f64>();
+            total_duration += segments.iter().map(|s| s.duration.as_seconds()).sum::<f64>();
             
             translated_segments += segments
                 .iter()
@@ -413,6 +502,7 @@ pub async fn find_and_replace_in_subtitles(
                     );
                     if new_text != segment.text {
                         segment.text = new_text;
+                        segment.words = None;
                         replaced = true;
                     }
                 }
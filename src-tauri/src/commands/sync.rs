@@ -1,19 +1,42 @@
 use tauri::Manager;
 use std::path::Path;
 use serde::{Deserialize, Serialize};
-use crate::project::SubtitleSegment;
+use crate::project::{Project, SubtitleSegment, Timestamp};
+use crate::subtitle_parser::framerate::FrameRate;
+use crate::vad::{self, VadOptions};
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SyncOptions {
     pub max_drift: f64, // Максимальный дрейф в секундах
     pub auto_correct: bool, // Автоматическая коррекция
+    /// Искать дрейф в пределах ± стольких секунд
+    pub search_window: f64,
+    /// Разные смещения по участкам (другая версия монтажа, рекламные паузы)
+    pub piecewise: bool,
+    /// Сколько секунд совпавшей речи должен дать новый участок, чтобы его выделить
+    pub split_penalty: f64,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self {
+            max_drift: 2.0,
+            auto_correct: true,
+            search_window: 30.0,
+            piecewise: false,
+            split_penalty: 10.0,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SyncResult {
     pub corrected_segments: Vec<SubtitleSegment>,
+    /// Дрейф самого длинного участка (при общем сдвиге — единственного)
     pub drift_detected: f64,
     pub corrections_applied: u32,
+    pub sections: Vec<SyncSection>,
 }
 
 #[tauri::command]
@@ -30,29 +53,35 @@ pub async fn sync_subtitles_with_video(
         return Err(format!("Видео файл не найден: {}", video_path));
     }
     
-    let options = options.unwrap_or(SyncOptions {
-        max_drift: 2.0,
-        auto_correct: true,
-    });
+    let options = options.unwrap_or_default();
     
     // Получаем аудио дорожку из видео
     let audio_path = extract_audio_for_sync(video_path_buf, &app_handle).await?;
     
     // Анализируем аудио для обнаружения речи
-    let speech_timestamps = detect_speech_timestamps(&audio_path).await?;
+    let speech_timestamps = vad::detect_speech_in_wav(Path::new(&audio_path), &VadOptions::default())?;
     
-    // Вычисляем дрейф
-    let drift = calculate_drift(&segments, &speech_timestamps)?;
+    // Сопоставляем реплики с речью (общий сдвиг или по участкам)
+    let sections = align_to_speech(
+        &segments,
+        &speech_timestamps,
+        options.search_window,
+        options.piecewise.then_some(options.split_penalty),
+    );
+    let drift = sections
+        .iter()
+        .max_by_key(|s| s.segment_count)
+        .map(|s| s.drift)
+        .unwrap_or(0.0);
     
     let mut corrected_segments = segments.clone();
     let mut corrections_applied = 0u32;
     
-    if drift.abs() > options.max_drift {
+    if sections.iter().any(|s| s.drift.abs() > options.max_drift) {
         if options.auto_correct {
             // Применяем коррекцию
-            corrected_segments = apply_drift_correction(segments, drift);
-            corrections_applied = corrected_segments.len() as u32;
-            println!("Применена коррекция дрейфа: {} сек", drift);
+            corrections_applied = apply_sections(&mut corrected_segments, &sections, options.max_drift);
+            println!("Применена коррекция дрейфа: {} участков, {} реплик", sections.len(), corrections_applied);
         } else {
             println!("Обнаружен дрейф: {} сек (превышает лимит {} сек)", 
                     drift, options.max_drift);
@@ -66,6 +95,7 @@ pub async fn sync_subtitles_with_video(
         corrected_segments,
         drift_detected: drift,
         corrections_applied,
+        sections,
     })
 }
 
@@ -109,110 +139,339 @@ async fn extract_audio_for_sync(
     Ok(audio_path_str)
 }
 
-async fn detect_speech_timestamps(audio_path: &str) -> Result<Vec<(f64, f64)>, String> {
+/// Шаг сетки активности при выравнивании — 10 мс
+const ALIGN_BIN_MS: i64 = 10;
+/// Шаг перебора смещений при поиске участков (в ячейках сетки); затем уточняется до 10 мс
+const ALIGN_COARSE_STEP: i64 = 10;
 
-    
-    use std::fs::File;
-    use std::io::Read;
-    
-    let mut file = File::open(audio_path)
-        .map_err(|e| format!("Ошибка открытия аудиофайла: {}", e))?;
-    
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)
-        .map_err(|e| format!("Ошибка чтения аудиофайла: {}", e))?;
-    
-    // Простой алгоритм: находим сегменты с амплитудой выше порога
-    const SAMPLE_RATE: u32 = 16000;
-    const CHANNELS: u32 = 1;
-    const BYTES_PER_SAMPLE: usize = 2; // 16-bit
-    
-    let samples: Vec<i16> = buffer
-        .chunks_exact(BYTES_PER_SAMPLE)
-        .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]))
-        .collect();
-    
-    let threshold = 500i16; // Порог амплитуды
-    let min_silence = SAMPLE_RATE as usize / 2; // Минимум 0.5 сек тишины между сегментами
-    
-    let mut speech_segments = Vec::new();
-    let mut in_speech = false;
-    let mut start_sample = 0;
-    let mut silence_count = 0usize;
-    
-    for (i, &sample) in samples.iter().enumerate() {
-        if sample.abs() > threshold {
-            if !in_speech {
-                in_speech = true;
-                start_sample = i;
-                silence_count = 0;
+/// Участок субтитров с одним смещением относительно речи
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyncSection {
+    pub first_segment_id: u32,
+    pub last_segment_id: u32,
+    pub segment_count: u32,
+    /// На сколько секунд субтитры опаздывают относительно речи (отрицательное — опережают)
+    pub drift: f64,
+    /// Доля длительности реплик участка, попавшая на речь после сдвига (0..1)
+    pub match_ratio: f64,
+}
+
+/// Речь на сетке 10 мс: префиксные суммы, чтобы считать пересечение с интервалом за O(1)
+struct SpeechActivity {
+    prefix: Vec<i64>,
+}
+
+impl SpeechActivity {
+    fn new(speech: &[(f64, f64)]) -> Self {
+        let to_bin = |t: f64| (t * 1000.0 / ALIGN_BIN_MS as f64).round().max(0.0) as usize;
+        let len = speech.iter().map(|&(_, end)| to_bin(end)).max().unwrap_or(0);
+        let mut active = vec![0i64; len];
+        for &(start, end) in speech {
+            for bin in active.iter_mut().take(to_bin(end)).skip(to_bin(start)) {
+                *bin = 1;
             }
-        } else {
-            if in_speech {
-                silence_count += 1;
-                if silence_count >= min_silence {
-                    in_speech = false;
-                    let end_sample = i.saturating_sub(silence_count);
-                    if end_sample > start_sample {
-                        let start_time = start_sample as f64 / SAMPLE_RATE as f64;
-                        let end_time = end_sample as f64 / SAMPLE_RATE as f64;
-                        speech_segments.push((start_time, end_time));
-                    }
-                }
+        }
+        let mut prefix = Vec::with_capacity(len + 1);
+        prefix.push(0);
+        for bin in active {
+            prefix.push(prefix.last().copied().unwrap_or(0) + bin);
+        }
+        SpeechActivity { prefix }
+    }
+
+    /// Число ячеек речи в `[from, to)`; вне записи — тишина
+    fn speech_bins(&self, from: i64, to: i64) -> i64 {
+        let last = self.prefix.len() as i64 - 1;
+        let clamp = |bin: i64| bin.clamp(0, last) as usize;
+        self.prefix[clamp(to)] - self.prefix[clamp(from)]
+    }
+}
+
+/// Реплика в ячейках сетки
+fn cue_bins(seg: &SubtitleSegment) -> (i64, i64) {
+    (seg.start.millis() / ALIGN_BIN_MS, seg.end.millis() / ALIGN_BIN_MS)
+}
+
+/// Корреляция реплики с речью при дрейфе `lag`: +1 за ячейку на речи, −1 за ячейку на тишине
+fn cue_score(activity: &SpeechActivity, (start, end): (i64, i64), lag: i64) -> i64 {
+    2 * activity.speech_bins(start - lag, end - lag) - (end - start)
+}
+
+/// Взаимная корреляция «реплика есть/нет» с детектированной речью. Для каждой реплики
+/// перебираются дрейфы в пределах `window` секунд; без `split_penalty` ищется один общий
+/// дрейф, иначе динамикой выбирается кусочно-постоянный: новый участок начинается, только
+/// если выигрыш совпадения больше штрафа (в секундах совпавшей речи).
+fn align_to_speech(
+    segments: &[SubtitleSegment],
+    speech: &[(f64, f64)],
+    window: f64,
+    split_penalty: Option<f64>,
+) -> Vec<SyncSection> {
+    if segments.is_empty() || speech.is_empty() {
+        return Vec::new();
+    }
+    let activity = SpeechActivity::new(speech);
+    let mut order: Vec<usize> = (0..segments.len()).collect();
+    order.sort_by_key(|&i| segments[i].start);
+    let cues: Vec<(i64, i64)> = order.iter().map(|&i| cue_bins(&segments[i])).collect();
+
+    let reach = (window.max(0.0) * 1000.0) as i64 / ALIGN_BIN_MS / ALIGN_COARSE_STEP;
+    // Сначала малые по модулю дрейфы — при равном счёте выигрывает меньший сдвиг
+    let mut lags: Vec<i64> = (-reach..=reach).map(|k| k * ALIGN_COARSE_STEP).collect();
+    lags.sort_by_key(|lag| lag.abs());
+
+    let assignment = match split_penalty {
+        None => {
+            let best = lags
+                .iter()
+                .copied()
+                .max_by_key(|&lag| {
+                    let total: i64 = cues.iter().map(|&cue| cue_score(&activity, cue, lag)).sum();
+                    (total, -lag.abs())
+                })
+                .unwrap_or(0);
+            vec![best; cues.len()]
+        }
+        Some(penalty) => {
+            let penalty = (penalty.max(0.0) * 1000.0) as i64 / ALIGN_BIN_MS;
+            piecewise_lags(&activity, &cues, &lags, penalty)
+        }
+    };
+
+    // Участки подряд идущих реплик с одним дрейфом, уточнённым до шага сетки
+    let mut sections = Vec::new();
+    let mut from = 0usize;
+    while from < cues.len() {
+        let mut to = from;
+        while to + 1 < cues.len() && assignment[to + 1] == assignment[from] {
+            to += 1;
+        }
+        let part = &cues[from..=to];
+        let coarse = assignment[from];
+        let lag = (coarse - ALIGN_COARSE_STEP..=coarse + ALIGN_COARSE_STEP)
+            .max_by_key(|&lag| {
+                let total: i64 = part.iter().map(|&cue| cue_score(&activity, cue, lag)).sum();
+                (total, -(lag - coarse).abs())
+            })
+            .unwrap_or(coarse);
+        let length: i64 = part.iter().map(|&(start, end)| end - start).sum();
+        let matched: i64 = part.iter().map(|&(start, end)| activity.speech_bins(start - lag, end - lag)).sum();
+        sections.push(SyncSection {
+            first_segment_id: segments[order[from]].id,
+            last_segment_id: segments[order[to]].id,
+            segment_count: part.len() as u32,
+            drift: (lag * ALIGN_BIN_MS) as f64 / 1000.0,
+            match_ratio: if length > 0 { matched as f64 / length as f64 } else { 0.0 },
+        });
+        from = to + 1;
+    }
+    sections
+}
+
+/// Витерби по репликам: состояние — дрейф, смена дрейфа стоит `penalty`
+fn piecewise_lags(activity: &SpeechActivity, cues: &[(i64, i64)], lags: &[i64], penalty: i64) -> Vec<i64> {
+    let k = lags.len();
+    let mut score: Vec<i64> = lags.iter().map(|&lag| cue_score(activity, cues[0], lag)).collect();
+    // Для каждой реплики: пришли ли в дрейф из другого и из какого (лучший на прошлом шаге)
+    let mut switched = vec![vec![false; k]; cues.len()];
+    let mut prev_best = vec![0usize; cues.len()];
+
+    for (i, &cue) in cues.iter().enumerate().skip(1) {
+        let best = (0..k).max_by_key(|&j| (score[j], std::cmp::Reverse(j))).unwrap_or(0);
+        prev_best[i] = best;
+        let switch_score = score[best] - penalty;
+        for j in 0..k {
+            if switch_score > score[j] {
+                score[j] = switch_score;
+                switched[i][j] = true;
             }
+            score[j] += cue_score(activity, cue, lags[j]);
         }
     }
-    
-    // Завершаем последний сегмент если нужно
-    if in_speech {
-        let end_sample = samples.len();
-        if end_sample > start_sample {
-            let start_time = start_sample as f64 / SAMPLE_RATE as f64;
-            let end_time = end_sample as f64 / SAMPLE_RATE as f64;
-            speech_segments.push((start_time, end_time));
+
+    let mut state = (0..k).max_by_key(|&j| (score[j], std::cmp::Reverse(j))).unwrap_or(0);
+    let mut assignment = vec![0i64; cues.len()];
+    for i in (0..cues.len()).rev() {
+        assignment[i] = lags[state];
+        if switched[i][state] {
+            state = prev_best[i];
         }
     }
-    
-    Ok(speech_segments)
+    assignment
 }
 
-fn calculate_drift(
-    subtitle_segments: &[SubtitleSegment],
-    speech_timestamps: &[(f64, f64)],
-) -> Result<f64, String> {
-    if subtitle_segments.is_empty() || speech_timestamps.is_empty() {
-        return Ok(0.0);
+/// Сдвигает реплики участков, дрейф которых больше `max_drift`; возвращает число сдвинутых
+fn apply_sections(segments: &mut [SubtitleSegment], sections: &[SyncSection], max_drift: f64) -> u32 {
+    let mut order: Vec<usize> = (0..segments.len()).collect();
+    order.sort_by_key(|&i| segments[i].start);
+    let mut corrected = 0u32;
+    let mut cursor = 0usize;
+    for section in sections {
+        let drift = Timestamp::from_seconds(section.drift);
+        let apply = section.drift.abs() > max_drift;
+        while cursor < order.len() {
+            let seg = &mut segments[order[cursor]];
+            let last = seg.id == section.last_segment_id;
+            cursor += 1;
+            if apply {
+                seg.start = (seg.start - drift).max(Timestamp::ZERO);
+                seg.end = (seg.end - drift).max(seg.start);
+                seg.duration = seg.end - seg.start;
+                for word in seg.words.iter_mut().flatten() {
+                    word.start = (word.start - drift).max(Timestamp::ZERO);
+                    word.end = (word.end - drift).max(word.start);
+                }
+                corrected += 1;
+            }
+            if last {
+                break;
+            }
+        }
     }
-    
-    // Находим первые и последние совпадающие сегменты
-    let first_subtitle = subtitle_segments[0].start;
-    let last_subtitle = subtitle_segments.last().unwrap().end;
-    
-    let first_speech = speech_timestamps[0].0;
-    let last_speech = speech_timestamps.last().unwrap().1;
-    
-    // Вычисляем дрейф как среднее от начального и конечного смещения
-    let start_drift = first_subtitle - first_speech;
-    let end_drift = last_subtitle - last_speech;
-    
-    let average_drift = (start_drift + end_drift) / 2.0;
-    
-    Ok(average_drift)
+    corrected
 }
 
-fn apply_drift_correction(
-    segments: Vec<SubtitleSegment>,
-    drift: f64,
-) -> Vec<SubtitleSegment> {
-    segments.into_iter().map(|mut seg| {
-        seg.start -= drift;
-        seg.end -= drift;
+/// Ручное преобразование таймингов (без анализа аудио).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind")]
+pub enum TimingTransform {
+    /// Сдвиг на `offset_ms` миллисекунд (может быть отрицательным)
+    Shift { offset_ms: i64 },
+    /// Растяжение: `t' = origin + (t - origin) * ratio`, `origin` в секундах
+    Scale {
+        ratio: f64,
+        #[serde(default)]
+        origin: f64,
+    },
+    /// Пересчёт под другую частоту кадров: `25` → `23.976` растягивает на 25/23.976
+    FrameRate { from: String, to: String },
+    /// Линейная подгонка по двум опорам: реплика `first_id` должна начинаться в `first_time`,
+    /// реплика `second_id` — в `second_time` (секунды)
+    TwoPoint {
+        first_id: u32,
+        first_time: f64,
+        second_id: u32,
+        second_time: f64,
+    },
+}
+
+/// Сдвигает/растягивает тайминги файла проекта и сохраняет результат. `segment_ids` — только
+/// выбранные реплики (по умолчанию все). Возвращает сегменты файла после изменения.
+#[tauri::command]
+pub async fn transform_subtitle_timing(
+    project_path: String,
+    file_id: String,
+    transform: TimingTransform,
+    segment_ids: Option<Vec<u32>>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<SubtitleSegment>, String> {
+    let mut project = Project::load_from_file(Path::new(&project_path), &app_handle)?;
+    let file = project
+        .files
+        .iter_mut()
+        .find(|f| f.id == file_id)
+        .ok_or_else(|| "Файл не найден в проекте".to_string())?;
+    let segments = file
+        .subtitle_segments
+        .as_mut()
+        .ok_or_else(|| "У файла нет субтитров".to_string())?;
+
+    let map = timing_map(&transform, segments)?;
+    let changed = apply_timing_map(segments, segment_ids.as_deref(), map);
+    println!("Тайминги изменены ({:?}): {} реплик", transform, changed);
+
+    let result = segments.clone();
+    file.updated_at = chrono::Utc::now().to_rfc3339();
+    project.updated_at = chrono::Utc::now().to_rfc3339();
+    project.save_to_file(&app_handle)?;
+    Ok(result)
+}
+
+/// Преобразование сводится к `t' = origin + offset + (t - origin) * ratio`
+#[derive(Debug, Clone, Copy)]
+struct LinearMap {
+    origin: Timestamp,
+    offset: Timestamp,
+    ratio: f64,
+}
+
+impl LinearMap {
+    fn apply(&self, time: Timestamp) -> Timestamp {
+        let scaled = ((time - self.origin).millis() as f64 * self.ratio).round() as i64;
+        self.origin + self.offset + Timestamp::from_millis(scaled)
+    }
+}
+
+fn timing_map(transform: &TimingTransform, segments: &[SubtitleSegment]) -> Result<LinearMap, String> {
+    let map = match transform {
+        TimingTransform::Shift { offset_ms } => LinearMap {
+            origin: Timestamp::ZERO,
+            offset: Timestamp::from_millis(*offset_ms),
+            ratio: 1.0,
+        },
+        TimingTransform::Scale { ratio, origin } => LinearMap {
+            origin: Timestamp::from_seconds(*origin),
+            offset: Timestamp::ZERO,
+            ratio: *ratio,
+        },
+        TimingTransform::FrameRate { from, to } => {
+            let parse = |value: &str| {
+                FrameRate::parse(value).ok_or_else(|| format!("Некорректная частота кадров: {}", value))
+            };
+            let (from, to) = (parse(from)?, parse(to)?);
+            LinearMap {
+                origin: Timestamp::ZERO,
+                offset: Timestamp::ZERO,
+                // Точная дробь: (from.num / from.den) / (to.num / to.den)
+                ratio: (from.numerator as f64 * to.denominator as f64)
+                    / (from.denominator as f64 * to.numerator as f64),
+            }
+        }
+        TimingTransform::TwoPoint { first_id, first_time, second_id, second_time } => {
+            let anchor = |id: u32| {
+                segments
+                    .iter()
+                    .find(|s| s.id == id)
+                    .map(|s| s.start)
+                    .ok_or_else(|| format!("Реплика №{} не найдена", id))
+            };
+            let (first_start, second_start) = (anchor(*first_id)?, anchor(*second_id)?);
+            let (first_target, second_target) =
+                (Timestamp::from_seconds(*first_time), Timestamp::from_seconds(*second_time));
+            if first_start == second_start {
+                return Err("Опорные реплики начинаются в одно время — нужны две разные точки".to_string());
+            }
+            LinearMap {
+                origin: first_start,
+                offset: first_target - first_start,
+                ratio: (second_target - first_target).millis() as f64
+                    / (second_start - first_start).millis() as f64,
+            }
+        }
+    };
+    if !map.ratio.is_finite() || map.ratio <= 0.0 {
+        return Err(format!("Некорректный коэффициент растяжения: {}", map.ratio));
+    }
+    Ok(map)
+}
+
+/// Применяет преобразование к выбранным репликам; время ниже нуля обрезается.
+/// Порядок по времени восстанавливается, id реплик не меняются.
+fn apply_timing_map(segments: &mut [SubtitleSegment], ids: Option<&[u32]>, map: LinearMap) -> u32 {
+    let mut changed = 0u32;
+    for seg in segments.iter_mut() {
+        if ids.is_some_and(|ids| !ids.contains(&seg.id)) {
+            continue;
+        }
+        seg.start = map.apply(seg.start).max(Timestamp::ZERO);
+        seg.end = map.apply(seg.end).max(seg.start);
         seg.duration = seg.end - seg.start;
-        // Убеждаемся, что время не отрицательное
-        if seg.start < 0.0 {
-            seg.start = 0.0;
-            seg.duration = seg.end;
+        for word in seg.words.iter_mut().flatten() {
+            word.start = map.apply(word.start).max(Timestamp::ZERO);
+            word.end = map.apply(word.end).max(word.start);
         }
-        seg
-    }).collect()
-}
\ No newline at end of file
+        changed += 1;
+    }
+    segments.sort_by(|a, b| a.start.cmp(&b.start).then_with(|| a.id.cmp(&b.id)));
+    changed
+}
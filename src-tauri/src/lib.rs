@@ -1,6 +1,10 @@
 pub mod commands;
 pub mod cache;
 pub mod project;
+pub mod segmentation;
 pub mod types;
 pub mod utils;
-pub mod subtitle_parser;
\ No newline at end of file
+pub mod subtitle_parser;
+pub mod transcription;
+pub mod translation;
+pub mod vad;
\ No newline at end of file
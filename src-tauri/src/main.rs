@@ -6,9 +6,13 @@
 mod commands;
 mod cache;
 mod project;
+mod segmentation;
 mod types;
 mod utils;
 mod subtitle_parser; 
+mod transcription;
+mod translation;
+mod vad;
 
 use tauri::Manager;
 use tauri_plugin_sql::{Migration, MigrationKind};
@@ -39,24 +43,34 @@ fn main() {
             commands::files::open_project,
             commands::files::save_project,
             commands::files::import_media,
+            commands::files::import_embedded_subtitles,
             commands::files::export_subtitles,
+            commands::files::batch_export_subtitles,
             commands::files::list_recent_projects,
             commands::ai::save_api_key,
             commands::ai::get_api_key_status,
+            commands::ai::save_provider_api_key,
             commands::ai::transcribe_audio,
             commands::ai::translate_batch,
             commands::project::create_project,
             commands::project::get_project_structure,
             commands::project::get_glossary,
             commands::project::update_glossary,
+            commands::project::update_transcription_settings,
+            commands::project::update_translation_settings,
             commands::project::add_glossary_entry,
             commands::project::update_subtitle_segment,
             commands::media::extract_audio_from_video,
+            commands::media::mux_subtitles,
+            commands::media::burn_in_subtitles,
+            commands::media::cancel_burn_in,
             commands::media::get_media_info,
+            commands::media::list_subtitle_tracks,
             commands::files::remove_file_from_project,
             commands::project::create_empty_segments,
             commands::project::insert_subtitle_segment,
             commands::project::delete_subtitle_segment,
+            commands::project::resegment_subtitles,
             commands::project::get_project_statistics,
             commands::project::find_and_replace_in_subtitles,
             commands::audio::generate_waveform,
@@ -65,6 +79,7 @@ fn main() {
             commands::files::list_project_directory_files,
             commands::files::import_existing_subtitles,
             commands::sync::sync_subtitles_with_video,
+            commands::sync::transform_subtitle_timing,
             commands::quality::check_translation_quality,
             commands::ai::auto_generate_glossary,
             commands::files::backup_project,
@@ -1,4 +1,6 @@
 pub mod model;
 pub mod glossary;
+pub mod time;
 
-pub use model::{Project, ProjectFile, SubtitleSegment, GlossaryEntry, ProjectType};
\ No newline at end of file
+pub use model::{Project, ProjectFile, SubtitleSegment, GlossaryEntry, ProjectType, SegmentFormatMeta, SubtitleFileMeta, SubtitleWord};
+pub use time::Timestamp;
\ No newline at end of file
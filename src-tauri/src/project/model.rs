@@ -2,6 +2,16 @@ use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::fs;
 use tauri::AppHandle;
+use super::time::Timestamp;
+use crate::subtitle_parser::ass::{AssEventMeta, AssScript};
+use crate::subtitle_parser::framerate::FrameRate;
+use crate::subtitle_parser::microdvd::MicroDvdCueMeta;
+use crate::subtitle_parser::scc::SccCueMeta;
+use crate::subtitle_parser::stl::{StlCueMeta, StlGsi};
+use crate::subtitle_parser::ttml::{TtmlCueMeta, TtmlDocument};
+use crate::subtitle_parser::vtt::{VttCueMeta, VttHeader};
+use crate::transcription::TranscriptionSettings;
+use crate::translation::TranslationSettings;
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub enum ProjectType {
@@ -18,6 +28,12 @@ pub struct Project {
     pub target_language: String,
     pub files: Vec<ProjectFile>,
     pub glossary: Vec<GlossaryEntry>,
+    /// Движок распознавания речи для этого проекта
+    #[serde(default)]
+    pub transcription: TranscriptionSettings,
+    /// Провайдер машинного перевода для этого проекта
+    #[serde(default)]
+    pub translation: TranslationSettings,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -33,6 +49,12 @@ pub struct ProjectFile {
     /// ID связанного файла: у Video — субтитры, у Subtitle — видео (один эпизод).
     #[serde(default)]
     pub linked_file_id: Option<String>,
+    /// Метаданные исходного файла субтитров (заголовок и стили ASS и т.п.) для экспорта без потерь.
+    #[serde(default)]
+    pub subtitle_meta: Option<SubtitleFileMeta>,
+    /// Частота кадров видео (или покадровых субтитров) — для MicroDVD и таймкодов.
+    #[serde(default)]
+    pub frame_rate: Option<FrameRate>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -40,12 +62,47 @@ pub struct ProjectFile {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SubtitleSegment {
     pub id: u32,
-    pub start: f64,
-    pub end: f64,
-    pub duration: f64,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub duration: Timestamp,
     pub text: String,
     pub translation: Option<String>,
     pub flags: Option<SegmentFlags>,
+    /// Данные строки, специфичные для формата источника (стиль/актёр/теги ASS и т.п.).
+    #[serde(default)]
+    pub format_meta: Option<SegmentFormatMeta>,
+    /// Слова с таймингами распознавания — для караоке и точной резки реплики по словам.
+    #[serde(default)]
+    pub words: Option<Vec<SubtitleWord>>,
+}
+
+/// Слово реплики с таймингом (пунктуация остаётся прикреплённой к слову)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SubtitleWord {
+    pub text: String,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    /// Уверенность распознавания 0..1, если модель её сообщает
+    #[serde(default)]
+    pub confidence: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum SegmentFormatMeta {
+    Ass(AssEventMeta),
+    Vtt(VttCueMeta),
+    Ttml(TtmlCueMeta),
+    Stl(StlCueMeta),
+    MicroDvd(MicroDvdCueMeta),
+    Scc(SccCueMeta),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum SubtitleFileMeta {
+    Ass(AssScript),
+    Vtt(VttHeader),
+    Ttml(TtmlDocument),
+    Stl(Box<StlGsi>),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -109,6 +166,8 @@ impl Project {
             target_language,
             files: vec![],
             glossary: vec![],
+            transcription: TranscriptionSettings::default(),
+            translation: TranslationSettings::default(),
             created_at: now.clone(),
             updated_at: now,
         };
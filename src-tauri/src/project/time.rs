@@ -0,0 +1,196 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+/// Момент (или длительность) в целых миллисекундах. Тайминги субтитров хранятся так,
+/// чтобы разбор и экспорт не накапливали ошибку округления `f64`. В JSON (project.json,
+/// команды Tauri) по-прежнему пишется числом секунд, например `1.999`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Timestamp(i64);
+
+impl Timestamp {
+    pub const ZERO: Timestamp = Timestamp(0);
+
+    pub const fn from_millis(millis: i64) -> Self {
+        Timestamp(millis)
+    }
+
+    /// Секунды округляются до ближайшей миллисекунды
+    pub fn from_seconds(seconds: f64) -> Self {
+        if seconds.is_finite() {
+            Timestamp((seconds * 1000.0).round() as i64)
+        } else {
+            Timestamp::ZERO
+        }
+    }
+
+    pub fn from_hmsm(hours: u32, minutes: u32, seconds: u32, millis: u32) -> Self {
+        Timestamp(((hours as i64 * 60 + minutes as i64) * 60 + seconds as i64) * 1000 + millis as i64)
+    }
+
+    pub const fn millis(self) -> i64 {
+        self.0
+    }
+
+    pub fn as_seconds(self) -> f64 {
+        self.0 as f64 / 1000.0
+    }
+
+    /// `(часы, минуты, секунды, миллисекунды)`; отрицательное время считается нулём
+    pub fn hmsm(self) -> (u32, u32, u32, u32) {
+        let ms = self.0.max(0);
+        (
+            (ms / 3_600_000) as u32,
+            (ms / 60_000 % 60) as u32,
+            (ms / 1000 % 60) as u32,
+            (ms % 1000) as u32,
+        )
+    }
+
+    pub fn max(self, other: Timestamp) -> Timestamp {
+        Ord::max(self, other)
+    }
+
+    pub fn min(self, other: Timestamp) -> Timestamp {
+        Ord::min(self, other)
+    }
+}
+
+impl Add for Timestamp {
+    type Output = Timestamp;
+    fn add(self, rhs: Timestamp) -> Timestamp {
+        Timestamp(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Timestamp {
+    type Output = Timestamp;
+    fn sub(self, rhs: Timestamp) -> Timestamp {
+        Timestamp(self.0 - rhs.0)
+    }
+}
+
+impl AddAssign for Timestamp {
+    fn add_assign(&mut self, rhs: Timestamp) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Timestamp {
+    fn sub_assign(&mut self, rhs: Timestamp) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.3}", self.as_seconds())
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.as_seconds())
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        f64::deserialize(deserializer).map(Timestamp::from_seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::files::{format_time_ass, format_time_srt, format_time_vtt};
+    use crate::subtitle_parser::{ass, srt, vtt};
+
+    /// Характерные моменты: ноль, границы секунд, минут и часов, больше суток
+    const SAMPLES_MS: [i64; 10] = [0, 1, 999, 1_000, 1_999, 59_999, 3_599_999, 3_600_000, 45_296_789, 90_000_001];
+
+    fn event_line(start: &str, end: &str) -> String {
+        format!(
+            "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,{},{},Default,,0,0,0,,текст\n",
+            start, end
+        )
+    }
+
+    #[test]
+    fn seconds_round_trip_through_millis() {
+        let sweep = (0..200_000).step_by(7);
+        for ms in SAMPLES_MS.into_iter().chain(sweep) {
+            let time = Timestamp::from_millis(ms);
+            assert_eq!(Timestamp::from_seconds(time.as_seconds()), time, "{} мс", ms);
+        }
+    }
+
+    #[test]
+    fn from_seconds_rounds_to_nearest_millisecond() {
+        assert_eq!(Timestamp::from_seconds(1.2344).millis(), 1234);
+        assert_eq!(Timestamp::from_seconds(1.2346).millis(), 1235);
+        // Типичная ошибка f64: 0.1 + 0.2 = 0.30000000000000004
+        assert_eq!(Timestamp::from_seconds(0.1 + 0.2).millis(), 300);
+        assert_eq!(Timestamp::from_seconds(f64::NAN), Timestamp::ZERO);
+        assert_eq!(Timestamp::from_seconds(f64::INFINITY), Timestamp::ZERO);
+    }
+
+    #[test]
+    fn json_keeps_seconds_and_round_trips() {
+        let time = Timestamp::from_millis(1_999);
+        let json = serde_json::to_string(&time).unwrap();
+        assert_eq!(json, "1.999");
+        assert_eq!(serde_json::from_str::<Timestamp>(&json).unwrap(), time);
+    }
+
+    #[test]
+    fn hmsm_round_trip() {
+        for ms in SAMPLES_MS {
+            let time = Timestamp::from_millis(ms);
+            let (h, m, s, millis) = time.hmsm();
+            assert_eq!(Timestamp::from_hmsm(h, m, s, millis), time);
+        }
+        assert_eq!(Timestamp::from_millis(-5).hmsm(), (0, 0, 0, 0));
+    }
+
+    #[test]
+    fn srt_time_round_trip() {
+        assert_eq!(format_time_srt(Timestamp::from_millis(45_296_789)), "12:34:56,789");
+        for ms in SAMPLES_MS {
+            let time = Timestamp::from_millis(ms);
+            let end = time + Timestamp::from_millis(1_000);
+            let content = format!("1\n{} --> {}\nтекст\n", format_time_srt(time), format_time_srt(end));
+            let (segments, _) = srt::parse(&content).unwrap();
+            assert_eq!((segments[0].start, segments[0].end), (time, end), "{}", content);
+        }
+    }
+
+    #[test]
+    fn vtt_time_round_trip() {
+        assert_eq!(format_time_vtt(Timestamp::from_millis(45_296_789)), "12:34:56.789");
+        for ms in SAMPLES_MS {
+            let time = Timestamp::from_millis(ms);
+            let end = time + Timestamp::from_millis(1_000);
+            let content = format!("WEBVTT\n\n{} --> {}\nтекст\n", format_time_vtt(time), format_time_vtt(end));
+            let (segments, _) = vtt::parse(&content).unwrap();
+            assert_eq!((segments[0].start, segments[0].end), (time, end), "{}", content);
+        }
+    }
+
+    #[test]
+    fn ass_time_round_trip_in_centiseconds() {
+        assert_eq!(format_time_ass(Timestamp::from_millis(45_296_789)), "12:34:56.79");
+        for ms in SAMPLES_MS {
+            let time = Timestamp::from_millis(ms);
+            let end = time + Timestamp::from_millis(1_000);
+            let content = event_line(&format_time_ass(time), &format_time_ass(end));
+            let (segments, _) = ass::parse(&content).unwrap();
+            // ASS хранит сотые: время округляется до ближайших 10 мс, а кратное им не меняется
+            let rounded = Timestamp::from_millis((ms + 5) / 10 * 10);
+            assert_eq!(segments[0].start, rounded, "{}", content);
+            let content = event_line(&format_time_ass(rounded), &format_time_ass(end));
+            let (segments, _) = ass::parse(&content).unwrap();
+            assert_eq!(segments[0].start, rounded, "{}", content);
+        }
+    }
+}
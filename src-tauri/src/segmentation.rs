@@ -0,0 +1,510 @@
+// Разбивка распознанной речи на читаемые реплики: по таймингам слов режем длинные сегменты
+// Whisper и склеиваем короткие, соблюдая лимиты строк, длительности и скорости чтения.
+
+use crate::project::{SubtitleSegment, SubtitleWord, Timestamp};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SegmentationOptions {
+    pub max_chars_per_line: usize,
+    pub max_lines: usize,
+    /// Максимальная длительность реплики, с
+    pub max_duration: f64,
+    /// Более короткие реплики сливаются с соседними или растягиваются, с
+    pub min_duration: f64,
+    /// Минимальный зазор между репликами, мс
+    pub min_gap_ms: i64,
+    /// Предел скорости чтения, символов в секунду
+    pub max_cps: f64,
+    /// Пауза в речи, после которой всегда начинается новая реплика, с
+    pub pause_threshold: f64,
+}
+
+impl Default for SegmentationOptions {
+    fn default() -> Self {
+        Self {
+            max_chars_per_line: 42,
+            max_lines: 2,
+            max_duration: 7.0,
+            min_duration: 1.0,
+            min_gap_ms: 80,
+            max_cps: 17.0,
+            pause_threshold: 0.7,
+        }
+    }
+}
+
+/// Сегменты распознавания → читаемые реплики по словам из [`transcript_words`]
+pub fn resegment_transcript(
+    segments: &[SubtitleSegment],
+    words: &[SubtitleWord],
+    options: &SegmentationOptions,
+) -> Vec<SubtitleSegment> {
+    resegment(&transcript_words(segments, words), options)
+}
+
+/// Слова текста сегментов с таймингами. Слова сегмента — его собственные `words`,
+/// иначе берутся из общего списка `words` (тайминги Whisper, без пунктуации) и привязываются
+/// к тексту сегмента с пунктуацией.
+pub fn transcript_words(segments: &[SubtitleSegment], words: &[SubtitleWord]) -> Vec<SubtitleWord> {
+    let mut cursor = 0usize;
+    let mut timed = Vec::new();
+    for seg in segments {
+        if let Some(words) = seg.words.as_ref().filter(|w| !w.is_empty()) {
+            timed.extend(align_words(&seg.text, seg.start, seg.end, words));
+            continue;
+        }
+        // Слова, закончившиеся заметно раньше сегмента, — от выброшенных повторов-галлюцинаций
+        while cursor < words.len() && words[cursor].end + Timestamp::from_millis(500) < seg.start {
+            cursor += 1;
+        }
+        let target = normalized(&seg.text).chars().count();
+        let mut taken = 0usize;
+        let from = cursor;
+        while cursor < words.len() && taken < target {
+            taken += normalized(&words[cursor].text).chars().count();
+            cursor += 1;
+        }
+        timed.extend(align_words(&seg.text, seg.start, seg.end, &words[from..cursor]));
+    }
+    timed
+}
+
+/// Слова текста с таймингами: если распознанные слова совпадают с текстом (без учёта
+/// пунктуации и регистра), время слова берётся из них, иначе делится пропорционально длине.
+pub fn align_words(text: &str, start: Timestamp, end: Timestamp, recognized: &[SubtitleWord]) -> Vec<SubtitleWord> {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let joined: String = recognized.iter().map(|w| normalized(&w.text)).collect();
+    if recognized.is_empty() || joined != normalized(text) {
+        return estimate_words(&tokens, start, end);
+    }
+
+    // Границы распознанных слов в «нормализованных» символах
+    let mut spans = Vec::with_capacity(recognized.len());
+    let mut offset = 0usize;
+    for word in recognized {
+        let len = normalized(&word.text).chars().count();
+        spans.push((offset, offset + len));
+        offset += len;
+    }
+
+    let mut result: Vec<SubtitleWord> = Vec::with_capacity(tokens.len());
+    let mut offset = 0usize;
+    for token in tokens {
+        let len = normalized(token).chars().count();
+        let (from, to) = (offset, offset + len);
+        offset = to;
+        let timing = if len == 0 {
+            // Отдельный знак («—», «...») — к концу предыдущего слова
+            result.last().map(|w| (w.end, w.end, None))
+        } else {
+            let first = spans.iter().position(|&(_, b)| b > from);
+            let last = spans.iter().rposition(|&(a, _)| a < to);
+            first.zip(last).map(|(f, l)| {
+                // Токен из нескольких распознанных слов — по наименее уверенному
+                let confidence = recognized[f..=l.max(f)]
+                    .iter()
+                    .filter_map(|w| w.confidence)
+                    .reduce(f64::min);
+                (recognized[f].start, recognized[l].end.max(recognized[f].start), confidence)
+            })
+        };
+        let (word_start, word_end, confidence) = timing.unwrap_or((start, start, None));
+        result.push(SubtitleWord { text: token.to_string(), start: word_start, end: word_end, confidence });
+    }
+    result
+}
+
+/// Тайминги слов без распознавания: длительность сегмента делится по длине слов
+fn estimate_words(tokens: &[&str], start: Timestamp, end: Timestamp) -> Vec<SubtitleWord> {
+    // Длина слов плюс пробелы между ними
+    let total: usize = tokens.iter().map(|t| t.chars().count() + 1).sum::<usize>().saturating_sub(1);
+    let span = (end - start).millis().max(0);
+    let at = |chars: usize| start + Timestamp::from_millis(span * chars as i64 / total.max(1) as i64);
+    let mut consumed = 0usize;
+    tokens
+        .iter()
+        .map(|token| {
+            let word_start = at(consumed);
+            consumed += token.chars().count();
+            let word_end = at(consumed);
+            consumed += 1;
+            SubtitleWord { text: token.to_string(), start: word_start, end: word_end, confidence: None }
+        })
+        .collect()
+}
+
+/// Слова → реплики: жёсткие разрывы на паузах и концах фраз, деление по лимитам
+/// в лучшем месте (знак препинания, пауза), склейка коротких и подгонка таймингов.
+pub fn resegment(words: &[SubtitleWord], options: &SegmentationOptions) -> Vec<SubtitleSegment> {
+    let min_duration = Timestamp::from_seconds(options.min_duration);
+    let max_duration = Timestamp::from_seconds(options.max_duration);
+    let pause = Timestamp::from_seconds(options.pause_threshold);
+
+    let mut cues: Vec<Vec<SubtitleWord>> = Vec::new();
+    let mut current: Vec<SubtitleWord> = Vec::new();
+    for word in words.iter().filter(|w| !w.text.is_empty()) {
+        if let Some(prev) = current.last() {
+            let sentence_done = ends_sentence(&prev.text) && prev.end - current[0].start >= min_duration;
+            if word.start - prev.end >= pause || sentence_done {
+                cues.push(std::mem::take(&mut current));
+            }
+        }
+        // Не помещается — отрезаем начало реплики в лучшем месте, пока слово не влезет
+        while !current.is_empty() && !cue_fits(&current, Some(word), max_duration, options) {
+            let at = best_break(&current, word);
+            let rest = current.split_off(at);
+            cues.push(std::mem::replace(&mut current, rest));
+        }
+        current.push(word.clone());
+    }
+    if !current.is_empty() {
+        cues.push(current);
+    }
+
+    fit_timings(&merge_short_cues(cues, options), options)
+}
+
+/// Слишком короткие реплики сливаются с соседней, если вместе помещаются и между ними нет паузы
+fn merge_short_cues(cues: Vec<Vec<SubtitleWord>>, options: &SegmentationOptions) -> Vec<Vec<SubtitleWord>> {
+    let min_duration = Timestamp::from_seconds(options.min_duration);
+    let max_duration = Timestamp::from_seconds(options.max_duration);
+    let pause = Timestamp::from_seconds(options.pause_threshold);
+    let join = |first: &[SubtitleWord], second: &[SubtitleWord]| {
+        let short = span(first) < min_duration || span(second) < min_duration;
+        let close = gap_between(first, second) < pause;
+        let mut joined = first.to_vec();
+        joined.extend(second.iter().cloned());
+        (short && close && cue_fits(&joined, None, max_duration, options)).then_some(joined)
+    };
+    let mut merged: Vec<Vec<SubtitleWord>> = Vec::with_capacity(cues.len());
+    let mut cues = cues.into_iter().peekable();
+    while let Some(cue) = cues.next() {
+        let with_prev = merged.last().and_then(|prev| join(prev, &cue));
+        if span(&cue) < min_duration {
+            if let Some(next) = cues.peek_mut() {
+                // Короткая реплика — к следующей, если она начинает фразу или ближе к следующей
+                let to_next = match merged.last().filter(|_| with_prev.is_some()) {
+                    None => true,
+                    Some(prev) => {
+                        let prev_done = ends_sentence(&prev[prev.len() - 1].text);
+                        let cue_done = ends_sentence(&cue[cue.len() - 1].text);
+                        if prev_done != cue_done {
+                            prev_done
+                        } else {
+                            gap_between(&cue, next) < gap_between(prev, &cue)
+                        }
+                    }
+                };
+                if let Some(joined) = join(&cue, next).filter(|_| to_next) {
+                    *next = joined;
+                    continue;
+                }
+            }
+        }
+        match (with_prev, merged.last_mut()) {
+            (Some(joined), Some(prev)) => *prev = joined,
+            _ => merged.push(cue),
+        }
+    }
+    merged
+}
+
+/// Тайминги реплик: зазор между соседними, минимальная длительность и скорость чтения
+/// (реплика растягивается в соседние паузы, но не налезает на другие)
+fn fit_timings(cues: &[Vec<SubtitleWord>], options: &SegmentationOptions) -> Vec<SubtitleSegment> {
+    let gap = Timestamp::from_millis(options.min_gap_ms.max(0));
+    let mut segments: Vec<SubtitleSegment> = Vec::with_capacity(cues.len());
+    for (i, cue) in cues.iter().enumerate() {
+        let text = wrap_lines(&words_text(cue), options.max_chars_per_line, options.max_lines)
+            .unwrap_or_else(|| words_text(cue).join(" "));
+        let prev_end = segments.last().map(|prev| prev.end + gap).unwrap_or(Timestamp::ZERO);
+        // Предыдущая реплика могла занять начало этой (слова почти на одном времени) —
+        // тогда начало сдвигается за неё
+        let mut start = cue[0].start.max(prev_end);
+        let mut end = cue[cue.len() - 1].end.max(start);
+
+        let reading = reading_chars(&words_text(cue)) as f64 / options.max_cps.max(1.0);
+        let needed = Timestamp::from_seconds(reading.max(options.min_duration));
+        // Следующая начинается меньше чем через зазор — она сдвинется сама, а эта не должна
+        // схлопнуться в нулевую длину
+        let next_start = cues
+            .get(i + 1)
+            .map(|next| next[0].start - gap)
+            .filter(|&limit| limit > start);
+
+        if end - start < needed {
+            let wanted = start + needed;
+            end = next_start.map_or(wanted, |limit| wanted.min(limit)).max(end);
+        }
+        if end - start < needed {
+            start = (end - needed).max(prev_end).min(start);
+        }
+        if let Some(limit) = next_start {
+            end = end.min(limit).max(start);
+        }
+
+        segments.push(SubtitleSegment {
+            id: segments.len() as u32 + 1,
+            start,
+            end,
+            duration: end - start,
+            text,
+            translation: None,
+            flags: None,
+            format_meta: None,
+            words: Some(cue.clone()),
+        });
+    }
+    segments
+}
+
+/// Место разреза: после `k` слов. Лучше всего — конец фразы, потом запятая и пауза;
+/// при прочих равных режем позже, но не оставляем в начале одно-два коротких слова,
+/// а в хвосте — одно-два слова, которыми фраза заканчивается.
+fn best_break(current: &[SubtitleWord], incoming: &SubtitleWord) -> usize {
+    let total: usize = current.iter().map(|w| w.text.chars().count() + 1).sum();
+    let phrase_ends = ends_sentence(&incoming.text) || ends_clause(&incoming.text);
+    let mut best = (f64::MIN, current.len());
+    let mut chars = 0usize;
+    for k in 1..=current.len() {
+        let last = &current[k - 1];
+        chars += last.text.chars().count() + 1;
+        let next_start = current.get(k).unwrap_or(incoming).start;
+        let pause = (next_start - last.end).as_seconds().max(0.0);
+
+        let mut score = (pause / 0.5).min(2.0) + chars as f64 / total as f64;
+        if ends_sentence(&last.text) {
+            score += 3.0;
+        } else if ends_clause(&last.text) {
+            score += 2.0;
+        }
+        if chars * 3 < total && k < current.len() {
+            score -= 2.0;
+        }
+        // Хвост вместе с входящим словом — не больше двух слов
+        if phrase_ends && current.len() - k < 2 {
+            score -= 2.0;
+        }
+        if score >= best.0 {
+            best = (score, k);
+        }
+    }
+    best.1
+}
+
+/// Помещаются ли слова `cue` (и `extra`) в одну реплику: длительность, строки и скорость чтения.
+/// Скорость считается по времени не меньше `min_duration` — до него реплику растянет [`fit_timings`].
+fn cue_fits(cue: &[SubtitleWord], extra: Option<&SubtitleWord>, max_duration: Timestamp, options: &SegmentationOptions) -> bool {
+    let mut words = words_text(cue);
+    if let Some(word) = extra {
+        words.push(word.text.as_str());
+    }
+    if words.len() <= 1 {
+        return true;
+    }
+    let end = extra.map_or(cue[cue.len() - 1].end, |w| w.end);
+    let duration = end - cue[0].start;
+    let reading_time = duration.max(Timestamp::from_seconds(options.min_duration)).as_seconds();
+    duration <= max_duration
+        && reading_chars(&words) as f64 <= options.max_cps.max(1.0) * reading_time
+        && wrap_lines(&words, options.max_chars_per_line, options.max_lines).is_some()
+}
+
+/// Текст в `max_lines` строк не длиннее `max_chars`, строки по возможности равной длины,
+/// переносы — после знаков препинания. `None`, если не помещается.
+pub fn wrap_lines(words: &[&str], max_chars: usize, max_lines: usize) -> Option<String> {
+    let n = words.len();
+    if n == 0 {
+        return Some(String::new());
+    }
+    let lens: Vec<usize> = words.iter().map(|w| w.chars().count()).collect();
+    let line_len = |from: usize, to: usize| lens[from..to].iter().sum::<usize>() + (to - from - 1);
+    if line_len(0, n) <= max_chars {
+        return Some(words.join(" "));
+    }
+
+    // best[l][i] — лучшая раскладка первых i слов в l строк: (самая длинная строка, переносов без знака, начало строки)
+    let mut best: Vec<Vec<Option<(usize, usize, usize)>>> = vec![vec![None; n + 1]; max_lines.max(1) + 1];
+    best[0][0] = Some((0, 0, 0));
+    for lines in 1..=max_lines.max(1) {
+        for i in 1..=n {
+            for j in 0..i {
+                let Some((longest, plain, _)) = best[lines - 1][j] else { continue };
+                let len = line_len(j, i);
+                if len > max_chars {
+                    continue;
+                }
+                let plain = plain + usize::from(j > 0 && !ends_clause(words[j - 1]) && !ends_sentence(words[j - 1]));
+                let candidate = (longest.max(len), plain, j);
+                if best[lines][i].is_none_or(|(l, p, _)| wrap_cost(candidate.0, candidate.1) < wrap_cost(l, p)) {
+                    best[lines][i] = Some(candidate);
+                }
+            }
+        }
+        if best[lines][n].is_some() {
+            let mut breaks = Vec::new();
+            let (mut l, mut i) = (lines, n);
+            while l > 0 {
+                let (_, _, j) = best[l][i]?;
+                breaks.push(words[j..i].join(" "));
+                i = j;
+                l -= 1;
+            }
+            breaks.reverse();
+            return Some(breaks.join("\n"));
+        }
+    }
+    None
+}
+
+/// Перенос не после знака препинания стоит как шесть лишних символов в самой длинной строке
+fn wrap_cost(longest: usize, plain_breaks: usize) -> usize {
+    longest + 6 * plain_breaks
+}
+
+fn words_text(cue: &[SubtitleWord]) -> Vec<&str> {
+    cue.iter().map(|w| w.text.as_str()).collect()
+}
+
+fn span(cue: &[SubtitleWord]) -> Timestamp {
+    cue[cue.len() - 1].end - cue[0].start
+}
+
+fn gap_between(first: &[SubtitleWord], second: &[SubtitleWord]) -> Timestamp {
+    second[0].start - first[first.len() - 1].end
+}
+
+/// Символы для скорости чтения — без пробелов
+fn reading_chars(words: &[&str]) -> usize {
+    words.iter().map(|w| w.chars().filter(|c| !c.is_whitespace()).count()).sum()
+}
+
+fn trailing_punctuation(word: &str) -> Option<char> {
+    word.trim_end_matches(['"', '\'', '»', '”', ')', ']']).chars().last()
+}
+
+fn ends_sentence(word: &str) -> bool {
+    matches!(trailing_punctuation(word), Some('.' | '!' | '?' | '…' | '。' | '！' | '？'))
+}
+
+fn ends_clause(word: &str) -> bool {
+    matches!(trailing_punctuation(word), Some(',' | ';' | ':' | '—' | '–' | '，' | '、'))
+}
+
+/// Буквы и цифры в нижнем регистре — для сравнения слов Whisper с текстом
+fn normalized(text: &str) -> String {
+    text.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Слова подряд: каждое длится `word_ms`, между словами `gap_ms`
+    fn speech(text: &str, start_ms: i64, word_ms: i64, gap_ms: i64) -> Vec<SubtitleWord> {
+        text.split_whitespace()
+            .enumerate()
+            .map(|(i, token)| {
+                let start = start_ms + i as i64 * (word_ms + gap_ms);
+                SubtitleWord {
+                    text: token.to_string(),
+                    start: Timestamp::from_millis(start),
+                    end: Timestamp::from_millis(start + word_ms),
+                    confidence: None,
+                }
+            })
+            .collect()
+    }
+
+    fn texts(cues: &[Vec<SubtitleWord>]) -> Vec<String> {
+        cues.iter().map(|cue| words_text(cue).join(" ")).collect()
+    }
+
+    #[test]
+    fn cue_fits_checks_reading_speed() {
+        let options = SegmentationOptions::default();
+        let max_duration = Timestamp::from_seconds(options.max_duration);
+        // 40 символов за 1,5 с — больше 17 символов в секунду
+        let fast = speech("слово слово слово слово слово слово слово слово", 0, 150, 30);
+        assert!(!cue_fits(&fast, None, max_duration, &options));
+        // Те же слова в обычном темпе
+        let calm = speech("слово слово слово слово слово слово слово слово", 0, 300, 100);
+        assert!(cue_fits(&calm, None, max_duration, &options));
+    }
+
+    #[test]
+    fn cue_fits_counts_short_cues_as_min_duration() {
+        let options = SegmentationOptions::default();
+        let max_duration = Timestamp::from_seconds(options.max_duration);
+        // 10 символов за 0,3 с, но реплика будет показана не меньше секунды
+        let cue = speech("коротко да", 0, 140, 20);
+        assert!(cue_fits(&cue, None, max_duration, &options));
+    }
+
+    #[test]
+    fn fast_speech_is_split_by_max_cps() {
+        let options = SegmentationOptions { max_chars_per_line: 200, max_duration: 30.0, ..Default::default() };
+        let text = ["быстрая"; 30].join(" ");
+        let segments = resegment(&speech(&text, 0, 200, 20), &options);
+        assert!(segments.len() > 1);
+        for seg in &segments {
+            let words = seg.words.as_ref().unwrap();
+            let seconds = span(words).max(Timestamp::from_seconds(options.min_duration)).as_seconds();
+            assert!(reading_chars(&words_text(words)) as f64 / seconds <= options.max_cps);
+        }
+    }
+
+    #[test]
+    fn cues_respect_line_and_duration_limits() {
+        let options = SegmentationOptions::default();
+        let text = ["расшифровка длинной речи без единого знака препинания"; 12].join(" ");
+        let segments = resegment(&speech(&text, 0, 350, 50), &options);
+        for seg in &segments {
+            let lines: Vec<&str> = seg.text.lines().collect();
+            assert!(lines.len() <= options.max_lines, "{:?}", seg.text);
+            assert!(lines.iter().all(|l| l.chars().count() <= options.max_chars_per_line), "{:?}", seg.text);
+            assert!(span(seg.words.as_ref().unwrap()) <= Timestamp::from_seconds(options.max_duration));
+            assert!(seg.end > seg.start);
+        }
+    }
+
+    #[test]
+    fn best_break_avoids_phrase_tail() {
+        let words = speech("я сто раз говорил тебе не ходить туда без меня.", 0, 300, 50);
+        let (current, incoming) = words.split_at(words.len() - 1);
+        let at = best_break(current, &incoming[0]);
+        // Вместе с входящим словом в новой реплике не меньше трёх слов
+        assert!(current.len() - at >= 2, "разрез после {} слов", at);
+    }
+
+    #[test]
+    fn best_break_prefers_sentence_end() {
+        let words = speech("Вот и всё. Теперь мы пойдём дальше", 0, 300, 50);
+        let (current, incoming) = words.split_at(words.len() - 1);
+        assert_eq!(best_break(current, &incoming[0]), 3);
+    }
+
+    #[test]
+    fn short_cue_starting_a_phrase_joins_the_next() {
+        let cues = vec![
+            speech("Вот и всё.", 0, 500, 0),
+            speech("Итак,", 1600, 250, 0),
+            speech("мы снова встретились", 1900, 400, 50),
+        ];
+        let merged = merge_short_cues(cues, &SegmentationOptions::default());
+        assert_eq!(texts(&merged), vec!["Вот и всё.", "Итак, мы снова встретились"]);
+    }
+
+    #[test]
+    fn short_cue_ending_a_phrase_joins_the_previous() {
+        let cues = vec![
+            speech("Я с тобой согласен", 0, 400, 50),
+            speech("полностью.", 1800, 300, 0),
+            speech("Но есть одно но", 2200, 400, 50),
+        ];
+        let merged = merge_short_cues(cues, &SegmentationOptions::default());
+        assert_eq!(texts(&merged), vec!["Я с тобой согласен полностью.", "Но есть одно но"]);
+    }
+}
@@ -0,0 +1,614 @@
+use crate::project::{SegmentFormatMeta, SubtitleSegment, Timestamp};
+use serde::{Deserialize, Serialize};
+
+/// Заголовок скрипта ASS/SSA: всё, что нужно, чтобы при экспорте вернуть исходное оформление.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AssScript {
+    /// `true` для SSA (v4.00), `false` для ASS (v4.00+)
+    pub is_ssa: bool,
+    /// Строки секции `[Script Info]` как есть (включая комментарии `;`)
+    pub script_info: Vec<String>,
+    pub styles: Vec<AssStyle>,
+    /// Строки `Comment:` из `[Events]` — в сегменты не попадают, но сохраняются для экспорта
+    #[serde(default)]
+    pub comment_events: Vec<String>,
+    /// Прочие секции (`[Fonts]`, `[Graphics]`, `[Aegisub Project Garbage]` и т.п.)
+    #[serde(default)]
+    pub extra_sections: Vec<AssSection>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AssSection {
+    pub name: String,
+    pub lines: Vec<String>,
+}
+
+/// Стиль в терминах V4+ (стили SSA приводятся к нему при разборе).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AssStyle {
+    pub name: String,
+    pub fontname: String,
+    pub fontsize: f64,
+    pub primary_colour: String,
+    pub secondary_colour: String,
+    pub outline_colour: String,
+    pub back_colour: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strike_out: bool,
+    pub scale_x: f64,
+    pub scale_y: f64,
+    pub spacing: f64,
+    pub angle: f64,
+    pub border_style: i32,
+    pub outline: f64,
+    pub shadow: f64,
+    /// Выравнивание по цифровой клавиатуре (1–9)
+    pub alignment: i32,
+    pub margin_l: i32,
+    pub margin_r: i32,
+    pub margin_v: i32,
+    pub encoding: i32,
+}
+
+impl Default for AssStyle {
+    fn default() -> Self {
+        Self {
+            name: "Default".to_string(),
+            fontname: "Arial".to_string(),
+            fontsize: 48.0,
+            primary_colour: "&H00FFFFFF".to_string(),
+            secondary_colour: "&H000000FF".to_string(),
+            outline_colour: "&H00000000".to_string(),
+            back_colour: "&H00000000".to_string(),
+            bold: false,
+            italic: false,
+            underline: false,
+            strike_out: false,
+            scale_x: 100.0,
+            scale_y: 100.0,
+            spacing: 0.0,
+            angle: 0.0,
+            border_style: 1,
+            outline: 2.0,
+            shadow: 2.0,
+            alignment: 2,
+            margin_l: 10,
+            margin_r: 10,
+            margin_v: 10,
+            encoding: 1,
+        }
+    }
+}
+
+pub const V4PLUS_STYLE_FORMAT: &str = "Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding";
+pub const V4PLUS_EVENT_FORMAT: &str = "Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text";
+
+impl AssStyle {
+    /// Строка `Style:` в порядке полей [`V4PLUS_STYLE_FORMAT`].
+    pub fn to_v4plus_line(&self) -> String {
+        format!(
+            "Style: {},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.name,
+            self.fontname,
+            format_number(self.fontsize),
+            self.primary_colour,
+            self.secondary_colour,
+            self.outline_colour,
+            self.back_colour,
+            format_flag(self.bold),
+            format_flag(self.italic),
+            format_flag(self.underline),
+            format_flag(self.strike_out),
+            format_number(self.scale_x),
+            format_number(self.scale_y),
+            format_number(self.spacing),
+            format_number(self.angle),
+            self.border_style,
+            format_number(self.outline),
+            format_number(self.shadow),
+            self.alignment,
+            self.margin_l,
+            self.margin_r,
+            self.margin_v,
+            self.encoding,
+        )
+    }
+}
+
+impl AssScript {
+    /// Значение поля `[Script Info]` по ключу (без учёта регистра).
+    pub fn info_value(&self, key: &str) -> Option<&str> {
+        self.script_info.iter().find_map(|line| {
+            let (k, v) = split_key_value(line)?;
+            k.eq_ignore_ascii_case(key).then(|| v.trim())
+        })
+    }
+}
+
+fn format_flag(value: bool) -> &'static str {
+    if value { "-1" } else { "0" }
+}
+
+/// `48.0` → `48`, `2.5` → `2.5`
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{}", value as i64)
+    } else {
+        format!("{}", value)
+    }
+}
+
+/// Метаданные одной строки `Dialogue:`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AssEventMeta {
+    pub layer: i32,
+    pub style: String,
+    /// Поле `Name` (актёр)
+    pub actor: String,
+    pub margin_l: i32,
+    pub margin_r: i32,
+    pub margin_v: i32,
+    pub effect: String,
+    /// Блоки `{\...}` в начале строки (позиция, выравнивание, fade и т.п.)
+    pub leading_tags: String,
+    /// Исходное поле `Text` со всеми тегами
+    pub raw_text: String,
+}
+
+const DEFAULT_EVENT_FORMAT: [&str; 10] = [
+    "layer", "start", "end", "style", "name", "marginl", "marginr", "marginv", "effect", "text",
+];
+
+#[derive(PartialEq)]
+enum Section {
+    None,
+    ScriptInfo,
+    Styles,
+    Events,
+    Other,
+}
+
+pub fn parse(content: &str) -> Result<(Vec<SubtitleSegment>, AssScript), String> {
+    let mut script = AssScript::default();
+    let mut segments = Vec::new();
+    let mut section = Section::None;
+    let mut style_format: Vec<String> = Vec::new();
+    let mut event_format: Vec<String> = DEFAULT_EVENT_FORMAT.iter().map(|s| s.to_string()).collect();
+    let mut seen_events = false;
+
+    for raw_line in content.lines() {
+        let line = raw_line.trim_start_matches('\u{feff}').trim_end();
+        let trimmed = line.trim();
+
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            let name = &trimmed[1..trimmed.len() - 1];
+            section = match name.to_lowercase().as_str() {
+                "script info" => Section::ScriptInfo,
+                "v4+ styles" => Section::Styles,
+                "v4 styles" => {
+                    script.is_ssa = true;
+                    Section::Styles
+                }
+                "events" => {
+                    seen_events = true;
+                    Section::Events
+                }
+                _ => {
+                    script.extra_sections.push(AssSection {
+                        name: name.to_string(),
+                        lines: Vec::new(),
+                    });
+                    Section::Other
+                }
+            };
+            continue;
+        }
+
+        match section {
+            Section::None => {}
+            Section::ScriptInfo => {
+                if trimmed.is_empty() {
+                    continue;
+                }
+                if let Some((key, value)) = split_key_value(trimmed) {
+                    if key.eq_ignore_ascii_case("ScriptType") && value.to_lowercase().contains("v4.00")
+                        && !value.contains('+')
+                    {
+                        script.is_ssa = true;
+                    }
+                }
+                script.script_info.push(trimmed.to_string());
+            }
+            Section::Styles => {
+                let Some((key, value)) = split_key_value(trimmed) else { continue };
+                if key.eq_ignore_ascii_case("Format") {
+                    style_format = split_format(value);
+                } else if key.eq_ignore_ascii_case("Style") {
+                    let format = if style_format.is_empty() {
+                        default_style_format(script.is_ssa)
+                    } else {
+                        style_format.clone()
+                    };
+                    script.styles.push(parse_style(value, &format, script.is_ssa));
+                }
+            }
+            Section::Events => {
+                let Some((key, value)) = split_key_value(trimmed) else { continue };
+                if key.eq_ignore_ascii_case("Format") {
+                    event_format = split_format(value);
+                } else if key.eq_ignore_ascii_case("Dialogue") {
+                    if let Some(segment) = parse_dialogue(value, &event_format, segments.len() as u32 + 1) {
+                        segments.push(segment);
+                    }
+                } else if key.eq_ignore_ascii_case("Comment") {
+                    script.comment_events.push(trimmed.to_string());
+                }
+            }
+            Section::Other => {
+                if let Some(last) = script.extra_sections.last_mut() {
+                    if !trimmed.is_empty() {
+                        last.lines.push(line.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    if !seen_events {
+        return Err("В файле ASS/SSA нет секции [Events]".to_string());
+    }
+
+    // Сортировка по времени: в ASS порядок строк не обязан совпадать с хронологией
+    segments.sort_by(|a, b| {
+        a.start
+            .partial_cmp(&b.start)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    for (i, seg) in segments.iter_mut().enumerate() {
+        seg.id = (i + 1) as u32;
+    }
+
+    Ok((segments, script))
+}
+
+fn split_key_value(line: &str) -> Option<(&str, &str)> {
+    if line.starts_with(';') {
+        return None;
+    }
+    let (key, value) = line.split_once(':')?;
+    Some((key.trim(), value.trim_start()))
+}
+
+fn split_format(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim().to_lowercase())
+        .collect()
+}
+
+fn default_style_format(is_ssa: bool) -> Vec<String> {
+    let fields: &[&str] = if is_ssa {
+        &[
+            "name", "fontname", "fontsize", "primarycolour", "secondarycolour", "tertiarycolour",
+            "backcolour", "bold", "italic", "borderstyle", "outline", "shadow", "alignment",
+            "marginl", "marginr", "marginv", "alphalevel", "encoding",
+        ]
+    } else {
+        &[
+            "name", "fontname", "fontsize", "primarycolour", "secondarycolour", "outlinecolour",
+            "backcolour", "bold", "italic", "underline", "strikeout", "scalex", "scaley",
+            "spacing", "angle", "borderstyle", "outline", "shadow", "alignment", "marginl",
+            "marginr", "marginv", "encoding",
+        ]
+    };
+    fields.iter().map(|s| s.to_string()).collect()
+}
+
+fn parse_style(value: &str, format: &[String], is_ssa: bool) -> AssStyle {
+    let mut style = AssStyle::default();
+    let values: Vec<&str> = value.splitn(format.len().max(1), ',').collect();
+
+    for (field, raw) in format.iter().zip(values.iter()) {
+        let v = raw.trim();
+        match field.as_str() {
+            "name" => style.name = v.to_string(),
+            "fontname" => style.fontname = v.to_string(),
+            "fontsize" => style.fontsize = v.parse().unwrap_or(style.fontsize),
+            "primarycolour" => style.primary_colour = normalize_colour(v),
+            "secondarycolour" => style.secondary_colour = normalize_colour(v),
+            "outlinecolour" | "tertiarycolour" => style.outline_colour = normalize_colour(v),
+            "backcolour" => style.back_colour = normalize_colour(v),
+            "bold" => style.bold = parse_flag(v),
+            "italic" => style.italic = parse_flag(v),
+            "underline" => style.underline = parse_flag(v),
+            "strikeout" => style.strike_out = parse_flag(v),
+            "scalex" => style.scale_x = v.parse().unwrap_or(style.scale_x),
+            "scaley" => style.scale_y = v.parse().unwrap_or(style.scale_y),
+            "spacing" => style.spacing = v.parse().unwrap_or(style.spacing),
+            "angle" => style.angle = v.parse().unwrap_or(style.angle),
+            "borderstyle" => style.border_style = v.parse().unwrap_or(style.border_style),
+            "outline" => style.outline = v.parse().unwrap_or(style.outline),
+            "shadow" => style.shadow = v.parse().unwrap_or(style.shadow),
+            "alignment" => {
+                let a: i32 = v.parse().unwrap_or(2);
+                style.alignment = if is_ssa { ssa_alignment_to_numpad(a) } else { a };
+            }
+            "marginl" => style.margin_l = v.parse().unwrap_or(style.margin_l),
+            "marginr" => style.margin_r = v.parse().unwrap_or(style.margin_r),
+            "marginv" => style.margin_v = v.parse().unwrap_or(style.margin_v),
+            "encoding" => style.encoding = v.parse().unwrap_or(style.encoding),
+            _ => {}
+        }
+    }
+
+    style
+}
+
+/// `-1`/`1` — включено, `0` — выключено
+fn parse_flag(v: &str) -> bool {
+    v.parse::<i32>().map(|n| n != 0).unwrap_or(false)
+}
+
+/// SSA допускает десятичные цвета; приводим всё к `&HAABBGGRR`.
+fn normalize_colour(v: &str) -> String {
+    if v.starts_with("&H") || v.starts_with("&h") {
+        return v.to_uppercase().trim_end_matches('&').to_string();
+    }
+    match v.parse::<i64>() {
+        Ok(n) => format!("&H{:08X}", n as u32),
+        Err(_) => v.to_string(),
+    }
+}
+
+/// SSA: 1–3 снизу, 5–7 сверху, 9–11 посередине → цифровая клавиатура V4+.
+fn ssa_alignment_to_numpad(a: i32) -> i32 {
+    match a {
+        1..=3 => a,
+        5..=7 => a + 2,
+        9..=11 => a - 5,
+        _ => 2,
+    }
+}
+
+fn parse_dialogue(value: &str, format: &[String], id: u32) -> Option<SubtitleSegment> {
+    let values: Vec<&str> = value.splitn(format.len().max(1), ',').collect();
+    if values.len() < format.len() {
+        return None;
+    }
+
+    let mut meta = AssEventMeta::default();
+    let mut start = None;
+    let mut end = None;
+
+    for (field, raw) in format.iter().zip(values.iter()) {
+        match field.as_str() {
+            "layer" => meta.layer = raw.trim().parse().unwrap_or(0),
+            "start" => start = parse_time(raw.trim()),
+            "end" => end = parse_time(raw.trim()),
+            "style" => meta.style = raw.trim().trim_start_matches('*').to_string(),
+            "name" | "actor" => meta.actor = raw.trim().to_string(),
+            "marginl" => meta.margin_l = raw.trim().parse().unwrap_or(0),
+            "marginr" => meta.margin_r = raw.trim().parse().unwrap_or(0),
+            "marginv" => meta.margin_v = raw.trim().parse().unwrap_or(0),
+            "effect" => meta.effect = raw.trim().to_string(),
+            "text" => meta.raw_text = raw.to_string(),
+            _ => {}
+        }
+    }
+
+    let start = start?;
+    let end = end?;
+    meta.leading_tags = leading_override_blocks(&meta.raw_text);
+    let text = plain_text(&meta.raw_text);
+
+    Some(SubtitleSegment {
+        id,
+        start,
+        end,
+        duration: (end - start).max(Timestamp::ZERO),
+        text,
+        translation: None,
+        flags: None,
+        words: None,
+        format_meta: Some(SegmentFormatMeta::Ass(meta)),
+    })
+}
+
+/// Время ASS: `H:MM:SS.cc` (сотые доли секунды)
+fn parse_time(s: &str) -> Option<Timestamp> {
+    let parts: Vec<&str> = s.split(':').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let hours = parts[0].parse::<u32>().ok()?;
+    let minutes = parts[1].parse::<u32>().ok()?;
+    let (secs, frac) = parts[2].split_once('.').unwrap_or((parts[2], "0"));
+    let secs = secs.parse::<u32>().ok()?;
+    // Доли секунды — сотые в ASS, но встречаются и миллисекунды: дополняем до трёх цифр
+    let millis = if frac.is_empty() {
+        0
+    } else {
+        let digits: String = frac.chars().chain("000".chars()).take(3).collect();
+        digits.parse::<u32>().ok()?
+    };
+    if !frac.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(Timestamp::from_hmsm(hours, minutes, secs, millis))
+}
+
+/// Обычный текст → поле `Text` ASS (переводы строк в `\N`).
+pub fn escape_text(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\n', "\\N")
+}
+
+/// Подряд идущие блоки `{...}` в начале текста.
+pub fn leading_override_blocks(raw: &str) -> String {
+    let mut rest = raw;
+    let mut out = String::new();
+    while rest.starts_with('{') {
+        match rest.find('}') {
+            Some(close) => {
+                out.push_str(&rest[..=close]);
+                rest = &rest[close + 1..];
+            }
+            None => break,
+        }
+    }
+    out
+}
+
+/// Блок, выключающий переключатели `\i`, `\b`, `\u`, `\s`, включённые в `leading`
+/// (`{\i1\b1}` → `{\i0\b0}`); пустая строка, если закрывать нечего.
+pub fn closing_overrides(leading: &str) -> String {
+    let Ok(re) = regex::Regex::new(r"\\(?:([ibus])(\d+)|r)") else {
+        return String::new();
+    };
+    // Последнее значение каждого переключателя, в порядке первого появления
+    let mut state: Vec<(&str, bool)> = Vec::new();
+    for caps in re.captures_iter(leading) {
+        // `\r` сбрасывает всё, что было включено до него
+        let (Some(tag), Some(value)) = (caps.get(1), caps.get(2)) else {
+            state.clear();
+            continue;
+        };
+        let tag = tag.as_str();
+        let on = value.as_str().parse::<u32>().is_ok_and(|v| v != 0);
+        match state.iter_mut().find(|(t, _)| *t == tag) {
+            Some(entry) => entry.1 = on,
+            None => state.push((tag, on)),
+        }
+    }
+    let tags: String = state.iter().filter(|(_, on)| *on).map(|(tag, _)| format!("\\{}0", tag)).collect();
+    if tags.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", tags)
+    }
+}
+
+/// Текст строки без тегов: `\N`/`\n` → перевод строки, `\h` → пробел, векторные рисунки (`\p1`) отбрасываются.
+pub fn plain_text(raw: &str) -> String {
+    let mut out = String::new();
+    let mut drawing = false;
+    let mut rest = raw;
+
+    while !rest.is_empty() {
+        if rest.starts_with('{') {
+            if let Some(close) = rest.find('}') {
+                let block = &rest[1..close];
+                if let Some(level) = drawing_level(block) {
+                    drawing = level > 0;
+                }
+                rest = &rest[close + 1..];
+                continue;
+            }
+        }
+
+        let mut chars = rest.chars();
+        let c = chars.next().unwrap_or_default();
+        if c == '\\' {
+            match chars.next() {
+                Some('N') | Some('n') => {
+                    if !drawing {
+                        out.push('\n');
+                    }
+                    rest = &rest[2..];
+                    continue;
+                }
+                Some('h') => {
+                    if !drawing {
+                        out.push(' ');
+                    }
+                    rest = &rest[2..];
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        if !drawing {
+            out.push(c);
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+
+    out.trim().to_string()
+}
+
+/// Последнее значение `\pN` в блоке тегов, если есть.
+fn drawing_level(block: &str) -> Option<u32> {
+    let mut level = None;
+    for tag in block.split('\\').skip(1) {
+        if let Some(rest) = tag.strip_prefix('p') {
+            if let Ok(n) = rest.trim().parse::<u32>() {
+                level = Some(n);
+            }
+        }
+    }
+    level
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::files::generate_ass;
+    use crate::project::SubtitleFileMeta;
+
+    const SCRIPT: &str = "[Script Info]\nTitle: Пример\nScriptType: v4.00+\nPlayResX: 1920\nPlayResY: 1080\n\n\
+[V4+ Styles]\n\
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+Style: Default,Arial,52,&H00FFFFFF,&H000000FF,&H00000000,&H64000000,-1,0,0,0,100,100,0,0,1,2.5,1,2,20,20,30,1\n\
+Style: Top,Arial,48,&H0000FFFF,&H000000FF,&H00000000,&H64000000,0,0,0,0,100,100,0,0,1,2,1,8,20,20,30,1\n\n\
+[Events]\n\
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+Dialogue: 0,0:00:01.10,0:00:02.00,Default,,0,0,0,,Первая строка\n\
+Dialogue: 0,0:00:05.00,0:00:06.50,Top,Боб,0,0,0,,{\\an8\\pos(960,80)}Привет, {\\i1}мир{\\i0}\\Nвторая строка\n\
+Dialogue: 1,1:02:03.45,1:02:07.89,Default,,0,0,0,,Через час\n";
+
+    /// Разбор → ASS со стилями исходного файла → снова разбор
+    fn round_trip(content: &str) -> (Vec<SubtitleSegment>, Vec<SubtitleSegment>) {
+        let (segments, script) = parse(content).unwrap();
+        let generated = generate_ass(&segments, Some(&SubtitleFileMeta::Ass(script)), "Пример", None);
+        let (reparsed, _) = parse(&generated).unwrap();
+        (segments, reparsed)
+    }
+
+    fn event_meta(seg: &SubtitleSegment) -> &AssEventMeta {
+        match &seg.format_meta {
+            Some(SegmentFormatMeta::Ass(meta)) => meta,
+            other => panic!("нет метаданных ASS: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn timings_and_text_survive_round_trip() {
+        let (segments, reparsed) = round_trip(SCRIPT);
+        assert_eq!(reparsed.len(), segments.len());
+        for (before, after) in segments.iter().zip(&reparsed) {
+            assert_eq!((after.start, after.end), (before.start, before.end), "{:?}", before.text);
+            assert_eq!(after.text, before.text);
+        }
+        assert_eq!(reparsed[2].start, Timestamp::from_hmsm(1, 2, 3, 450));
+    }
+
+    #[test]
+    fn override_tags_and_style_survive_round_trip() {
+        let (segments, reparsed) = round_trip(SCRIPT);
+        let (before, after) = (event_meta(&segments[1]), event_meta(&reparsed[1]));
+        assert_eq!(after.style, "Top");
+        assert_eq!(after.actor, before.actor);
+        assert_eq!(after.leading_tags, "{\\an8\\pos(960,80)}");
+        assert_eq!(reparsed[1].text, "Привет, мир\nвторая строка");
+    }
+
+    #[test]
+    fn millisecond_timings_round_to_centiseconds() {
+        let content = SCRIPT.replace("0:00:01.10,0:00:02.00", "0:00:01.104,0:00:02.996");
+        let (segments, reparsed) = round_trip(&content);
+        assert_eq!(segments[0].start, Timestamp::from_millis(1_104));
+        assert_eq!(reparsed[0].start, Timestamp::from_millis(1_100));
+        assert_eq!(reparsed[0].end, Timestamp::from_millis(3_000));
+    }
+}
@@ -0,0 +1,126 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+/// Текст файла после декодирования и то, как он был декодирован.
+#[derive(Debug)]
+pub struct DecodedText {
+    pub text: String,
+    /// Имя кодировки в терминах WHATWG (`UTF-8`, `windows-1251`, `Shift_JIS`, …)
+    pub encoding: String,
+    pub had_bom: bool,
+    /// В исходных байтах были последовательности, недопустимые для кодировки
+    pub had_errors: bool,
+}
+
+/// Декодирует байты файла субтитров. `forced` — ручной выбор кодировки пользователем
+/// (любая метка WHATWG: `cp1251`, `sjis`, `utf-16le`…); без него кодировка определяется
+/// по BOM, затем по валидности UTF-8, затем статистически.
+pub fn decode(bytes: &[u8], forced: Option<&str>) -> Result<DecodedText, String> {
+    if let Some(label) = forced.map(str::trim).filter(|l| !l.is_empty()) {
+        let encoding = lookup(label)?;
+        let (text, had_errors) = encoding.decode_with_bom_removal(bytes);
+        return Ok(DecodedText {
+            text: text.into_owned(),
+            encoding: encoding.name().to_string(),
+            had_bom: Encoding::for_bom(bytes).is_some(),
+            had_errors,
+        });
+    }
+
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (text, had_errors) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        return Ok(DecodedText {
+            text: text.into_owned(),
+            encoding: encoding.name().to_string(),
+            had_bom: true,
+            had_errors,
+        });
+    }
+
+    let encoding = if let Some(utf16) = sniff_utf16_without_bom(bytes) {
+        utf16
+    } else if std::str::from_utf8(bytes).is_ok() {
+        UTF_8
+    } else {
+        let mut detector = EncodingDetector::new();
+        detector.feed(bytes, true);
+        detector.guess(None, true)
+    };
+
+    let (text, had_errors) = encoding.decode_without_bom_handling(bytes);
+    Ok(DecodedText {
+        text: text.into_owned(),
+        encoding: encoding.name().to_string(),
+        had_bom: false,
+        had_errors,
+    })
+}
+
+/// Кодирует текст для записи на диск. UTF-16 пишется с BOM; для однобайтовых и CJK
+/// кодировок символы, которых нет в кодовой странице, — ошибка, а не тихая замена.
+pub fn encode(text: &str, label: &str) -> Result<Vec<u8>, String> {
+    let normalized = label.trim().to_lowercase();
+    if normalized == "utf-8-bom" || normalized == "utf8-bom" {
+        let mut out = vec![0xEF, 0xBB, 0xBF];
+        out.extend_from_slice(text.as_bytes());
+        return Ok(out);
+    }
+
+    let encoding = lookup(label)?;
+    if encoding == UTF_8 {
+        return Ok(text.as_bytes().to_vec());
+    }
+    // encoding_rs не кодирует в UTF-16 (отдаёт UTF-8), поэтому пишем вручную
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        let little_endian = encoding == UTF_16LE;
+        let mut out = if little_endian { vec![0xFF, 0xFE] } else { vec![0xFE, 0xFF] };
+        for unit in text.encode_utf16() {
+            let pair = if little_endian { unit.to_le_bytes() } else { unit.to_be_bytes() };
+            out.extend_from_slice(&pair);
+        }
+        return Ok(out);
+    }
+
+    let (bytes, _, had_unmappable) = encoding.encode(text);
+    if had_unmappable {
+        let bad: String = text
+            .chars()
+            .filter(|c| {
+                let mut buf = [0u8; 4];
+                encoding.encode(c.encode_utf8(&mut buf)).2
+            })
+            .take(10)
+            .collect();
+        return Err(format!(
+            "Текст содержит символы, которые нельзя записать в кодировке {}: {}",
+            encoding.name(),
+            bad
+        ));
+    }
+    Ok(bytes.into_owned())
+}
+
+fn lookup(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| format!("Неизвестная кодировка: {}", label))
+}
+
+/// UTF-16 без BOM: в тексте из латиницы/кириллицы каждый второй байт — почти всегда
+/// нулевой или одинаковый старший байт. Смотрим долю нулей на чётных и нечётных позициях.
+fn sniff_utf16_without_bom(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(4096)];
+    if sample.len() < 16 {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let zeros_even = sample.iter().step_by(2).filter(|&&b| b == 0).count();
+    let zeros_odd = sample.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+
+    if zeros_odd * 10 >= pairs * 4 && zeros_even * 10 < pairs {
+        Some(UTF_16LE)
+    } else if zeros_even * 10 >= pairs * 4 && zeros_odd * 10 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,142 @@
+use crate::project::Timestamp;
+use serde::{Deserialize, Serialize};
+
+/// Частота кадров как точная дробь: 23.976 — это 24000/1001, а не 23.976,
+/// иначе на часовом видео набегает ошибка в несколько кадров.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRate {
+    pub numerator: u32,
+    pub denominator: u32,
+}
+
+impl FrameRate {
+    pub const FILM: FrameRate = FrameRate { numerator: 24000, denominator: 1001 };
+
+    /// Из записи ffprobe (`24000/1001`, `25/1`) или десятичной (`23.976`, `29.97`, `25`).
+    /// Частоты ниже 1 кадра в секунду не принимаются: номинальная частота таймкода была бы 0.
+    pub fn parse(value: &str) -> Option<FrameRate> {
+        let value = value.trim();
+        if let Some((num, den)) = value.split_once('/') {
+            let num = num.trim().parse::<u32>().ok()?;
+            let den = den.trim().parse::<u32>().ok()?;
+            if num == 0 || den == 0 || num < den {
+                return None;
+            }
+            return Some(FrameRate { numerator: num, denominator: den }.normalized());
+        }
+        Self::from_fps(value.parse::<f64>().ok()?)
+    }
+
+    /// Десятичная частота с привязкой к стандартным NTSC-частотам (x000/1001).
+    pub fn from_fps(fps: f64) -> Option<FrameRate> {
+        if !fps.is_finite() || fps < 1.0 {
+            return None;
+        }
+        let nominal = fps.round();
+        let ntsc = nominal * 1000.0 / 1001.0;
+        if (fps - nominal).abs() > 1e-3 && (fps - ntsc).abs() < 0.01 {
+            return Some(FrameRate { numerator: nominal as u32 * 1000, denominator: 1001 });
+        }
+        if (fps - nominal).abs() < 1e-3 {
+            return Some(FrameRate { numerator: nominal as u32, denominator: 1 });
+        }
+        Some(FrameRate { numerator: (fps * 1000.0).round() as u32, denominator: 1000 }.normalized())
+    }
+
+    fn normalized(self) -> FrameRate {
+        fn gcd(a: u32, b: u32) -> u32 {
+            if b == 0 { a } else { gcd(b, a % b) }
+        }
+        let g = gcd(self.numerator, self.denominator).max(1);
+        FrameRate { numerator: self.numerator / g, denominator: self.denominator / g }
+    }
+
+    pub fn fps(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// Номинальная частота таймкода: 30 для 29.97, 24 для 23.976
+    pub fn nominal(&self) -> u32 {
+        self.fps().round() as u32
+    }
+
+    /// NTSC-частота (29.97, 59.94, 23.976): только для них бывает drop-frame
+    pub fn is_ntsc(&self) -> bool {
+        self.denominator == 1001
+    }
+
+    /// Drop-frame таймкод определён для 29.97 и 59.94
+    pub fn supports_drop_frame(&self) -> bool {
+        self.is_ntsc() && self.nominal().is_multiple_of(30)
+    }
+
+    pub fn frames_to_seconds(&self, frames: i64) -> f64 {
+        frames as f64 * self.denominator as f64 / self.numerator as f64
+    }
+
+    /// Начало кадра в целых миллисекундах (округление от точной дроби, без `f64`)
+    pub fn frames_to_timestamp(&self, frames: i64) -> Timestamp {
+        let num = frames as i128 * self.denominator as i128 * 1000;
+        let den = self.numerator as i128;
+        Timestamp::from_millis(((2 * num + den).div_euclid(2 * den)) as i64)
+    }
+
+    /// Ближайший кадр к моменту времени, тоже в целочисленной арифметике
+    pub fn timestamp_to_frames(&self, time: Timestamp) -> i64 {
+        let num = time.millis() as i128 * self.numerator as i128;
+        let den = self.denominator as i128 * 1000;
+        ((2 * num + den).div_euclid(2 * den)) as i64
+    }
+
+    /// Ближайший кадр к моменту времени
+    pub fn seconds_to_frames(&self, seconds: f64) -> i64 {
+        (seconds * self.numerator as f64 / self.denominator as f64).round() as i64
+    }
+
+    /// Номер кадра → таймкод `(ч, м, с, кадр)`. В drop-frame режиме номера кадров 0 и 1
+    /// (для 59.94 — 0–3) пропускаются в начале каждой минуты, кроме каждой десятой,
+    /// чтобы таймкод не отставал от реального времени.
+    pub fn frames_to_timecode(&self, frames: i64, drop_frame: bool) -> (u32, u32, u32, u32) {
+        let nominal = self.nominal() as i64;
+        let mut frames = frames.max(0);
+        if drop_frame && self.supports_drop_frame() {
+            let dropped = nominal / 15;
+            let frames_per_10_min = nominal * 600 - dropped * 9;
+            let frames_per_min = nominal * 60 - dropped;
+            let tens = frames / frames_per_10_min;
+            let rest = frames % frames_per_10_min;
+            let skipped = if rest > dropped {
+                dropped * 9 * tens + dropped * ((rest - dropped) / frames_per_min)
+            } else {
+                dropped * 9 * tens
+            };
+            frames += skipped;
+        }
+        let frame = frames % nominal;
+        let total_seconds = frames / nominal;
+        (
+            (total_seconds / 3600) as u32,
+            ((total_seconds % 3600) / 60) as u32,
+            (total_seconds % 60) as u32,
+            frame as u32,
+        )
+    }
+
+    /// Таймкод → номер кадра (обратное к `frames_to_timecode`)
+    pub fn timecode_to_frames(&self, hours: u32, minutes: u32, seconds: u32, frame: u32, drop_frame: bool) -> i64 {
+        let nominal = self.nominal() as i64;
+        let total_minutes = hours as i64 * 60 + minutes as i64;
+        let mut frames = (total_minutes * 60 + seconds as i64) * nominal + frame as i64;
+        if drop_frame && self.supports_drop_frame() {
+            let dropped = nominal / 15;
+            frames -= dropped * (total_minutes - total_minutes / 10);
+        }
+        frames
+    }
+}
+
+impl Default for FrameRate {
+    fn default() -> Self {
+        FrameRate::FILM
+    }
+}
@@ -0,0 +1,148 @@
+use crate::project::{SegmentFormatMeta, SubtitleSegment};
+use super::framerate::FrameRate;
+use super::ParseDiagnostic;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Данные строки MicroDVD помимо времени и текста.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MicroDvdCueMeta {
+    /// Управляющие коды в начале строки: `{y:i}`, `{c:$0000FF}` и т.п.
+    pub leading_codes: String,
+    /// Текст строки как есть (с кодами и `|`)
+    pub raw_text: String,
+}
+
+/// Разбор `{начальный кадр}{конечный кадр}текст`. Частота кадров берётся из строки-заголовка
+/// `{1}{1}23.976`, если она есть, иначе `frame_rate` (частота видео проекта), иначе 23.976.
+/// Возвращает и фактически использованную частоту.
+pub fn parse(
+    content: &str,
+    frame_rate: Option<FrameRate>,
+) -> Result<(Vec<SubtitleSegment>, FrameRate, Vec<ParseDiagnostic>), String> {
+    let line_regex = Regex::new(r"^\{(\d+)\}\{(\d*)\}(.*)$")
+        .map_err(|e| format!("Ошибка компиляции регулярного выражения: {}", e))?;
+
+    let mut diagnostics = Vec::new();
+    let mut cues: Vec<(usize, i64, Option<i64>, String)> = Vec::new();
+    let mut header_rate: Option<FrameRate> = None;
+
+    for (i, raw_line) in content.trim_start_matches('\u{feff}').lines().enumerate() {
+        let line_no = i + 1;
+        let line = raw_line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some(captures) = line_regex.captures(line) else {
+            diagnostics.push(ParseDiagnostic::skipped(line_no, "Строка не в формате {кадр}{кадр}текст — пропущена"));
+            continue;
+        };
+        let start = captures[1].parse::<i64>().unwrap_or(0);
+        let end = captures[2].parse::<i64>().ok();
+        let text = captures[3].to_string();
+
+        // Заголовок с частотой кадров: `{1}{1}23.976` (иногда `{0}{0}25`)
+        if cues.is_empty() && header_rate.is_none() && start <= 1 && end.unwrap_or(start) <= 1 {
+            if let Some(rate) = FrameRate::parse(&text).filter(|r| (10.0..=120.0).contains(&r.fps())) {
+                header_rate = Some(rate);
+                continue;
+            }
+        }
+
+        cues.push((line_no, start, end, text));
+    }
+
+    let rate = match header_rate.or(frame_rate) {
+        Some(rate) => rate,
+        None => {
+            diagnostics.push(ParseDiagnostic::repaired(
+                1,
+                "Частота кадров неизвестна (нет заголовка и видео) — принята 23.976",
+            ));
+            FrameRate::default()
+        }
+    };
+
+    let mut segments = Vec::new();
+    for (idx, (line_no, start_frame, end_frame, raw_text)) in cues.iter().enumerate() {
+        let end_frame = match end_frame {
+            Some(end) if end >= start_frame => *end,
+            Some(_) => {
+                diagnostics.push(ParseDiagnostic::skipped(*line_no, "Конечный кадр раньше начального — строка пропущена"));
+                continue;
+            }
+            None => {
+                // Конец не указан: до следующей строки, но не дольше 5 секунд
+                let limit = start_frame + rate.seconds_to_frames(5.0);
+                let next = cues.get(idx + 1).map(|c| c.1).unwrap_or(limit);
+                diagnostics.push(ParseDiagnostic::repaired(*line_no, "Не указан конечный кадр"));
+                next.min(limit).max(*start_frame)
+            }
+        };
+
+        let text = plain_text(raw_text);
+        if text.is_empty() {
+            continue;
+        }
+        let start = rate.frames_to_timestamp(*start_frame);
+        let end = rate.frames_to_timestamp(end_frame);
+        segments.push(SubtitleSegment {
+            id: segments.len() as u32 + 1,
+            start,
+            end,
+            duration: end - start,
+            text,
+            translation: None,
+            flags: None,
+            words: None,
+            format_meta: Some(SegmentFormatMeta::MicroDvd(MicroDvdCueMeta {
+                leading_codes: leading_codes(raw_text),
+                raw_text: raw_text.clone(),
+            })),
+        });
+    }
+
+    diagnostics.sort_by_key(|d| d.line);
+    Ok((segments, rate, diagnostics))
+}
+
+/// Текст без управляющих кодов: `|` — перевод строки, `/` в начале строки — курсив.
+pub fn plain_text(raw: &str) -> String {
+    let Ok(codes) = Regex::new(r"\{[A-Za-z]:[^}]*\}") else {
+        return raw.to_string();
+    };
+    codes
+        .replace_all(raw, "")
+        .split('|')
+        .map(|line| line.trim().trim_start_matches('/').trim())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Коды в начале строки (`{y:i}{c:$0000FF}`) — переносим их на переведённый текст.
+pub fn leading_codes(raw: &str) -> String {
+    let mut rest = raw;
+    let mut codes = String::new();
+    while rest.starts_with('{') {
+        let Some(close) = rest.find('}') else { break };
+        let code = &rest[..=close];
+        if code.len() < 4 || code.as_bytes()[2] != b':' {
+            break;
+        }
+        codes.push_str(code);
+        rest = &rest[close + 1..];
+    }
+    codes
+}
+
+pub fn escape_text(text: &str) -> String {
+    text.lines().map(str::trim).collect::<Vec<_>>().join("|")
+}
+
+/// Частота для строки-заголовка: `23.976`, `29.97`, `25`
+pub fn format_frame_rate(rate: FrameRate) -> String {
+    let formatted = format!("{:.3}", rate.fps());
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
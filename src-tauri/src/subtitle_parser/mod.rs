@@ -1,15 +1,50 @@
-use crate::project::SubtitleSegment;
+use crate::project::{SubtitleFileMeta, SubtitleSegment};
+use framerate::FrameRate;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
+pub mod ass;
+pub mod encoding;
+pub mod framerate;
+pub mod microdvd;
+pub mod scc;
 pub mod srt;
+pub mod stl;
+pub mod ttml;
 pub mod vtt;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum SubtitleFormat {
     SRT,
     VTT,
     ASS,
     SSA,
+    MicroDVD,
+    TTML,
+    STL,
+    SCC,
+}
+
+/// Формат, определённый по содержимому файла, и уверенность (0.0–1.0).
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct DetectedFormat {
+    pub format: SubtitleFormat,
+    pub confidence: f64,
+}
+
+/// Имя формата из аргумента команды (`srt`, `vtt`, `ass`, …).
+pub fn format_from_name(name: &str) -> Result<SubtitleFormat, String> {
+    match name.to_lowercase().as_str() {
+        "srt" => Ok(SubtitleFormat::SRT),
+        "vtt" | "webvtt" => Ok(SubtitleFormat::VTT),
+        "ass" => Ok(SubtitleFormat::ASS),
+        "ssa" => Ok(SubtitleFormat::SSA),
+        "sub" | "microdvd" => Ok(SubtitleFormat::MicroDVD),
+        "ttml" | "dfxp" | "xml" => Ok(SubtitleFormat::TTML),
+        "stl" | "ebu-stl" => Ok(SubtitleFormat::STL),
+        "scc" => Ok(SubtitleFormat::SCC),
+        _ => Err(format!("Неподдерживаемый формат: {}", name)),
+    }
 }
 
 pub fn detect_format(path: &Path) -> Result<SubtitleFormat, String> {
@@ -23,16 +58,190 @@ pub fn detect_format(path: &Path) -> Result<SubtitleFormat, String> {
         "vtt" => Ok(SubtitleFormat::VTT),
         "ass" => Ok(SubtitleFormat::ASS),
         "ssa" => Ok(SubtitleFormat::SSA),
+        "sub" => Ok(SubtitleFormat::MicroDVD),
+        "ttml" | "dfxp" => Ok(SubtitleFormat::TTML),
+        "stl" => Ok(SubtitleFormat::STL),
+        "scc" => Ok(SubtitleFormat::SCC),
         _ => Err(format!("Неподдерживаемый формат субтитров: {}", ext)),
     }
 }
 
-pub fn parse_subtitles(content: &str, format: SubtitleFormat) -> Result<Vec<SubtitleSegment>, String> {
+/// Определение формата по содержимому: заголовок `WEBVTT`, `[Script Info]`, `Scenarist_SCC`, корень `<tt>`,
+/// кадры MicroDVD `{N}{M}`, стрелки таймингов SRT. Расширение используется только как
+/// запасной вариант с низкой уверенностью.
+pub fn sniff_format(content: &str, path: &Path) -> Result<DetectedFormat, String> {
+    let head = content.trim_start_matches('\u{feff}').trim_start();
+    let head_lower: String = head.chars().take(4096).collect::<String>().to_lowercase();
+
+    if head.starts_with("WEBVTT") {
+        return Ok(DetectedFormat { format: SubtitleFormat::VTT, confidence: 1.0 });
+    }
+
+    if head.starts_with("Scenarist_SCC") {
+        return Ok(DetectedFormat { format: SubtitleFormat::SCC, confidence: 1.0 });
+    }
+
+    if head_lower.contains("[script info]") || head_lower.contains("[v4+ styles]") || head_lower.contains("[v4 styles]") {
+        let is_ssa = head_lower.contains("[v4 styles]")
+            || (head_lower.contains("scripttype: v4.00") && !head_lower.contains("scripttype: v4.00+"));
+        let confidence = if head_lower.contains("[events]") { 1.0 } else { 0.8 };
+        let format = if is_ssa { SubtitleFormat::SSA } else { SubtitleFormat::ASS };
+        return Ok(DetectedFormat { format, confidence });
+    }
+
+    if head.starts_with('<') && head_lower.contains("<tt") {
+        let confidence = if head_lower.contains("http://www.w3.org/ns/ttml") { 1.0 } else { 0.7 };
+        return Ok(DetectedFormat { format: SubtitleFormat::TTML, confidence });
+    }
+
+    let lines: Vec<&str> = content
+        .lines()
+        .map(|l| l.trim().trim_start_matches('\u{feff}'))
+        .filter(|l| !l.is_empty())
+        .take(400)
+        .collect();
+
+    if !lines.is_empty() {
+        let microdvd = regex::Regex::new(r"^\{\d+\}\{\d*\}").map_err(|e| e.to_string())?;
+        let frame_lines = lines.iter().filter(|l| microdvd.is_match(l)).count();
+        let ratio = frame_lines as f64 / lines.len() as f64;
+        if ratio >= 0.5 {
+            return Ok(DetectedFormat { format: SubtitleFormat::MicroDVD, confidence: ratio });
+        }
+
+        let arrow = regex::Regex::new(r"^(?:\d{1,3}:)?\d{1,2}:\d{1,2}(?:[,.:]\d{1,3})?\s*-{1,2}>")
+            .map_err(|e| e.to_string())?;
+        let timing_positions: Vec<usize> = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| arrow.is_match(l))
+            .map(|(i, _)| i)
+            .collect();
+        if !timing_positions.is_empty() {
+            // Доля таймингов, перед которыми стоит номер, — признак «настоящего» SRT
+            let indexed = timing_positions
+                .iter()
+                .filter(|&&i| i > 0 && lines[i - 1].parse::<u32>().is_ok())
+                .count();
+            let confidence = 0.6 + 0.4 * indexed as f64 / timing_positions.len() as f64;
+            return Ok(DetectedFormat { format: SubtitleFormat::SRT, confidence });
+        }
+    }
+
+    let format = detect_format(path)
+        .map_err(|_| "Не удалось определить формат субтитров по содержимому".to_string())?;
+    Ok(DetectedFormat { format, confidence: 0.3 })
+}
+
+/// Результат разбора файла: сегменты и метаданные уровня файла (заголовок ASS и т.п.).
+#[derive(Debug)]
+pub struct ParsedSubtitles {
+    pub segments: Vec<SubtitleSegment>,
+    pub metadata: Option<SubtitleFileMeta>,
+    /// Что парсер исправил или пропустил (для диалога импорта)
+    pub diagnostics: Vec<ParseDiagnostic>,
+    /// Частота кадров, по которой пересчитаны кадры (для покадровых форматов)
+    pub frame_rate: Option<FrameRate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// Реплика восстановлена, но исходная запись была нестандартной
+    Repaired,
+    /// Реплика или строка не попала в результат
+    Skipped,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ParseDiagnostic {
+    /// Номер строки в исходном файле (с единицы); для EBU STL — номер TTI-блока
+    pub line: usize,
+    pub kind: DiagnosticKind,
+    pub message: String,
+}
+
+impl ParseDiagnostic {
+    pub fn repaired(line: usize, message: &str) -> Self {
+        Self { line, kind: DiagnosticKind::Repaired, message: message.to_string() }
+    }
+
+    pub fn skipped(line: usize, message: &str) -> Self {
+        Self { line, kind: DiagnosticKind::Skipped, message: message.to_string() }
+    }
+}
+
+/// EBU STL разбирается из байтов: кодовые таблицы текста заданы в самом файле.
+pub fn parse_stl(bytes: &[u8]) -> Result<ParsedSubtitles, String> {
+    let (segments, gsi, diagnostics) = stl::parse(bytes)?;
+    Ok(ParsedSubtitles {
+        segments,
+        metadata: Some(SubtitleFileMeta::Stl(Box::new(gsi))),
+        diagnostics,
+        frame_rate: None,
+    })
+}
+
+/// `frame_rate` — частота видео проекта, нужна форматам с таймингом в кадрах (MicroDVD).
+pub fn parse_subtitles(
+    content: &str,
+    format: SubtitleFormat,
+    frame_rate: Option<FrameRate>,
+) -> Result<ParsedSubtitles, String> {
     match format {
-        SubtitleFormat::SRT => srt::parse(content),
-        SubtitleFormat::VTT => vtt::parse(content),
+        SubtitleFormat::SRT => {
+            let (segments, diagnostics) = srt::parse(content)?;
+            Ok(ParsedSubtitles {
+                segments,
+                metadata: None,
+                diagnostics,
+                frame_rate: None,
+            })
+        }
+        SubtitleFormat::VTT => {
+            let (segments, header) = vtt::parse(content)?;
+            Ok(ParsedSubtitles {
+                segments,
+                metadata: Some(SubtitleFileMeta::Vtt(header)),
+                diagnostics: Vec::new(),
+                frame_rate: None,
+            })
+        }
         SubtitleFormat::ASS | SubtitleFormat::SSA => {
-            Err("Форматы ASS/SSA пока не поддерживаются".to_string())
+            let (segments, script) = ass::parse(content)?;
+            Ok(ParsedSubtitles {
+                segments,
+                metadata: Some(SubtitleFileMeta::Ass(script)),
+                diagnostics: Vec::new(),
+                frame_rate: None,
+            })
+        }
+        SubtitleFormat::MicroDVD => {
+            let (segments, rate, diagnostics) = microdvd::parse(content, frame_rate)?;
+            Ok(ParsedSubtitles {
+                segments,
+                metadata: None,
+                diagnostics,
+                frame_rate: Some(rate),
+            })
+        }
+        SubtitleFormat::TTML => {
+            let (segments, document, diagnostics) = ttml::parse(content)?;
+            Ok(ParsedSubtitles {
+                segments,
+                metadata: Some(SubtitleFileMeta::Ttml(document)),
+                diagnostics,
+                frame_rate: None,
+            })
+        }
+        SubtitleFormat::SCC => {
+            let (segments, diagnostics) = scc::parse(content)?;
+            Ok(ParsedSubtitles {
+                segments,
+                metadata: None,
+                diagnostics,
+                frame_rate: None,
+            })
         }
+        SubtitleFormat::STL => Err("EBU STL — двоичный формат, его нужно разбирать из байтов файла".to_string()),
     }
 }
\ No newline at end of file
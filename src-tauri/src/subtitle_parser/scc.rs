@@ -0,0 +1,762 @@
+use crate::project::{SegmentFormatMeta, SubtitleSegment, Timestamp};
+use super::framerate::FrameRate;
+use super::ParseDiagnostic;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+const ROWS: usize = 15;
+const COLUMNS: usize = 32;
+/// SCC всегда передаётся на 29.97, одно слово (два байта) — один кадр
+const SCC_RATE: FrameRate = FrameRate { numerator: 30000, denominator: 1001 };
+const HEADER: &str = "Scenarist_SCC V1.0";
+
+// Вторые байты служебных команд канала CC1 (первый байт 0x14)
+const MISC: u8 = 0x14;
+const RCL: u8 = 0x20;
+const BS: u8 = 0x21;
+const DER: u8 = 0x24;
+const RU2: u8 = 0x25;
+const RU4: u8 = 0x27;
+const RDC: u8 = 0x29;
+const EDM: u8 = 0x2C;
+const CR: u8 = 0x2D;
+const ENM: u8 = 0x2E;
+const EOC: u8 = 0x2F;
+/// Пустой байт-заполнитель (0x80 с битом чётности)
+const PAD: u8 = 0x00;
+
+/// Способ вывода титра в CEA-608.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SccCaptionMode {
+    /// Титр собирается в скрытой памяти и выводится целиком (EOC)
+    PopOn,
+    /// Строки «прокручиваются» снизу вверх
+    RollUp,
+    /// Текст выводится сразу по мере поступления
+    PaintOn,
+}
+
+/// Расположение титра на экране 15×32.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SccCueMeta {
+    pub mode: SccCaptionMode,
+    /// Строка экрана (1–15) для каждой строки текста
+    pub rows: Vec<u8>,
+    /// Колонка начала (0–31) для каждой строки текста
+    pub columns: Vec<u8>,
+    #[serde(default)]
+    pub italic: bool,
+}
+
+/// Реплика для записи: текст и (если была импортирована из SCC) её расположение.
+pub struct SccCue<'a> {
+    pub start: f64,
+    pub end: f64,
+    pub text: &'a str,
+    pub meta: Option<&'a SccCueMeta>,
+}
+
+/// Реплика, выведенная позже своего начала: эфир был занят предыдущим титром.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SccLateCue {
+    /// Номер реплики в списке, переданном в `write` (с нуля)
+    pub index: usize,
+    pub delay_frames: i64,
+}
+
+#[derive(Clone)]
+struct Screen {
+    cells: [[Option<char>; COLUMNS]; ROWS],
+    italic: bool,
+}
+
+impl Screen {
+    fn new() -> Self {
+        Self { cells: [[None; COLUMNS]; ROWS], italic: false }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.cells.iter().all(|row| row.iter().all(|c| c.map(char::is_whitespace).unwrap_or(true)))
+    }
+
+    fn row_text(&self, row: usize) -> Option<(u8, String)> {
+        let cells = &self.cells[row];
+        let first = cells.iter().position(|c| c.map(|c| !c.is_whitespace()).unwrap_or(false))?;
+        let text: String = cells[first..].iter().map(|c| c.unwrap_or(' ')).collect();
+        Some((first as u8, text.trim_end().to_string()))
+    }
+
+    /// Строки с текстом: (строка экрана 1–15, колонка, текст)
+    fn lines(&self) -> Vec<(u8, u8, String)> {
+        (0..ROWS)
+            .filter_map(|row| self.row_text(row).map(|(col, text)| (row as u8 + 1, col, text)))
+            .collect()
+    }
+}
+
+struct Decoder {
+    mode: SccCaptionMode,
+    roll_up_rows: usize,
+    displayed: Screen,
+    hidden: Screen,
+    row: usize,
+    col: usize,
+    shown_since: Option<f64>,
+    last_control: Option<(u8, u8)>,
+    /// Данные второго канала (CC2) пропускаем
+    other_channel: bool,
+    /// Roll-up: завершённые строки, которые ещё на экране, и начало текущей
+    roll_lines: VecDeque<(f64, String)>,
+    line_started: Option<f64>,
+    captions: Vec<(f64, f64, String, SccCueMeta)>,
+}
+
+impl Decoder {
+    fn new() -> Self {
+        Self {
+            mode: SccCaptionMode::PopOn,
+            roll_up_rows: 2,
+            displayed: Screen::new(),
+            hidden: Screen::new(),
+            row: ROWS - 1,
+            col: 0,
+            shown_since: None,
+            last_control: None,
+            other_channel: false,
+            roll_lines: VecDeque::new(),
+            line_started: None,
+            captions: Vec::new(),
+        }
+    }
+
+    fn target(&mut self) -> &mut Screen {
+        match self.mode {
+            SccCaptionMode::PopOn => &mut self.hidden,
+            _ => &mut self.displayed,
+        }
+    }
+
+    fn put_char(&mut self, c: char, now: f64) {
+        if self.mode != SccCaptionMode::PopOn && self.shown_since.is_none() {
+            self.shown_since = Some(now);
+        }
+        if self.mode == SccCaptionMode::RollUp && self.line_started.is_none() && !c.is_whitespace() {
+            self.line_started = Some(now);
+        }
+        let (row, col) = (self.row, self.col);
+        if col < COLUMNS {
+            self.target().cells[row][col] = Some(c);
+            self.col += 1;
+        }
+    }
+
+    /// Титр, выведенный на экран (pop-on, paint-on), снят с экрана в момент `now`
+    fn close_displayed(&mut self, now: f64) {
+        if let Some(since) = self.shown_since.take() {
+            if !self.displayed.is_empty() && self.mode != SccCaptionMode::RollUp {
+                let lines = self.displayed.lines();
+                self.captions.push((
+                    since,
+                    now,
+                    lines.iter().map(|l| l.2.clone()).collect::<Vec<_>>().join("\n"),
+                    SccCueMeta {
+                        mode: self.mode,
+                        rows: lines.iter().map(|l| l.0).collect(),
+                        columns: lines.iter().map(|l| l.1).collect(),
+                        italic: self.displayed.italic,
+                    },
+                ));
+            }
+        }
+    }
+
+    fn close_roll_line(&mut self, start: f64, end: f64, text: String) {
+        self.captions.push((
+            start,
+            end,
+            text,
+            SccCueMeta {
+                mode: SccCaptionMode::RollUp,
+                rows: vec![self.row as u8 + 1],
+                columns: vec![0],
+                italic: self.displayed.italic,
+            },
+        ));
+    }
+
+    /// Roll-up: все строки, что сейчас на экране, снимаются (EDM, смена режима, конец файла)
+    fn flush_roll_up(&mut self, now: f64) {
+        while let Some((start, text)) = self.roll_lines.pop_front() {
+            self.close_roll_line(start, now, text);
+        }
+        if let (Some(start), Some((_, text))) = (self.line_started.take(), self.displayed.row_text(self.row)) {
+            self.close_roll_line(start, now, text);
+        }
+        self.shown_since = None;
+    }
+
+    fn set_mode(&mut self, mode: SccCaptionMode, now: f64) {
+        if self.mode == SccCaptionMode::RollUp && mode != SccCaptionMode::RollUp {
+            self.flush_roll_up(now);
+            self.displayed = Screen::new();
+        }
+        self.mode = mode;
+    }
+
+    fn misc_command(&mut self, code: u8, now: f64) {
+        match code {
+            RCL => self.set_mode(SccCaptionMode::PopOn, now),
+            RDC => self.set_mode(SccCaptionMode::PaintOn, now),
+            RU2..=RU4 => {
+                if self.mode != SccCaptionMode::RollUp {
+                    self.close_displayed(now);
+                    self.displayed = Screen::new();
+                }
+                self.mode = SccCaptionMode::RollUp;
+                self.roll_up_rows = (code - RU2) as usize + 2;
+            }
+            BS if self.col > 0 => {
+                self.col -= 1;
+                let (row, col) = (self.row, self.col);
+                self.target().cells[row][col] = None;
+            }
+            DER => {
+                let (row, col) = (self.row, self.col);
+                for cell in self.target().cells[row].iter_mut().skip(col) {
+                    *cell = None;
+                }
+            }
+            EDM => {
+                if self.mode == SccCaptionMode::RollUp {
+                    self.flush_roll_up(now);
+                } else {
+                    self.close_displayed(now);
+                }
+                self.displayed = Screen::new();
+            }
+            ENM => self.hidden = Screen::new(),
+            EOC => {
+                self.close_displayed(now);
+                std::mem::swap(&mut self.displayed, &mut self.hidden);
+                self.mode = SccCaptionMode::PopOn;
+                if !self.displayed.is_empty() {
+                    self.shown_since = Some(now);
+                }
+            }
+            CR if self.mode == SccCaptionMode::RollUp => {
+                if let (Some(start), Some((_, text))) = (self.line_started.take(), self.displayed.row_text(self.row)) {
+                    self.roll_lines.push_back((start, text));
+                }
+                // В окне из N строк видно N−1 завершённых
+                while self.roll_lines.len() >= self.roll_up_rows {
+                    if let Some((start, text)) = self.roll_lines.pop_front() {
+                        self.close_roll_line(start, now, text);
+                    }
+                }
+                self.displayed.cells[self.row] = [None; COLUMNS];
+                self.col = 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn preamble(&mut self, first: u8, second: u8) {
+        let pair_row = match first {
+            0x11 => Some(0),
+            0x12 => Some(2),
+            0x15 => Some(4),
+            0x16 => Some(6),
+            0x17 => Some(8),
+            0x10 => Some(10),
+            0x13 => Some(11),
+            0x14 => Some(13),
+            _ => None,
+        };
+        let Some(base) = pair_row else { return };
+        let row = if second >= 0x60 && first != 0x10 { base + 1 } else { base };
+        if self.mode == SccCaptionMode::RollUp && row != self.row {
+            // Базовая строка roll-up сменилась — текущая строка переезжает вместе с ней
+            let current = self.displayed.cells[self.row];
+            self.displayed.cells[self.row] = [None; COLUMNS];
+            self.displayed.cells[row] = current;
+        }
+        self.row = row;
+        let attribute = second & 0x1F;
+        if attribute >= 0x10 {
+            self.col = ((attribute & 0x0E) >> 1) as usize * 4;
+        } else {
+            self.col = 0;
+            if attribute & 0x0E == 0x0E {
+                self.target().italic = true;
+            }
+        }
+    }
+
+    fn word(&mut self, b1: u8, b2: u8, now: f64) {
+        if (0x10..=0x1F).contains(&b1) {
+            // Управляющие коды передаются дважды — повтор игнорируется
+            if self.last_control == Some((b1, b2)) {
+                self.last_control = None;
+                return;
+            }
+            self.last_control = Some((b1, b2));
+            self.other_channel = b1 & 0x08 != 0;
+            if self.other_channel {
+                return;
+            }
+            match (b1, b2) {
+                (MISC, 0x20..=0x2F) => self.misc_command(b2, now),
+                (0x17, 0x21..=0x23) => self.col = (self.col + (b2 - 0x20) as usize).min(COLUMNS - 1),
+                // Mid-row: цвет/курсив, на экране занимает позицию пробела
+                (0x11, 0x20..=0x2F) => {
+                    if b2 >= 0x2E {
+                        self.target().italic = true;
+                    }
+                    self.put_char(' ', now);
+                }
+                (0x11, 0x30..=0x3F) => self.put_char(SPECIAL[(b2 - 0x30) as usize], now),
+                (0x12 | 0x13, 0x20..=0x3F) => {
+                    // Расширенный символ заменяет предыдущий (его упрощённую замену)
+                    if self.col > 0 {
+                        self.col -= 1;
+                    }
+                    let table = if b1 == 0x12 { &EXTENDED_12 } else { &EXTENDED_13 };
+                    self.put_char(table[(b2 - 0x20) as usize].0, now);
+                }
+                (0x10..=0x17, 0x40..=0x7F) => self.preamble(b1, b2),
+                _ => {}
+            }
+            return;
+        }
+
+        self.last_control = None;
+        if self.other_channel {
+            return;
+        }
+        for b in [b1, b2] {
+            if b >= 0x20 {
+                self.put_char(standard_char(b), now);
+            }
+        }
+    }
+}
+
+pub fn parse(content: &str) -> Result<(Vec<SubtitleSegment>, Vec<ParseDiagnostic>), String> {
+    let line_regex = Regex::new(r"^(\d{1,2}):(\d{2}):(\d{2})([:;.,])(\d{2})\s+(.*)$")
+        .map_err(|e| format!("Ошибка компиляции регулярного выражения: {}", e))?;
+
+    let mut diagnostics = Vec::new();
+    let mut decoder = Decoder::new();
+    let mut now = 0.0;
+    let mut saw_header = false;
+
+    for (i, raw_line) in content.trim_start_matches('\u{feff}').lines().enumerate() {
+        let line_no = i + 1;
+        let line = raw_line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with("Scenarist_SCC") {
+            saw_header = true;
+            continue;
+        }
+        let Some(captures) = line_regex.captures(line) else {
+            diagnostics.push(ParseDiagnostic::skipped(line_no, "Строка без таймкода — пропущена"));
+            continue;
+        };
+
+        let part = |n: usize| captures[n].parse::<u32>().unwrap_or(0);
+        // `;` (или `.`/`,`) перед кадрами — drop-frame таймкод
+        let drop_frame = &captures[4] != ":";
+        let first_frame = SCC_RATE.timecode_to_frames(part(1), part(2), part(3), part(5), drop_frame);
+
+        for (n, word) in captures[6].split_whitespace().enumerate() {
+            let Ok(value) = u16::from_str_radix(word, 16) else {
+                diagnostics.push(ParseDiagnostic::repaired(line_no, &format!("Некорректное слово «{}» пропущено", word)));
+                continue;
+            };
+            if word.len() != 4 {
+                diagnostics.push(ParseDiagnostic::repaired(line_no, &format!("Некорректное слово «{}» пропущено", word)));
+                continue;
+            }
+            now = SCC_RATE.frames_to_seconds(first_frame + n as i64);
+            let [b1, b2] = value.to_be_bytes();
+            decoder.word(b1 & 0x7F, b2 & 0x7F, now);
+        }
+    }
+
+    if !saw_header {
+        diagnostics.push(ParseDiagnostic::repaired(1, "Нет заголовка Scenarist_SCC V1.0"));
+    }
+
+    // Титр, оставшийся на экране в конце файла
+    let on_screen = decoder.shown_since.is_some() || decoder.line_started.is_some() || !decoder.roll_lines.is_empty();
+    if on_screen {
+        let end = now.max(decoder.shown_since.unwrap_or(now) + 2.0);
+        if decoder.mode == SccCaptionMode::RollUp {
+            decoder.flush_roll_up(end);
+        } else {
+            decoder.close_displayed(end);
+        }
+        diagnostics.push(ParseDiagnostic::repaired(
+            content.lines().count(),
+            "Последний титр не снят с экрана (нет EDM) — длительность 2 секунды",
+        ));
+    }
+
+    let mut captions = decoder.captions;
+    captions.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    let segments = captions
+        .into_iter()
+        .filter(|c| !c.2.trim().is_empty())
+        .enumerate()
+        .map(|(i, (start, end, text, meta))| SubtitleSegment {
+            id: i as u32 + 1,
+            start: Timestamp::from_seconds(start),
+            end: Timestamp::from_seconds(end),
+            duration: Timestamp::from_seconds(end) - Timestamp::from_seconds(start),
+            text,
+            translation: None,
+            flags: None,
+            words: None,
+            format_meta: Some(SegmentFormatMeta::Scc(meta)),
+        })
+        .collect();
+
+    Ok((segments, diagnostics))
+}
+
+/// Собирает SCC с pop-on титрами. Загрузка титра в скрытую память начинается заранее
+/// (pre-roll), чтобы команда вывода EOC пришлась ровно на начало реплики; если эфир
+/// в это время занят предыдущим титром, вывод сдвигается на ближайший свободный кадр —
+/// такие реплики возвращаются вместе с файлом.
+pub fn write(cues: &[SccCue]) -> Result<(String, Vec<SccLateCue>), String> {
+    let mut stream: BTreeMap<i64, [u8; 2]> = BTreeMap::new();
+    // Раньше этого кадра нельзя начинать загрузку: предыдущий титр ещё не выведен
+    let mut floor: i64 = 0;
+    let mut late = Vec::new();
+
+    let targets: Vec<i64> = cues.iter().map(|c| SCC_RATE.seconds_to_frames(c.start)).collect();
+
+    for (i, cue) in cues.iter().enumerate() {
+        let lines = wrap_lines(cue.text);
+        if lines.is_empty() {
+            continue;
+        }
+        let mut load = vec![[MISC, ENM], [MISC, ENM], [MISC, RCL], [MISC, RCL]];
+        load.extend(encode_caption(&lines, cue.meta)?);
+
+        let target = targets[i];
+        let eoc_frame = place_load(&mut stream, &load, target, floor);
+        floor = eoc_frame + 2;
+        if eoc_frame > target {
+            late.push(SccLateCue { index: i, delay_frames: eoc_frame - target });
+        }
+
+        // EDM не нужен, если следующий титр сменяет этот без паузы (EOC заменяет экран)
+        let end_frame = SCC_RATE.seconds_to_frames(cue.end).max(eoc_frame + 2);
+        let next_replaces = targets.get(i + 1).map(|&next| next <= end_frame + 1).unwrap_or(false);
+        if !next_replaces {
+            let edm_frame = first_free_pair(&stream, end_frame);
+            stream.insert(edm_frame, [MISC, EDM]);
+            stream.insert(edm_frame + 1, [MISC, EDM]);
+        }
+    }
+
+    let mut result = format!("{}\n\n", HEADER);
+    let mut line_start: Option<i64> = None;
+    let mut previous = i64::MIN;
+    let mut words: Vec<String> = Vec::new();
+    for (&frame, word) in &stream {
+        if frame != previous + 1 {
+            if let Some(start) = line_start {
+                result.push_str(&format!("{}\t{}\n\n", format_timecode(start), words.join(" ")));
+            }
+            line_start = Some(frame);
+            words.clear();
+        }
+        words.push(format!("{:02x}{:02x}", with_parity(word[0]), with_parity(word[1])));
+        previous = frame;
+    }
+    if let Some(start) = line_start {
+        result.push_str(&format!("{}\t{}\n", format_timecode(start), words.join(" ")));
+    }
+
+    Ok((result, late))
+}
+
+/// Раскладывает слова загрузки по кадрам так, чтобы пара EOC пришлась на `target`
+/// (или ближайшие свободные кадры после него). Возвращает кадр первого EOC.
+fn place_load(stream: &mut BTreeMap<i64, [u8; 2]>, load: &[[u8; 2]], target: i64, floor: i64) -> i64 {
+    let eoc_frame = first_free_pair(stream, target.max(floor));
+
+    // Назад от EOC по свободным кадрам
+    let mut frames = Vec::with_capacity(load.len());
+    let mut pos = eoc_frame - 1;
+    while frames.len() < load.len() && pos >= floor {
+        if !stream.contains_key(&pos) {
+            frames.push(pos);
+        }
+        pos -= 1;
+    }
+
+    if frames.len() == load.len() {
+        for (word, frame) in load.iter().zip(frames.iter().rev()) {
+            stream.insert(*frame, *word);
+        }
+        stream.insert(eoc_frame, [MISC, EOC]);
+        stream.insert(eoc_frame + 1, [MISC, EOC]);
+        return eoc_frame;
+    }
+
+    // Не хватает времени до начала реплики — передаём с `floor`, вывод сдвигается
+    let mut pos = floor;
+    for word in load {
+        while stream.contains_key(&pos) {
+            pos += 1;
+        }
+        stream.insert(pos, *word);
+        pos += 1;
+    }
+    let eoc_frame = first_free_pair(stream, pos);
+    stream.insert(eoc_frame, [MISC, EOC]);
+    stream.insert(eoc_frame + 1, [MISC, EOC]);
+    eoc_frame
+}
+
+/// Повтор управляющего кода должен идти в соседнем кадре, иначе он выполнится дважды
+fn first_free_pair(stream: &BTreeMap<i64, [u8; 2]>, from: i64) -> i64 {
+    let mut frame = from.max(0);
+    while stream.contains_key(&frame) || stream.contains_key(&(frame + 1)) {
+        frame += 1;
+    }
+    frame
+}
+
+/// Перенос по словам на строки не длиннее 32 символов.
+fn wrap_lines(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    for source in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let mut current = String::new();
+        for word in source.split_whitespace() {
+            let mut word = word.to_string();
+            // Слово длиннее строки режем жёстко
+            while word.chars().count() > COLUMNS {
+                if !current.is_empty() {
+                    lines.push(std::mem::take(&mut current));
+                }
+                let head: String = word.chars().take(COLUMNS).collect();
+                word = word.chars().skip(COLUMNS).collect();
+                lines.push(head);
+            }
+            let needed = current.chars().count() + word.chars().count() + usize::from(!current.is_empty());
+            if needed > COLUMNS && !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(&word);
+        }
+        if !current.is_empty() {
+            lines.push(current);
+        }
+    }
+    lines
+}
+
+/// PAC, отступ и текст каждой строки титра.
+fn encode_caption(lines: &[String], meta: Option<&SccCueMeta>) -> Result<Vec<[u8; 2]>, String> {
+    if lines.len() > ROWS {
+        return Err(format!("Титр не помещается на экран CEA-608: {} строк", lines.len()));
+    }
+    let italic = meta.map(|m| m.italic).unwrap_or(false);
+    let keep_layout = meta
+        .filter(|m| m.mode == SccCaptionMode::PopOn && m.rows.len() == lines.len() && m.columns.len() == lines.len())
+        .filter(|m| {
+            lines
+                .iter()
+                .zip(&m.columns)
+                .all(|(line, &col)| col as usize + line.chars().count() + usize::from(italic) <= COLUMNS)
+        });
+
+    let mut words = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let width = line.chars().count() + usize::from(italic);
+        let (row, column) = match keep_layout {
+            Some(m) => (m.rows[i] as usize, m.columns[i] as usize),
+            // Снизу экрана, по центру
+            None => (ROWS - lines.len() + i + 1, (COLUMNS - width.min(COLUMNS)) / 2),
+        };
+
+        let (pac1, pac_row_base) = preamble_bytes(row);
+        let indent = (column / 4).min(7) as u8;
+        let pac = [pac1, pac_row_base | 0x10 | (indent << 1)];
+        words.push(pac);
+        words.push(pac);
+        let tab = column - indent as usize * 4;
+        if tab > 0 {
+            let to = [0x17, 0x20 + tab as u8];
+            words.push(to);
+            words.push(to);
+        }
+        if italic {
+            words.push([0x11, 0x2E]);
+            words.push([0x11, 0x2E]);
+        }
+
+        let mut pending: Option<u8> = None;
+        for c in line.chars() {
+            match encode_char(c).ok_or_else(|| format!("Символ «{}» не поддерживается CEA-608", c))? {
+                Encoded::Standard(b) => match pending.take() {
+                    Some(first) => words.push([first, b]),
+                    None => pending = Some(b),
+                },
+                Encoded::Control(b1, b2, fallback) => {
+                    if let Some(fallback) = fallback {
+                        match pending.take() {
+                            Some(first) => words.push([first, fallback]),
+                            None => words.push([fallback, PAD]),
+                        }
+                    } else if let Some(first) = pending.take() {
+                        words.push([first, PAD]);
+                    }
+                    // Как и прочие служебные пары, передаётся дважды: декодер пропускает
+                    // повтор, и два одинаковых символа подряд («♪♪») не сливаются в один
+                    words.push([b1, b2]);
+                    words.push([b1, b2]);
+                }
+            }
+        }
+        if let Some(first) = pending {
+            words.push([first, PAD]);
+        }
+    }
+    Ok(words)
+}
+
+/// Первый байт PAC и база второго байта для строки экрана 1–15
+fn preamble_bytes(row: usize) -> (u8, u8) {
+    match row {
+        1 => (0x11, 0x40), 2 => (0x11, 0x60),
+        3 => (0x12, 0x40), 4 => (0x12, 0x60),
+        5 => (0x15, 0x40), 6 => (0x15, 0x60),
+        7 => (0x16, 0x40), 8 => (0x16, 0x60),
+        9 => (0x17, 0x40), 10 => (0x17, 0x60),
+        11 => (0x10, 0x40),
+        12 => (0x13, 0x40), 13 => (0x13, 0x60),
+        14 => (0x14, 0x40),
+        _ => (0x14, 0x60),
+    }
+}
+
+enum Encoded {
+    Standard(u8),
+    /// Служебная пара и упрощённый символ, который она заменяет
+    Control(u8, u8, Option<u8>),
+}
+
+fn encode_char(c: char) -> Option<Encoded> {
+    if let Some(i) = STANDARD_EXCEPTIONS.iter().position(|&(_, ch)| ch == c) {
+        return Some(Encoded::Standard(STANDARD_EXCEPTIONS[i].0));
+    }
+    if c == '’' {
+        return Some(Encoded::Standard(b'\''));
+    }
+    let in_exceptions = STANDARD_EXCEPTIONS.iter().any(|&(b, _)| b as char == c);
+    if (' '..='~').contains(&c) && !in_exceptions {
+        return Some(Encoded::Standard(c as u8));
+    }
+    if let Some(i) = SPECIAL.iter().position(|&ch| ch == c) {
+        return Some(Encoded::Control(0x11, 0x30 + i as u8, None));
+    }
+    for (first, table) in [(0x12u8, &EXTENDED_12), (0x13u8, &EXTENDED_13)] {
+        if let Some(i) = table.iter().position(|&(ch, _)| ch == c) {
+            return Some(Encoded::Control(first, 0x20 + i as u8, Some(table[i].1 as u8)));
+        }
+    }
+    None
+}
+
+fn standard_char(b: u8) -> char {
+    STANDARD_EXCEPTIONS
+        .iter()
+        .find(|&&(code, _)| code == b)
+        .map(|&(_, c)| c)
+        .unwrap_or(b as char)
+}
+
+fn with_parity(b: u8) -> u8 {
+    if b.count_ones().is_multiple_of(2) { b | 0x80 } else { b }
+}
+
+fn format_timecode(frame: i64) -> String {
+    let (h, m, s, f) = SCC_RATE.frames_to_timecode(frame, true);
+    format!("{:02}:{:02}:{:02};{:02}", h, m, s, f)
+}
+
+/// Позиции базового набора, где CEA-608 отличается от ASCII
+const STANDARD_EXCEPTIONS: [(u8, char); 10] = [
+    (0x2A, 'á'), (0x5C, 'é'), (0x5E, 'í'), (0x5F, 'ó'), (0x60, 'ú'),
+    (0x7B, 'ç'), (0x7C, '÷'), (0x7D, 'Ñ'), (0x7E, 'ñ'), (0x7F, '█'),
+];
+
+/// Специальные символы: 0x11 0x30–0x3F (0x39 — прозрачный пробел)
+const SPECIAL: [char; 16] = [
+    '®', '°', '½', '¿', '™', '¢', '£', '♪', 'à', '\u{A0}', 'è', 'â', 'ê', 'î', 'ô', 'û',
+];
+
+/// Расширенные символы 0x12 0x20–0x3F и их упрощённая замена в базовом наборе
+const EXTENDED_12: [(char, char); 32] = [
+    ('Á', 'A'), ('É', 'E'), ('Ó', 'O'), ('Ú', 'U'), ('Ü', 'U'), ('ü', 'u'), ('‘', '\''), ('¡', '!'),
+    ('*', '-'), ('\'', '\''), ('—', '-'), ('©', 'c'), ('℠', 's'), ('•', '.'), ('“', '"'), ('”', '"'),
+    ('À', 'A'), ('Â', 'A'), ('Ç', 'C'), ('È', 'E'), ('Ê', 'E'), ('Ë', 'E'), ('ë', 'e'), ('Î', 'I'),
+    ('Ï', 'I'), ('ï', 'i'), ('Ô', 'O'), ('Ù', 'U'), ('ù', 'u'), ('Û', 'U'), ('«', '"'), ('»', '"'),
+];
+
+/// Расширенные символы 0x13 0x20–0x3F
+const EXTENDED_13: [(char, char); 32] = [
+    ('Ã', 'A'), ('ã', 'a'), ('Í', 'I'), ('Ì', 'I'), ('ì', 'i'), ('Ò', 'O'), ('ò', 'o'), ('Õ', 'O'),
+    ('õ', 'o'), ('{', '('), ('}', ')'), ('\\', '/'), ('^', '\''), ('_', '-'), ('|', '!'), ('~', '-'),
+    ('Ä', 'A'), ('ä', 'a'), ('Ö', 'O'), ('ö', 'o'), ('ß', 's'), ('¥', 'Y'), ('¤', 'C'), ('¦', '!'),
+    ('Å', 'A'), ('å', 'a'), ('Ø', 'O'), ('ø', 'o'), ('┌', '+'), ('┐', '+'), ('└', '+'), ('┘', '+'),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(text: &str) -> Vec<SubtitleSegment> {
+        let cues = [SccCue { start: 2.0, end: 4.0, text, meta: None }];
+        let (content, late) = write(&cues).unwrap();
+        assert!(late.is_empty());
+        parse(&content).unwrap().0
+    }
+
+    #[test]
+    fn repeated_special_characters_survive_round_trip() {
+        let segments = round_trip("♪♪");
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].text, "♪♪");
+    }
+
+    #[test]
+    fn repeated_extended_characters_survive_round_trip() {
+        let segments = round_trip("Ééé ♪ la");
+        assert_eq!(segments[0].text, "Ééé ♪ la");
+    }
+
+    #[test]
+    fn late_cues_are_reported() {
+        // Вторая реплика начинается через кадр после первой — загрузка не успевает
+        let cues = [
+            SccCue { start: 2.0, end: 2.02, text: "First caption line", meta: None },
+            SccCue { start: 2.04, end: 4.0, text: "Second caption line", meta: None },
+        ];
+        let (_, late) = write(&cues).unwrap();
+        assert_eq!(late.len(), 1);
+        assert_eq!(late[0].index, 1);
+        assert!(late[0].delay_frames > 0);
+    }
+}
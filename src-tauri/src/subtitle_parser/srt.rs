@@ -1,86 +1,229 @@
-use crate::project::SubtitleSegment;
+use crate::project::{SubtitleSegment, Timestamp};
+use super::ParseDiagnostic;
 use regex::Regex;
 
-pub fn parse(content: &str) -> Result<Vec<SubtitleSegment>, String> {
+struct PendingCue {
+    start: Timestamp,
+    end: Timestamp,
+    text_lines: Vec<String>,
+}
+
+/// Терпимый разбор SRT: восстанавливает реплики без номера, с точкой вместо запятой,
+/// однозначными часами, без пустой строки между блоками и т.п. Всё исправленное
+/// и пропущенное попадает в диагностику с номером строки.
+pub fn parse(content: &str) -> Result<(Vec<SubtitleSegment>, Vec<ParseDiagnostic>), String> {
     let mut segments = Vec::new();
-    let lines: Vec<&str> = content.lines().collect();
-    let mut i = 0;
-    
-    // Компилируем регулярное выражение для времени
-    let time_regex = Regex::new(r"(\d{2}):(\d{2}):(\d{2}),(\d{3})\s*-->\s*(\d{2}):(\d{2}):(\d{2}),(\d{3})")
+    let mut diagnostics = Vec::new();
+
+    if content.starts_with('\u{feff}') {
+        diagnostics.push(ParseDiagnostic::repaired(1, "Удалён BOM в начале файла"));
+    }
+    let normalized = content
+        .trim_start_matches('\u{feff}')
+        .replace("\r\n", "\n")
+        .replace('\r', "\n");
+    let lines: Vec<&str> = normalized.lines().collect();
+
+    // Часы и миллисекунды необязательны, разделитель — запятая или точка
+    let time_regex = Regex::new(
+        r"^(?:(\d{1,3}):)?(\d{1,2}):(\d{1,2})(?:([,.])(\d{1,3}))?\s*-{1,2}>\s*(?:(\d{1,3}):)?(\d{1,2}):(\d{1,2})(?:([,.])(\d{1,3}))?(.*)$",
+    )
+    .map_err(|e| format!("Ошибка компиляции регулярного выражения: {}", e))?;
+    // `ЧЧ:ММ:СС:КК` — кадры, а не миллисекунды; без частоты кадров время не восстановить
+    let frame_timecode_regex = Regex::new(r"^\d{1,3}:\d{1,2}:\d{1,2}:\d{1,3}\s*-{1,2}>")
         .map_err(|e| format!("Ошибка компиляции регулярного выражения: {}", e))?;
-    
-    while i < lines.len() {
-        let line = lines[i].trim();
-        
-        // Пропускаем пустые строки
-        if line.is_empty() {
-            i += 1;
+
+    let mut current: Option<PendingCue> = None;
+    // Номер реплики, встреченный между блоками (номер строки для диагностики)
+    let mut pending_index: Option<usize> = None;
+    // Блок с нераспознанным таймингом пропускается до пустой строки
+    let mut skipping = false;
+
+    for (i, raw_line) in lines.iter().enumerate() {
+        let line_no = i + 1;
+        let line = raw_line.trim();
+
+        if let Some(captures) = time_regex.captures(line) {
+            if let Some(mut cue) = current.take() {
+                // Нет пустой строки между репликами: номер следующей оказался в тексте
+                let index_in_text = cue
+                    .text_lines
+                    .last()
+                    .map(|l| l.trim().parse::<u32>().is_ok())
+                    .unwrap_or(false);
+                if index_in_text {
+                    cue.text_lines.pop();
+                    pending_index = Some(line_no - 1);
+                }
+                diagnostics.push(ParseDiagnostic::repaired(
+                    line_no,
+                    "Нет пустой строки перед репликой — блоки разделены по строке тайминга",
+                ));
+                push_cue(&mut segments, cue);
+            }
+            skipping = false;
+
+            if pending_index.take().is_none() {
+                diagnostics.push(ParseDiagnostic::repaired(line_no, "Отсутствует номер реплики"));
+            }
+
+            let mut repairs = Vec::new();
+            let start = captures_to_time(&captures, 1, &mut repairs);
+            let end = captures_to_time(&captures, 6, &mut repairs);
+            if !line.contains("-->") {
+                repairs.push("нестандартная стрелка");
+            }
+            if !captures[11].trim().is_empty() {
+                repairs.push("лишние символы после тайминга");
+            }
+            if line != *raw_line {
+                repairs.push("лишние пробелы");
+            }
+            let mut seen = Vec::new();
+            repairs.retain(|r| {
+                let first = !seen.contains(r);
+                seen.push(*r);
+                first
+            });
+
+            if end < start {
+                diagnostics.push(ParseDiagnostic::skipped(
+                    line_no,
+                    "Конец реплики раньше начала — реплика пропущена",
+                ));
+                skipping = true;
+                continue;
+            }
+            if !repairs.is_empty() {
+                diagnostics.push(ParseDiagnostic::repaired(
+                    line_no,
+                    &format!("Тайминг исправлен: {}", repairs.join(", ")),
+                ));
+            }
+
+            current = Some(PendingCue {
+                start,
+                end,
+                text_lines: Vec::new(),
+            });
             continue;
         }
-        
-        // Проверяем, является ли строка номером сегмента
-        if line.parse::<u32>().is_ok() {
-            i += 1;
-            if i >= lines.len() {
-                break;
+
+        if frame_timecode_regex.is_match(line) {
+            if let Some(cue) = current.take() {
+                push_cue(&mut segments, cue);
             }
-            
-            // Следующая строка должна содержать время
-            let time_line = lines[i].trim();
-            if let Some(captures) = time_regex.captures(time_line) {
-                let start_hours = captures[1].parse::<u32>().unwrap_or(0);
-                let start_minutes = captures[2].parse::<u32>().unwrap_or(0);
-                let start_seconds = captures[3].parse::<u32>().unwrap_or(0);
-                let start_millis = captures[4].parse::<u32>().unwrap_or(0);
-                
-                let end_hours = captures[5].parse::<u32>().unwrap_or(0);
-                let end_minutes = captures[6].parse::<u32>().unwrap_or(0);
-                let end_seconds = captures[7].parse::<u32>().unwrap_or(0);
-                let end_millis = captures[8].parse::<u32>().unwrap_or(0);
-                
-                let start = (start_hours as f64 * 3600.0) + 
-                           (start_minutes as f64 * 60.0) + 
-                           (start_seconds as f64) + 
-                           (start_millis as f64 / 1000.0);
-                
-                let end = (end_hours as f64 * 3600.0) + 
-                         (end_minutes as f64 * 60.0) + 
-                         (end_seconds as f64) + 
-                         (end_millis as f64 / 1000.0);
-                
-                i += 1;
-                let mut text_lines = Vec::new();
-                
-                // Собираем текст сегмента (до пустой строки или конца)
-                while i < lines.len() && !lines[i].trim().is_empty() {
-                    text_lines.push(lines[i]);
-                    i += 1;
+            diagnostics.push(ParseDiagnostic::skipped(
+                line_no,
+                "Таймкод с кадрами (ЧЧ:ММ:СС:КК) — реплика пропущена",
+            ));
+            pending_index = None;
+            skipping = true;
+            continue;
+        }
+
+        if let Some(cue) = current.as_mut() {
+            if line.is_empty() {
+                if let Some(cue) = current.take() {
+                    push_cue(&mut segments, cue);
                 }
-                
-                let text = text_lines.join("\n");
-                let id = segments.len() as u32 + 1;
-                
-                segments.push(SubtitleSegment {
-                    id,
-                    start,
-                    end,
-                    duration: end - start,
-                    text,
-                    translation: None,
-                    flags: None,
-                });
             } else {
-                // Если строка времени не распознана, пропускаем сегмент
-                while i < lines.len() && !lines[i].trim().is_empty() {
-                    i += 1;
-                }
+                cue.text_lines.push(raw_line.trim_end().to_string());
+            }
+            continue;
+        }
+
+        if line.is_empty() {
+            skipping = false;
+            if let Some(index_line) = pending_index.take() {
+                diagnostics.push(ParseDiagnostic::skipped(
+                    index_line,
+                    "Номер реплики без тайминга — пропущен",
+                ));
             }
+            continue;
+        }
+        if skipping {
+            continue;
+        }
+
+        if pending_index.is_none() && line.parse::<u32>().is_ok() {
+            pending_index = Some(line_no);
+            continue;
+        }
+
+        let kind = if line.contains("-->") {
+            "Не распознан тайминг — реплика пропущена"
         } else {
-            // Если строка не является номером, пропускаем её
-            i += 1;
+            "Строка вне реплики — пропущена"
+        };
+        diagnostics.push(ParseDiagnostic::skipped(line_no, kind));
+        pending_index = None;
+        skipping = true;
+    }
+
+    if let Some(cue) = current.take() {
+        push_cue(&mut segments, cue);
+    }
+
+    Ok((segments, diagnostics))
+}
+
+fn push_cue(segments: &mut Vec<SubtitleSegment>, cue: PendingCue) {
+    let id = segments.len() as u32 + 1;
+    segments.push(SubtitleSegment {
+        id,
+        start: cue.start,
+        end: cue.end,
+        duration: cue.end - cue.start,
+        text: cue.text_lines.join("\n"),
+        translation: None,
+        flags: None,
+        words: None,
+        format_meta: None,
+    });
+}
+
+/// Группы `first..first+4`: часы, минуты, секунды, разделитель, миллисекунды.
+fn captures_to_time(captures: &regex::Captures, first: usize, repairs: &mut Vec<&'static str>) -> Timestamp {
+    let hours = match captures.get(first) {
+        Some(h) => {
+            if h.as_str().len() != 2 {
+                repairs.push("нестандартная запись часов");
+            }
+            h.as_str().parse::<u32>().unwrap_or(0)
+        }
+        None => {
+            repairs.push("нет часов");
+            0
         }
+    };
+    let minutes_str = &captures[first + 1];
+    let seconds_str = &captures[first + 2];
+    if minutes_str.len() != 2 || seconds_str.len() != 2 {
+        repairs.push("однозначные минуты/секунды");
     }
-    
-    Ok(segments)
-}
\ No newline at end of file
+    let minutes = minutes_str.parse::<u32>().unwrap_or(0);
+    let seconds = seconds_str.parse::<u32>().unwrap_or(0);
+
+    let millis = match (captures.get(first + 3), captures.get(first + 4)) {
+        (Some(sep), Some(ms)) => {
+            if sep.as_str() != "," {
+                repairs.push("разделитель миллисекунд не запятая");
+            }
+            let digits = ms.as_str();
+            if digits.len() != 3 {
+                repairs.push("неполные миллисекунды");
+            }
+            // `1,5` — это полсекунды, а не 5 мс
+            let digits: String = digits.chars().chain("000".chars()).take(3).collect();
+            digits.parse::<u32>().unwrap_or(0)
+        }
+        _ => {
+            repairs.push("нет миллисекунд");
+            0
+        }
+    };
+
+    Timestamp::from_hmsm(hours, minutes, seconds, millis)
+}
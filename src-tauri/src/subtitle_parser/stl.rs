@@ -0,0 +1,739 @@
+use crate::project::{SegmentFormatMeta, SubtitleSegment, Timestamp};
+use super::ParseDiagnostic;
+use encoding_rs::{Encoding, ISO_8859_5, ISO_8859_6, ISO_8859_7, ISO_8859_8};
+use serde::{Deserialize, Serialize};
+
+const GSI_SIZE: usize = 1024;
+const TTI_SIZE: usize = 128;
+const TEXT_FIELD_SIZE: usize = 112;
+
+const ITALIC_ON: u8 = 0x80;
+const ITALIC_OFF: u8 = 0x81;
+const LINE_BREAK: u8 = 0x8A;
+const UNUSED: u8 = 0x8F;
+const DOUBLE_HEIGHT: u8 = 0x0D;
+const START_BOX: u8 = 0x0B;
+const END_BOX: u8 = 0x0A;
+/// EBN последнего (или единственного) блока реплики
+const LAST_EXTENSION_BLOCK: u8 = 0xFF;
+/// EBN блока с пользовательскими данными
+const USER_DATA_BLOCK: u8 = 0xFE;
+
+/// GSI-блок EBU STL (Tech 3264). Счётчики блоков и реплик не храним — при экспорте
+/// они пересчитываются.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StlGsi {
+    /// CPN: кодовая страница самого GSI (`437`, `850`, …)
+    pub code_page: String,
+    /// DFC: `STL25.01` или `STL30.01`
+    pub disk_format_code: String,
+    /// DSC: ` `/`0` — открытые субтитры, `1`/`2` — телетекст
+    pub display_standard_code: String,
+    /// CCT: кодовая таблица текста (`00` — ISO 6937, `01` — ISO 8859-5, …)
+    pub character_code_table: String,
+    /// LC: код языка по таблице EBU (`09` — английский, `56` — русский)
+    pub language_code: String,
+    pub original_programme_title: String,
+    pub original_episode_title: String,
+    pub translated_programme_title: String,
+    pub translated_episode_title: String,
+    pub translator_name: String,
+    pub translator_contact: String,
+    pub subtitle_list_reference: String,
+    pub creation_date: String,
+    pub revision_date: String,
+    pub revision_number: String,
+    pub max_chars_per_row: String,
+    pub max_rows: String,
+    pub timecode_status: String,
+    /// TCP: таймкод начала программы `HHMMSSFF`; таймкоды реплик отсчитываются от него
+    pub start_of_programme: String,
+    pub country_of_origin: String,
+    pub publisher: String,
+    pub editor_name: String,
+    pub editor_contact: String,
+}
+
+impl Default for StlGsi {
+    fn default() -> Self {
+        Self {
+            code_page: "850".to_string(),
+            disk_format_code: "STL25.01".to_string(),
+            display_standard_code: "1".to_string(),
+            character_code_table: "00".to_string(),
+            language_code: "00".to_string(),
+            original_programme_title: String::new(),
+            original_episode_title: String::new(),
+            translated_programme_title: String::new(),
+            translated_episode_title: String::new(),
+            translator_name: String::new(),
+            translator_contact: String::new(),
+            subtitle_list_reference: String::new(),
+            creation_date: String::new(),
+            revision_date: String::new(),
+            revision_number: "00".to_string(),
+            max_chars_per_row: "40".to_string(),
+            max_rows: "23".to_string(),
+            timecode_status: "1".to_string(),
+            start_of_programme: "00000000".to_string(),
+            country_of_origin: String::new(),
+            publisher: String::new(),
+            editor_name: String::new(),
+            editor_contact: String::new(),
+        }
+    }
+}
+
+impl StlGsi {
+    /// Частота кадров таймкодов по DFC
+    pub fn frame_rate(&self) -> f64 {
+        match self.disk_format_code.get(3..5) {
+            Some("30") => 30.0,
+            _ => 25.0,
+        }
+    }
+
+    /// Телетекст (DSC `1`/`2`): строки двойной высоты, VP — номер строки 1–23
+    pub fn is_teletext(&self) -> bool {
+        matches!(self.display_standard_code.trim(), "1" | "2")
+    }
+}
+
+/// Данные TTI-блока помимо времени и текста.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StlCueMeta {
+    /// SGN
+    pub subtitle_group: u8,
+    /// VP: строка экрана (телетекст) или позиция по вертикали
+    pub vertical_position: u8,
+    /// JC: 0 — без изменений, 1 — влево, 2 — по центру, 3 — вправо
+    pub justification: u8,
+    /// CS: кумулятивные субтитры
+    pub cumulative_status: u8,
+    /// Текст был выделен курсивом (код 0x80)
+    #[serde(default)]
+    pub italic: bool,
+}
+
+/// Похоже ли содержимое на EBU STL: в GSI на позиции 3 стоит `STLxx.01`.
+pub fn looks_like_stl(bytes: &[u8]) -> bool {
+    bytes.len() >= GSI_SIZE
+        && bytes.get(3..6) == Some(b"STL".as_slice())
+        && bytes.get(8..11) == Some(b".01".as_slice())
+}
+
+pub fn parse(bytes: &[u8]) -> Result<(Vec<SubtitleSegment>, StlGsi, Vec<ParseDiagnostic>), String> {
+    if !looks_like_stl(bytes) {
+        return Err("Файл не является EBU STL: нет GSI-блока".to_string());
+    }
+    let gsi = parse_gsi(&bytes[..GSI_SIZE]);
+    let fps = gsi.frame_rate();
+    let table = gsi.character_code_table.clone();
+    let programme_start = parse_timecode_digits(&gsi.start_of_programme, fps).unwrap_or(0.0);
+
+    let mut diagnostics = Vec::new();
+    if !(bytes.len() - GSI_SIZE).is_multiple_of(TTI_SIZE) {
+        diagnostics.push(ParseDiagnostic::repaired(
+            0,
+            "Размер файла не кратен TTI-блоку — неполный последний блок отброшен",
+        ));
+    }
+
+    struct RawCue {
+        block: usize,
+        number: [u8; 2],
+        start: f64,
+        end: f64,
+        text_field: Vec<u8>,
+        meta: StlCueMeta,
+        complete: bool,
+    }
+
+    let mut cues: Vec<RawCue> = Vec::new();
+    for (i, tti) in bytes[GSI_SIZE..].chunks_exact(TTI_SIZE).enumerate() {
+        // Номер блока для диагностики (GSI — блок 0)
+        let block = i + 1;
+        let extension = tti[3];
+        let comment = tti[15] == 1;
+        if extension == USER_DATA_BLOCK {
+            continue;
+        }
+        if comment {
+            diagnostics.push(ParseDiagnostic::skipped(block, "Блок-комментарий (CF=1) пропущен"));
+            continue;
+        }
+
+        let text_field = &tti[16..TTI_SIZE];
+        // Продолжение реплики из нескольких блоков (EBN 0, 1, … , 0xFF)
+        let number = [tti[1], tti[2]];
+        if let Some(last) = cues.last_mut().filter(|c| !c.complete && c.number == number) {
+            last.text_field.extend_from_slice(text_field);
+            last.complete = extension == LAST_EXTENSION_BLOCK;
+            continue;
+        }
+
+        let start = timecode_to_seconds(&tti[5..9], fps);
+        let end = timecode_to_seconds(&tti[9..13], fps);
+        let (Some(start), Some(end)) = (start, end) else {
+            diagnostics.push(ParseDiagnostic::skipped(block, "Некорректный таймкод — блок пропущен"));
+            continue;
+        };
+
+        cues.push(RawCue {
+            block,
+            number,
+            start,
+            end,
+            text_field: text_field.to_vec(),
+            meta: StlCueMeta {
+                subtitle_group: tti[0],
+                vertical_position: tti[13],
+                justification: tti[14],
+                cumulative_status: tti[4],
+                italic: false,
+            },
+            complete: extension == LAST_EXTENSION_BLOCK,
+        });
+    }
+
+    // Таймкоды обычно идут от TCP (например, 10:00:00:00) — переводим в время от начала видео
+    let offset = if cues.iter().all(|c| c.start >= programme_start) {
+        programme_start
+    } else {
+        0.0
+    };
+
+    let mut segments = Vec::new();
+    for mut cue in cues {
+        let (text, italic) = decode_text_field(&cue.text_field, &table);
+        if text.is_empty() {
+            diagnostics.push(ParseDiagnostic::skipped(cue.block, "Пустой текст — блок пропущен"));
+            continue;
+        }
+        let start = Timestamp::from_seconds(cue.start - offset);
+        let mut end = Timestamp::from_seconds(cue.end - offset);
+        if end < start {
+            diagnostics.push(ParseDiagnostic::repaired(cue.block, "Таймкод выхода раньше входа — заменён на вход"));
+            end = start;
+        }
+        cue.meta.italic = italic;
+        segments.push(SubtitleSegment {
+            id: segments.len() as u32 + 1,
+            start,
+            end,
+            duration: end - start,
+            text,
+            translation: None,
+            flags: None,
+            words: None,
+            format_meta: Some(SegmentFormatMeta::Stl(cue.meta)),
+        });
+    }
+
+    Ok((segments, gsi, diagnostics))
+}
+
+/// Реплика для записи: текст и (если была импортирована из STL) её TTI-параметры.
+pub struct StlCue<'a> {
+    pub start: f64,
+    pub end: f64,
+    pub text: &'a str,
+    pub meta: Option<&'a StlCueMeta>,
+}
+
+/// Реплика, символы которой не нашлись в кодовой таблице файла и заменены на `?`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StlUnmappedCue {
+    /// Номер реплики в списке, переданном в `write` (с нуля)
+    pub index: usize,
+    /// Заменённые символы, без повторов
+    pub characters: String,
+}
+
+/// Собирает файл EBU STL. Поля GSI, зависящие от содержимого (число блоков и реплик,
+/// максимальная длина строки, кодовая таблица, таймкод первой реплики), пересчитываются.
+/// Символы, которых нет в выбранной кодовой таблице, заменяются на `?` — такие реплики
+/// возвращаются вместе с файлом.
+pub fn write(cues: &[StlCue], gsi: &StlGsi) -> (Vec<u8>, Vec<StlUnmappedCue>) {
+    let fps = gsi.frame_rate();
+    let teletext = gsi.is_teletext();
+    let programme_start = parse_timecode_digits(&gsi.start_of_programme, fps).unwrap_or(0.0);
+
+    let table = choose_character_table(cues.iter().map(|c| c.text), &gsi.character_code_table);
+    let mut unmapped = Vec::new();
+
+    let mut tti_blocks: Vec<[u8; TTI_SIZE]> = Vec::new();
+    let mut max_chars = 0;
+    for (i, cue) in cues.iter().enumerate() {
+        let lines: Vec<&str> = cue.text.lines().filter(|l| !l.trim().is_empty()).collect();
+        max_chars = lines
+            .iter()
+            .map(|l| l.trim().chars().count())
+            .fold(max_chars, usize::max);
+        let italic = cue.meta.map(|m| m.italic).unwrap_or(false);
+        let (text_field, replaced) = encode_text_field(&lines, &table, teletext, italic);
+        if !replaced.is_empty() {
+            unmapped.push(StlUnmappedCue { index: i, characters: replaced });
+        }
+
+        let vertical_position = match cue.meta {
+            Some(m) => m.vertical_position,
+            // Нижняя строка двойной высоты занимает ряды 22–23
+            None if teletext => 22u8.saturating_sub(2 * lines.len().saturating_sub(1) as u8).max(1),
+            None => 20,
+        };
+        let subtitle_number = (i as u16).to_le_bytes();
+        let tci = seconds_to_timecode(cue.start + programme_start, fps);
+        let tco = seconds_to_timecode(cue.end + programme_start, fps);
+
+        let chunks: Vec<&[u8]> = text_field.chunks(TEXT_FIELD_SIZE).collect();
+        for (ext, chunk) in chunks.iter().enumerate() {
+            let mut tti = [UNUSED; TTI_SIZE];
+            tti[0] = cue.meta.map(|m| m.subtitle_group).unwrap_or(0);
+            tti[1] = subtitle_number[0];
+            tti[2] = subtitle_number[1];
+            tti[3] = if ext + 1 == chunks.len() { LAST_EXTENSION_BLOCK } else { ext as u8 };
+            tti[4] = cue.meta.map(|m| m.cumulative_status).unwrap_or(0);
+            tti[5..9].copy_from_slice(&tci);
+            tti[9..13].copy_from_slice(&tco);
+            tti[13] = vertical_position;
+            tti[14] = cue.meta.map(|m| m.justification).unwrap_or(2);
+            tti[15] = 0;
+            tti[16..16 + chunk.len()].copy_from_slice(chunk);
+            tti_blocks.push(tti);
+        }
+    }
+
+    let mut gsi = gsi.clone();
+    gsi.character_code_table = table;
+    gsi.max_chars_per_row = if teletext {
+        "40".to_string()
+    } else {
+        format!("{:02}", max_chars.min(99))
+    };
+    let first_in_cue = cues
+        .first()
+        .map(|c| timecode_digits(&seconds_to_timecode(c.start + programme_start, fps)))
+        .unwrap_or_else(|| gsi.start_of_programme.clone());
+
+    let mut out = build_gsi(&gsi, tti_blocks.len(), cues.len(), &first_in_cue);
+    for tti in &tti_blocks {
+        out.extend_from_slice(tti);
+    }
+    (out, unmapped)
+}
+
+/// Код языка EBU (Tech 3264, приложение 3) по коду ISO 639-1.
+pub fn language_code(iso: &str) -> &'static str {
+    let iso = iso.trim().to_lowercase();
+    let primary = iso.split(['-', '_']).next().unwrap_or("");
+    match primary {
+        "sq" => "01", "br" => "02", "ca" => "03", "hr" => "04", "cy" => "05",
+        "cs" => "06", "da" => "07", "de" => "08", "en" => "09", "es" => "0A",
+        "eo" => "0B", "et" => "0C", "eu" => "0D", "fo" => "0E", "fr" => "0F",
+        "fy" => "10", "ga" => "11", "gd" => "12", "gl" => "13", "is" => "14",
+        "it" => "15", "se" => "16", "la" => "17", "lv" => "18", "lb" => "19",
+        "lt" => "1A", "hu" => "1B", "mt" => "1C", "nl" => "1D", "no" | "nb" | "nn" => "1E",
+        "oc" => "1F", "pl" => "20", "pt" => "21", "ro" => "22", "rm" => "23",
+        "sr" => "24", "sk" => "25", "sl" => "26", "fi" => "27", "sv" => "28",
+        "tr" => "29", "am" => "7F", "ar" => "7E", "hy" => "7D", "as" => "7C",
+        "az" => "7B", "bm" => "7A", "be" => "79", "bn" => "78", "bg" => "77",
+        "my" => "76", "zh" => "75", "ff" => "72", "ka" => "71", "el" => "70",
+        "gu" => "6F", "gn" => "6E", "ha" => "6D", "he" => "6C", "hi" => "6B",
+        "id" => "6A", "ja" => "69", "kn" => "68", "kk" => "67", "km" => "66",
+        "ko" => "65", "lo" => "64", "mk" => "63", "mg" => "62", "ms" => "61",
+        "mr" => "5F", "nd" => "5E", "ne" => "5D", "or" => "5C", "fa" => "5A",
+        "pa" => "59", "ps" => "58", "qu" => "57", "ru" => "56", "sn" => "53",
+        "si" => "52", "so" => "51", "sw" => "4F", "tg" => "4E", "ta" => "4D",
+        "tt" => "4C", "te" => "4B", "th" => "4A", "uk" => "49", "ur" => "48",
+        "uz" => "47", "vi" => "46", "zu" => "45",
+        _ => "00",
+    }
+}
+
+/// Название кодовой таблицы CCT для отчёта об импорте
+pub fn character_table_name(table: &str) -> &'static str {
+    match table {
+        "01" => "ISO 8859-5",
+        "02" => "ISO 8859-6",
+        "03" => "ISO 8859-7",
+        "04" => "ISO 8859-8",
+        _ => "ISO 6937",
+    }
+}
+
+fn parse_gsi(gsi: &[u8]) -> StlGsi {
+    let field = |start: usize, len: usize| decode_gsi_field(&gsi[start..start + len]);
+    StlGsi {
+        code_page: field(0, 3),
+        disk_format_code: field(3, 8),
+        display_standard_code: decode_gsi_field(&gsi[11..12]),
+        character_code_table: field(12, 2),
+        language_code: field(14, 2),
+        original_programme_title: field(16, 32),
+        original_episode_title: field(48, 32),
+        translated_programme_title: field(80, 32),
+        translated_episode_title: field(112, 32),
+        translator_name: field(144, 32),
+        translator_contact: field(176, 32),
+        subtitle_list_reference: field(208, 16),
+        creation_date: field(224, 6),
+        revision_date: field(230, 6),
+        revision_number: field(236, 2),
+        max_chars_per_row: field(251, 2),
+        max_rows: field(253, 2),
+        timecode_status: field(255, 1),
+        start_of_programme: field(256, 8),
+        country_of_origin: field(274, 3),
+        publisher: field(277, 32),
+        editor_name: field(309, 32),
+        editor_contact: field(341, 32),
+    }
+}
+
+fn build_gsi(gsi: &StlGsi, total_blocks: usize, total_subtitles: usize, first_in_cue: &str) -> Vec<u8> {
+    let mut out = vec![b' '; GSI_SIZE];
+    let mut put = |start: usize, len: usize, value: &str| {
+        let encoded = encode_gsi_field(value);
+        let n = encoded.len().min(len);
+        out[start..start + n].copy_from_slice(&encoded[..n]);
+    };
+    put(0, 3, &gsi.code_page);
+    put(3, 8, &gsi.disk_format_code);
+    put(11, 1, &gsi.display_standard_code);
+    put(12, 2, &gsi.character_code_table);
+    put(14, 2, &gsi.language_code);
+    put(16, 32, &gsi.original_programme_title);
+    put(48, 32, &gsi.original_episode_title);
+    put(80, 32, &gsi.translated_programme_title);
+    put(112, 32, &gsi.translated_episode_title);
+    put(144, 32, &gsi.translator_name);
+    put(176, 32, &gsi.translator_contact);
+    put(208, 16, &gsi.subtitle_list_reference);
+    put(224, 6, &gsi.creation_date);
+    put(230, 6, &gsi.revision_date);
+    put(236, 2, &gsi.revision_number);
+    put(238, 5, &format!("{:05}", total_blocks.min(99_999)));
+    put(243, 5, &format!("{:05}", total_subtitles.min(99_999)));
+    // Группы субтитров не используем — одна группа
+    put(248, 3, "001");
+    put(251, 2, &gsi.max_chars_per_row);
+    put(253, 2, &gsi.max_rows);
+    put(255, 1, &gsi.timecode_status);
+    put(256, 8, &gsi.start_of_programme);
+    put(264, 8, first_in_cue);
+    put(272, 1, "1");
+    put(273, 1, "1");
+    put(274, 3, &gsi.country_of_origin);
+    put(277, 32, &gsi.publisher);
+    put(309, 32, &gsi.editor_name);
+    put(341, 32, &gsi.editor_contact);
+    out
+}
+
+/// Поля GSI записаны в кодовой странице CPN; 437/850/860/863/865 совпадают в ASCII,
+/// верхнюю половину читаем и пишем по 850 — самой распространённой.
+fn decode_gsi_field(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| if b < 0x80 { b as char } else { CP850_HIGH[(b - 0x80) as usize] })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn encode_gsi_field(value: &str) -> Vec<u8> {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() {
+                c as u8
+            } else {
+                CP850_HIGH
+                    .iter()
+                    .position(|&h| h == c)
+                    .map(|i| 0x80 + i as u8)
+                    .unwrap_or(b'?')
+            }
+        })
+        .collect()
+}
+
+fn timecode_to_seconds(tc: &[u8], fps: f64) -> Option<f64> {
+    let (h, m, s, f) = (tc[0] as f64, tc[1] as f64, tc[2] as f64, tc[3] as f64);
+    if tc[1] > 59 || tc[2] > 59 || f >= fps {
+        return None;
+    }
+    Some(h * 3600.0 + m * 60.0 + s + f / fps)
+}
+
+fn seconds_to_timecode(seconds: f64, fps: f64) -> [u8; 4] {
+    let frames_per_second = fps.round() as u64;
+    let total_frames = (seconds.max(0.0) * fps).round() as u64;
+    let frames = total_frames % frames_per_second;
+    let total_seconds = total_frames / frames_per_second;
+    [
+        ((total_seconds / 3600) % 24) as u8,
+        ((total_seconds % 3600) / 60) as u8,
+        (total_seconds % 60) as u8,
+        frames as u8,
+    ]
+}
+
+/// `HHMMSSFF` из GSI (TCP, TCF)
+fn parse_timecode_digits(digits: &str, fps: f64) -> Option<f64> {
+    if digits.len() != 8 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let parts: Vec<u8> = (0..4)
+        .map(|i| digits[i * 2..i * 2 + 2].parse::<u8>().unwrap_or(0))
+        .collect();
+    timecode_to_seconds(&parts, fps)
+}
+
+fn timecode_digits(tc: &[u8; 4]) -> String {
+    format!("{:02}{:02}{:02}{:02}", tc[0], tc[1], tc[2], tc[3])
+}
+
+fn table_encoding(table: &str) -> Option<&'static Encoding> {
+    match table {
+        "01" => Some(ISO_8859_5),
+        "02" => Some(ISO_8859_6),
+        "03" => Some(ISO_8859_7),
+        "04" => Some(ISO_8859_8),
+        _ => None,
+    }
+}
+
+/// Текстовое поле TTI → обычный текст. Управляющие коды телетекста (цвет, двойная высота,
+/// рамка) на экране занимают позицию пробела, поэтому заменяем их пробелом.
+fn decode_text_field(field: &[u8], table: &str) -> (String, bool) {
+    let mut text = String::new();
+    let mut italic = false;
+    let mut pending_diacritic: Option<u8> = None;
+    let encoding = table_encoding(table);
+
+    for &b in field {
+        match b {
+            ITALIC_ON => italic = true,
+            // Двойная высота даёт два перевода строки подряд — оставляем один
+            LINE_BREAK if !text.is_empty() && !text.ends_with('\n') => text.push('\n'),
+            0x00..=0x1F => text.push(' '),
+            0x7F => {}
+            0x20..=0x7E | 0xA0..=0xFF => {
+                match encoding {
+                    Some(enc) => {
+                        let byte = [b];
+                        let (decoded, _) = enc.decode_without_bom_handling(&byte);
+                        text.push_str(&decoded);
+                    }
+                    None => {
+                        if (0xC1..=0xCF).contains(&b) {
+                            pending_diacritic = Some(b);
+                            continue;
+                        }
+                        let c = iso6937_char(b);
+                        match pending_diacritic.take() {
+                            Some(mark) => text.push_str(&compose_iso6937(mark, c)),
+                            None => text.push(c),
+                        }
+                    }
+                }
+            }
+            // 0x81–0x8F, 0x90–0x9F: прочие коды оформления и заполнитель
+            _ => {}
+        }
+    }
+
+    let text = text
+        .lines()
+        .map(|l| l.split(' ').filter(|w| !w.is_empty()).collect::<Vec<_>>().join(" "))
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (text, italic)
+}
+
+/// Текстовое поле TTI и символы, заменённые на `?`
+fn encode_text_field(lines: &[&str], table: &str, teletext: bool, italic: bool) -> (Vec<u8>, String) {
+    let mut out = Vec::new();
+    let mut replaced = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            out.push(LINE_BREAK);
+            if teletext {
+                out.push(LINE_BREAK);
+            }
+        }
+        if teletext {
+            out.extend_from_slice(&[DOUBLE_HEIGHT, START_BOX, START_BOX]);
+        }
+        if italic {
+            out.push(ITALIC_ON);
+        }
+        let (bytes, unmappable) = encode_text(line.trim(), table);
+        out.extend(bytes);
+        for c in unmappable {
+            if !replaced.contains(c) {
+                replaced.push(c);
+            }
+        }
+        if italic {
+            out.push(ITALIC_OFF);
+        }
+        if teletext {
+            out.extend_from_slice(&[END_BOX, END_BOX]);
+        }
+    }
+    (out, replaced)
+}
+
+/// Текст в кодовой таблице; символы, которых в ней нет, заменяются на `?` и возвращаются отдельно
+fn encode_text(text: &str, table: &str) -> (Vec<u8>, Vec<char>) {
+    let encoding = table_encoding(table);
+    let mut out = Vec::new();
+    let mut unmappable = Vec::new();
+    let mut buf = [0u8; 4];
+    for c in text.chars() {
+        let bytes = match encoding {
+            Some(enc) => {
+                let (bytes, _, failed) = enc.encode(c.encode_utf8(&mut buf));
+                (!failed).then(|| bytes.into_owned())
+            }
+            None => encode_iso6937(c),
+        };
+        match bytes {
+            Some(bytes) => out.extend(bytes),
+            None => {
+                out.push(b'?');
+                unmappable.push(c);
+            }
+        }
+    }
+    (out, unmappable)
+}
+
+/// Кодовая таблица для экспорта: исходная, если в неё помещается весь текст, иначе
+/// та из латиницы, кириллицы, греческого, арабского и иврита, где меньше всего
+/// символов придётся заменить.
+fn choose_character_table<'a>(texts: impl Iterator<Item = &'a str> + Clone, preferred: &str) -> String {
+    let unmappable = |table: &str| {
+        texts
+            .clone()
+            .map(|t| encode_text(&t.replace('\n', " "), table).1.len())
+            .sum::<usize>()
+    };
+    if unmappable(preferred) == 0 {
+        return preferred.to_string();
+    }
+    ["00", "01", "03", "02", "04"]
+        .into_iter()
+        .min_by_key(|table| unmappable(table))
+        .unwrap_or("00")
+        .to_string()
+}
+
+fn iso6937_char(b: u8) -> char {
+    if b < 0x80 {
+        return b as char;
+    }
+    ISO6937_SPECIAL
+        .iter()
+        .find(|(code, _)| *code == b)
+        .map(|(_, c)| *c)
+        .unwrap_or('\u{FFFD}')
+}
+
+fn compose_iso6937(mark: u8, base: char) -> String {
+    if let Some((_, _, composed)) = ISO6937_COMPOSED.iter().find(|(m, b, _)| *m == mark && *b == base) {
+        return composed.to_string();
+    }
+    // Комбинация, которой нет в таблице, — базовая буква и комбинируемый знак
+    let combining = match mark {
+        0xC1 => '\u{300}', 0xC2 => '\u{301}', 0xC3 => '\u{302}', 0xC4 => '\u{303}',
+        0xC5 => '\u{304}', 0xC6 => '\u{306}', 0xC7 => '\u{307}', 0xC8 => '\u{308}',
+        0xCA => '\u{30A}', 0xCB => '\u{327}', 0xCD => '\u{30B}', 0xCE => '\u{328}',
+        0xCF => '\u{30C}',
+        _ => return base.to_string(),
+    };
+    format!("{}{}", base, combining)
+}
+
+fn encode_iso6937(c: char) -> Option<Vec<u8>> {
+    if (' '..='~').contains(&c) {
+        return Some(vec![c as u8]);
+    }
+    if let Some((code, _)) = ISO6937_SPECIAL.iter().find(|(_, ch)| *ch == c) {
+        return Some(vec![*code]);
+    }
+    ISO6937_COMPOSED
+        .iter()
+        .find(|(_, _, composed)| *composed == c)
+        .map(|(mark, base, _)| vec![*mark, *base as u8])
+}
+
+/// Самостоятельные символы ISO 6937 в верхней половине таблицы
+const ISO6937_SPECIAL: &[(u8, char)] = &[
+    (0xA0, '\u{A0}'), (0xA1, '¡'), (0xA2, '¢'), (0xA3, '£'), (0xA5, '¥'), (0xA7, '§'),
+    (0xA8, '¤'), (0xA9, '‘'), (0xAA, '“'), (0xAB, '«'), (0xAC, '←'), (0xAD, '↑'),
+    (0xAE, '→'), (0xAF, '↓'), (0xB0, '°'), (0xB1, '±'), (0xB2, '²'), (0xB3, '³'),
+    (0xB4, '×'), (0xB5, 'µ'), (0xB6, '¶'), (0xB7, '·'), (0xB8, '÷'), (0xB9, '’'),
+    (0xBA, '”'), (0xBB, '»'), (0xBC, '¼'), (0xBD, '½'), (0xBE, '¾'), (0xBF, '¿'),
+    (0xD0, '―'), (0xD1, '¹'), (0xD2, '®'), (0xD3, '©'), (0xD4, '™'), (0xD5, '♪'),
+    (0xD6, '¬'), (0xD7, '¦'), (0xDC, '⅛'), (0xDD, '⅜'), (0xDE, '⅝'), (0xDF, '⅞'),
+    (0xE0, 'Ω'), (0xE1, 'Æ'), (0xE2, 'Đ'), (0xE3, 'ª'), (0xE4, 'Ħ'), (0xE6, 'Ĳ'),
+    (0xE7, 'Ŀ'), (0xE8, 'Ł'), (0xE9, 'Ø'), (0xEA, 'Œ'), (0xEB, 'º'), (0xEC, 'Þ'),
+    (0xED, 'Ŧ'), (0xEE, 'Ŋ'), (0xEF, 'ŉ'), (0xF0, 'ĸ'), (0xF1, 'æ'), (0xF2, 'đ'),
+    (0xF3, 'ð'), (0xF4, 'ħ'), (0xF5, 'ı'), (0xF6, 'ĳ'), (0xF7, 'ŀ'), (0xF8, 'ł'),
+    (0xF9, 'ø'), (0xFA, 'œ'), (0xFB, 'ß'), (0xFC, 'þ'), (0xFD, 'ŧ'), (0xFE, 'ŋ'),
+    (0xFF, '\u{AD}'),
+];
+
+/// Буквы с диакритикой: ISO 6937 пишет их двумя байтами — знак (0xC1–0xCF), затем буква
+const ISO6937_COMPOSED: &[(u8, char, char)] = &[
+    (0xC1, 'A', 'À'), (0xC2, 'A', 'Á'), (0xC3, 'A', 'Â'), (0xC4, 'A', 'Ã'), (0xC8, 'A', 'Ä'),
+    (0xCA, 'A', 'Å'), (0xCB, 'C', 'Ç'), (0xC1, 'E', 'È'), (0xC2, 'E', 'É'), (0xC3, 'E', 'Ê'),
+    (0xC8, 'E', 'Ë'), (0xC1, 'I', 'Ì'), (0xC2, 'I', 'Í'), (0xC3, 'I', 'Î'), (0xC8, 'I', 'Ï'),
+    (0xC4, 'N', 'Ñ'), (0xC1, 'O', 'Ò'), (0xC2, 'O', 'Ó'), (0xC3, 'O', 'Ô'), (0xC4, 'O', 'Õ'),
+    (0xC8, 'O', 'Ö'), (0xC1, 'U', 'Ù'), (0xC2, 'U', 'Ú'), (0xC3, 'U', 'Û'), (0xC8, 'U', 'Ü'),
+    (0xC2, 'Y', 'Ý'), (0xC1, 'a', 'à'), (0xC2, 'a', 'á'), (0xC3, 'a', 'â'), (0xC4, 'a', 'ã'),
+    (0xC8, 'a', 'ä'), (0xCA, 'a', 'å'), (0xCB, 'c', 'ç'), (0xC1, 'e', 'è'), (0xC2, 'e', 'é'),
+    (0xC3, 'e', 'ê'), (0xC8, 'e', 'ë'), (0xC1, 'i', 'ì'), (0xC2, 'i', 'í'), (0xC3, 'i', 'î'),
+    (0xC8, 'i', 'ï'), (0xC4, 'n', 'ñ'), (0xC1, 'o', 'ò'), (0xC2, 'o', 'ó'), (0xC3, 'o', 'ô'),
+    (0xC4, 'o', 'õ'), (0xC8, 'o', 'ö'), (0xC1, 'u', 'ù'), (0xC2, 'u', 'ú'), (0xC3, 'u', 'û'),
+    (0xC8, 'u', 'ü'), (0xC2, 'y', 'ý'), (0xC8, 'y', 'ÿ'), (0xC5, 'A', 'Ā'), (0xC5, 'a', 'ā'),
+    (0xC6, 'A', 'Ă'), (0xC6, 'a', 'ă'), (0xCE, 'A', 'Ą'), (0xCE, 'a', 'ą'), (0xC2, 'C', 'Ć'),
+    (0xC2, 'c', 'ć'), (0xC3, 'C', 'Ĉ'), (0xC3, 'c', 'ĉ'), (0xC7, 'C', 'Ċ'), (0xC7, 'c', 'ċ'),
+    (0xCF, 'C', 'Č'), (0xCF, 'c', 'č'), (0xCF, 'D', 'Ď'), (0xCF, 'd', 'ď'), (0xC5, 'E', 'Ē'),
+    (0xC5, 'e', 'ē'), (0xC6, 'E', 'Ĕ'), (0xC6, 'e', 'ĕ'), (0xC7, 'E', 'Ė'), (0xC7, 'e', 'ė'),
+    (0xCE, 'E', 'Ę'), (0xCE, 'e', 'ę'), (0xCF, 'E', 'Ě'), (0xCF, 'e', 'ě'), (0xC3, 'G', 'Ĝ'),
+    (0xC3, 'g', 'ĝ'), (0xC6, 'G', 'Ğ'), (0xC6, 'g', 'ğ'), (0xC7, 'G', 'Ġ'), (0xC7, 'g', 'ġ'),
+    (0xCB, 'G', 'Ģ'), (0xCB, 'g', 'ģ'), (0xC3, 'H', 'Ĥ'), (0xC3, 'h', 'ĥ'), (0xC4, 'I', 'Ĩ'),
+    (0xC4, 'i', 'ĩ'), (0xC5, 'I', 'Ī'), (0xC5, 'i', 'ī'), (0xC6, 'I', 'Ĭ'), (0xC6, 'i', 'ĭ'),
+    (0xCE, 'I', 'Į'), (0xCE, 'i', 'į'), (0xC7, 'I', 'İ'), (0xC3, 'J', 'Ĵ'), (0xC3, 'j', 'ĵ'),
+    (0xCB, 'K', 'Ķ'), (0xCB, 'k', 'ķ'), (0xC2, 'L', 'Ĺ'), (0xC2, 'l', 'ĺ'), (0xCB, 'L', 'Ļ'),
+    (0xCB, 'l', 'ļ'), (0xCF, 'L', 'Ľ'), (0xCF, 'l', 'ľ'), (0xC2, 'N', 'Ń'), (0xC2, 'n', 'ń'),
+    (0xCB, 'N', 'Ņ'), (0xCB, 'n', 'ņ'), (0xCF, 'N', 'Ň'), (0xCF, 'n', 'ň'), (0xC5, 'O', 'Ō'),
+    (0xC5, 'o', 'ō'), (0xC6, 'O', 'Ŏ'), (0xC6, 'o', 'ŏ'), (0xCD, 'O', 'Ő'), (0xCD, 'o', 'ő'),
+    (0xC2, 'R', 'Ŕ'), (0xC2, 'r', 'ŕ'), (0xCB, 'R', 'Ŗ'), (0xCB, 'r', 'ŗ'), (0xCF, 'R', 'Ř'),
+    (0xCF, 'r', 'ř'), (0xC2, 'S', 'Ś'), (0xC2, 's', 'ś'), (0xC3, 'S', 'Ŝ'), (0xC3, 's', 'ŝ'),
+    (0xCB, 'S', 'Ş'), (0xCB, 's', 'ş'), (0xCF, 'S', 'Š'), (0xCF, 's', 'š'), (0xCB, 'T', 'Ţ'),
+    (0xCB, 't', 'ţ'), (0xCF, 'T', 'Ť'), (0xCF, 't', 'ť'), (0xC4, 'U', 'Ũ'), (0xC4, 'u', 'ũ'),
+    (0xC5, 'U', 'Ū'), (0xC5, 'u', 'ū'), (0xC6, 'U', 'Ŭ'), (0xC6, 'u', 'ŭ'), (0xCA, 'U', 'Ů'),
+    (0xCA, 'u', 'ů'), (0xCD, 'U', 'Ű'), (0xCD, 'u', 'ű'), (0xCE, 'U', 'Ų'), (0xCE, 'u', 'ų'),
+    (0xC3, 'W', 'Ŵ'), (0xC3, 'w', 'ŵ'), (0xC3, 'Y', 'Ŷ'), (0xC3, 'y', 'ŷ'), (0xC8, 'Y', 'Ÿ'),
+    (0xC2, 'Z', 'Ź'), (0xC2, 'z', 'ź'), (0xC7, 'Z', 'Ż'), (0xC7, 'z', 'ż'), (0xCF, 'Z', 'Ž'),
+    (0xCF, 'z', 'ž'),
+];
+
+/// Верхняя половина кодовой страницы 850
+const CP850_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', 'ø', '£', 'Ø', '×', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '®', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', 'Á', 'Â', 'À', '©', '╣', '║', '╗', '╝', '¢', '¥', '┐',
+    '└', '┴', '┬', '├', '─', '┼', 'ã', 'Ã', '╚', '╔', '╩', '╦', '╠', '═', '╬', '¤',
+    'ð', 'Ð', 'Ê', 'Ë', 'È', 'ı', 'Í', 'Î', 'Ï', '┘', '┌', '█', '▄', '¦', 'Ì', '▀',
+    'Ó', 'ß', 'Ô', 'Ò', 'õ', 'Õ', 'µ', 'þ', 'Þ', 'Ú', 'Û', 'Ù', 'ý', 'Ý', '¯', '´',
+    '\u{AD}', '±', '‗', '¾', '¶', '§', '÷', '¸', '°', '¨', '·', '¹', '³', '²', '■', '\u{A0}',
+];
@@ -0,0 +1,526 @@
+use super::ParseDiagnostic;
+use crate::project::{SegmentFormatMeta, SubtitleSegment, Timestamp};
+use serde::{Deserialize, Serialize};
+
+/// Параметры документа TTML/IMSC1/EBU-TT-D, нужные для экспорта без потери оформления.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TtmlDocument {
+    /// `xml:lang` корневого элемента
+    pub lang: Option<String>,
+    /// Открывающий тег `<tt ...>` как есть (пространства имён, профиль, параметры времени)
+    pub root_start_tag: String,
+    /// Элемент `<head>` целиком (стили, регионы, метаданные)
+    pub head_xml: Option<String>,
+    pub body_start_tag: Option<String>,
+    pub div_start_tag: Option<String>,
+    pub frame_rate: Option<f64>,
+    pub tick_rate: Option<f64>,
+}
+
+impl TtmlDocument {
+    /// Префикс пространства имён TTML, если корень записан как `<tt:tt ...>` (EBU-TT-D и др.)
+    pub fn prefix(&self) -> Option<&str> {
+        tag_name(&self.root_start_tag).split_once(':').map(|(prefix, _)| prefix)
+    }
+
+    /// Объявления `xmlns` и `xmlns:*` корневого элемента (`itts:`, `ebutts:` и т.п.)
+    pub fn namespace_declarations(&self) -> Vec<String> {
+        match regex::Regex::new(r#"\sxmlns(?::[\w.-]+)?\s*=\s*(?:"[^"]*"|'[^']*')"#) {
+            Ok(re) => re
+                .find_iter(start_tag(&self.root_start_tag))
+                .map(|m| m.as_str().trim().to_string())
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+/// Данные элемента `<p>` помимо времени и текста.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TtmlCueMeta {
+    pub xml_id: Option<String>,
+    pub region: Option<String>,
+    /// Ссылки на стили (`style="s1 s2"`)
+    pub style: Option<String>,
+    pub lang: Option<String>,
+    /// Атрибуты `tts:*`, заданные прямо на `<p>`
+    #[serde(default)]
+    pub inline_styles: Vec<TtmlAttribute>,
+    #[serde(default)]
+    pub spans: Vec<TtmlSpan>,
+    /// Содержимое `<p>` как есть (со `<span>` и `<br/>`)
+    pub raw_content: String,
+    /// Текст `<p>` при импорте: если он не изменился, `raw_content` выгружается как есть
+    #[serde(default)]
+    pub source_text: Option<String>,
+    /// Если весь текст лежит в одном `<span>`, его открывающий тег без времени — перевод оборачивается в него же
+    pub wrapper_span: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TtmlAttribute {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TtmlSpan {
+    pub text: String,
+    pub style: Option<String>,
+    #[serde(default)]
+    pub inline_styles: Vec<TtmlAttribute>,
+}
+
+/// Параметры разбора выражений времени
+struct TimeBase {
+    frame_rate: f64,
+    sub_frame_rate: f64,
+    tick_rate: f64,
+}
+
+/// Интервал элемента в абсолютном времени; `end: None` — открыт до конца документа
+#[derive(Clone, Copy)]
+struct Interval {
+    begin: f64,
+    end: Option<f64>,
+}
+
+pub fn parse(content: &str) -> Result<(Vec<SubtitleSegment>, TtmlDocument, Vec<ParseDiagnostic>), String> {
+    let text = content.trim_start_matches('\u{feff}');
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let doc = roxmltree::Document::parse_with_options(text, options)
+        .map_err(|e| format!("Ошибка разбора XML: {}", e))?;
+
+    let root = doc.root_element();
+    if root.tag_name().name() != "tt" {
+        return Err("Корневой элемент TTML должен быть <tt>".to_string());
+    }
+
+    let frame_rate_base = attr(root, "frameRate").and_then(|v| v.trim().parse::<f64>().ok());
+    let multiplier = attr(root, "frameRateMultiplier")
+        .and_then(|v| {
+            let mut parts = v.split_whitespace().map(|p| p.parse::<f64>().ok());
+            match (parts.next().flatten(), parts.next().flatten()) {
+                (Some(n), Some(d)) if d > 0.0 => Some(n / d),
+                _ => None,
+            }
+        })
+        .unwrap_or(1.0);
+    let frame_rate = frame_rate_base.map(|f| f * multiplier);
+    let sub_frame_rate = attr(root, "subFrameRate")
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .unwrap_or(1.0);
+    let tick_rate = attr(root, "tickRate").and_then(|v| v.trim().parse::<f64>().ok());
+
+    let time_base = TimeBase {
+        frame_rate: frame_rate.unwrap_or(30.0),
+        sub_frame_rate,
+        // По спецификации: без tickRate — frameRate × subFrameRate, если задан frameRate, иначе 1
+        tick_rate: tick_rate.unwrap_or_else(|| {
+            frame_rate_base.map(|f| f * sub_frame_rate).unwrap_or(1.0)
+        }),
+    };
+
+    let mut document = TtmlDocument {
+        lang: attr(root, "lang").map(|s| s.to_string()),
+        root_start_tag: start_tag(&text[root.range()]).to_string(),
+        frame_rate,
+        tick_rate,
+        ..Default::default()
+    };
+
+    if let Some(head) = child(root, "head") {
+        document.head_xml = Some(text[head.range()].to_string());
+    }
+
+    let mut segments = Vec::new();
+    let mut diagnostics = Vec::new();
+    if let Some(body) = child(root, "body") {
+        document.body_start_tag = Some(strip_timing(start_tag(&text[body.range()])));
+        let document_interval = Interval { begin: 0.0, end: None };
+        let body_interval = element_interval(body, document_interval, &time_base);
+        collect_paragraphs(text, body, body_interval, &time_base, &mut document, &mut segments, &mut diagnostics);
+    }
+
+    segments.sort_by(|a, b| {
+        a.start
+            .partial_cmp(&b.start)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    for (i, seg) in segments.iter_mut().enumerate() {
+        seg.id = (i + 1) as u32;
+    }
+
+    Ok((segments, document, diagnostics))
+}
+
+fn collect_paragraphs(
+    source: &str,
+    node: roxmltree::Node,
+    parent: Interval,
+    time_base: &TimeBase,
+    document: &mut TtmlDocument,
+    segments: &mut Vec<SubtitleSegment>,
+    diagnostics: &mut Vec<ParseDiagnostic>,
+) {
+    for el in node.children().filter(|n| n.is_element()) {
+        match el.tag_name().name() {
+            "div" => {
+                if document.div_start_tag.is_none() {
+                    document.div_start_tag = Some(strip_timing(start_tag(&source[el.range()])));
+                }
+                let interval = element_interval(el, parent, time_base);
+                collect_paragraphs(source, el, interval, time_base, document, segments, diagnostics);
+            }
+            "p" => {
+                if let Some(segment) = parse_paragraph(source, el, parent, time_base, diagnostics) {
+                    segments.push(segment);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Время элемента: `begin`/`end` отсчитываются от начала родителя, без `end` и `dur`
+/// элемент длится до конца родителя.
+fn element_interval(node: roxmltree::Node, parent: Interval, time_base: &TimeBase) -> Interval {
+    let begin = parent.begin + attr(node, "begin").and_then(|v| parse_time(v, time_base)).unwrap_or(0.0);
+    Interval { begin, end: own_end(node, parent.begin, begin, time_base).or(parent.end) }
+}
+
+/// Конец из собственных `end` или `dur` элемента.
+fn own_end(node: roxmltree::Node, parent_begin: f64, begin: f64, time_base: &TimeBase) -> Option<f64> {
+    match attr(node, "end").and_then(|v| parse_time(v, time_base)) {
+        Some(end) => Some(parent_begin + end),
+        None => attr(node, "dur").and_then(|v| parse_time(v, time_base)).map(|dur| begin + dur),
+    }
+}
+
+/// Самое раннее начало и самый поздний конец среди `<span>` с временем (вложенные — от начала родителя).
+fn span_bounds(node: roxmltree::Node, parent_begin: f64, time_base: &TimeBase, bounds: &mut (Option<f64>, Option<f64>)) {
+    for span in node.children().filter(|n| n.is_element() && n.tag_name().name() == "span") {
+        let begin_attr = attr(span, "begin").and_then(|v| parse_time(v, time_base));
+        let begin = parent_begin + begin_attr.unwrap_or(0.0);
+        let end = own_end(span, parent_begin, begin, time_base);
+        if begin_attr.is_some() {
+            bounds.0 = Some(bounds.0.map_or(begin, |b| b.min(begin)));
+        }
+        if let Some(end) = end {
+            bounds.1 = Some(bounds.1.map_or(end, |e| e.max(end)));
+        }
+        span_bounds(span, begin, time_base, bounds);
+    }
+}
+
+fn parse_paragraph(
+    source: &str,
+    p: roxmltree::Node,
+    parent: Interval,
+    time_base: &TimeBase,
+    diagnostics: &mut Vec<ParseDiagnostic>,
+) -> Option<SubtitleSegment> {
+    let line = p.document().text_pos_at(p.range().start).row as usize;
+    let begin_attr = attr(p, "begin").and_then(|v| parse_time(v, time_base));
+    let begin = parent.begin + begin_attr.unwrap_or(0.0);
+    let mut bounds = (None, None);
+    span_bounds(p, begin, time_base, &mut bounds);
+
+    // Без своего времени у `<p>` оно берётся из `<span>` с временем, затем из `<div>`/`<body>`
+    let start = match (begin_attr, bounds.0) {
+        (None, Some(span_begin)) => span_begin,
+        _ => begin,
+    };
+    let end = match (own_end(p, parent.begin, begin, time_base), bounds.1, parent.end) {
+        (Some(end), _, _) => end,
+        (None, Some(span_end), _) => {
+            diagnostics.push(ParseDiagnostic::repaired(line, "Конец реплики взят из времени <span>"));
+            span_end
+        }
+        (None, None, Some(parent_end)) => {
+            diagnostics.push(ParseDiagnostic::repaired(line, "Конец реплики взят из родительского элемента"));
+            parent_end
+        }
+        (None, None, None) => {
+            diagnostics.push(ParseDiagnostic::skipped(
+                line,
+                "Нет времени ни у <p>, ни у <span>, ни у родительского элемента — реплика пропущена",
+            ));
+            return None;
+        }
+    };
+    let start = Timestamp::from_seconds(start);
+    let end = Timestamp::from_seconds(end);
+
+    let mut spans = Vec::new();
+    let mut raw_text = String::new();
+    collect_text(p, &mut raw_text, &mut spans);
+    let text = normalize_whitespace(&raw_text);
+
+    let raw = &source[p.range()];
+    // Начало `<p>` взято из `<span>`, значит их время, отсчитанное от прежнего начала, уже не верно
+    let raw_content = match (begin_attr, bounds.0) {
+        (None, Some(_)) => strip_timing(inner_xml(raw)),
+        _ => inner_xml(raw).to_string(),
+    };
+
+    let element_children: Vec<roxmltree::Node> = p.children().filter(|n| n.is_element()).collect();
+    let only_text_outside = p
+        .children()
+        .filter(|n| n.is_text())
+        .all(|n| n.text().unwrap_or("").trim().is_empty());
+    let wrapper_span = match element_children.as_slice() {
+        [span] if span.tag_name().name() == "span" && only_text_outside => {
+            Some(strip_timing(start_tag(&source[span.range()])))
+        }
+        _ => None,
+    };
+
+    let meta = TtmlCueMeta {
+        xml_id: attr(p, "id").map(|s| s.to_string()),
+        region: attr(p, "region").map(|s| s.to_string()),
+        style: attr(p, "style").map(|s| s.to_string()),
+        lang: attr(p, "lang").map(|s| s.to_string()),
+        inline_styles: styling_attributes(p),
+        spans,
+        raw_content,
+        source_text: Some(text.clone()),
+        wrapper_span,
+    };
+
+    Some(SubtitleSegment {
+        id: 0,
+        start,
+        end,
+        duration: (end - start).max(Timestamp::ZERO),
+        text,
+        translation: None,
+        flags: None,
+        words: None,
+        format_meta: Some(SegmentFormatMeta::Ttml(meta)),
+    })
+}
+
+fn collect_text(node: roxmltree::Node, out: &mut String, spans: &mut Vec<TtmlSpan>) {
+    for n in node.children() {
+        if n.is_text() {
+            // Переводы строк в исходнике — обычные пробелы, строку разрывает только `<br/>`
+            out.push_str(&n.text().unwrap_or("").replace(['\n', '\r', '\t'], " "));
+        } else if n.is_element() {
+            match n.tag_name().name() {
+                "br" => out.push('\n'),
+                "span" => {
+                    let mut span_text = String::new();
+                    let mut nested = Vec::new();
+                    collect_text(n, &mut span_text, &mut nested);
+                    out.push_str(&span_text);
+                    spans.push(TtmlSpan {
+                        text: normalize_whitespace(&span_text),
+                        style: attr(n, "style").map(|s| s.to_string()),
+                        inline_styles: styling_attributes(n),
+                    });
+                    spans.extend(nested);
+                }
+                _ => collect_text(n, out, spans),
+            }
+        }
+    }
+}
+
+/// `xml:space="default"`: пробельные последовательности схлопываются, края строк обрезаются.
+fn normalize_whitespace(text: &str) -> String {
+    text.split('\n')
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+fn styling_attributes(node: roxmltree::Node) -> Vec<TtmlAttribute> {
+    node.attributes()
+        .filter(|a| a.namespace() == Some("http://www.w3.org/ns/ttml#styling"))
+        .map(|a| TtmlAttribute {
+            name: format!("tts:{}", a.name()),
+            value: a.value().to_string(),
+        })
+        .collect()
+}
+
+/// Квалифицированное имя из открывающего тега: `<tt:div ...>` → `tt:div`.
+pub fn tag_name(start_tag: &str) -> &str {
+    let name = start_tag.trim_start().trim_start_matches('<');
+    let end = name
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(name.len());
+    &name[..end]
+}
+
+/// Имя элемента TTML с префиксом документа: `p` → `tt:p`.
+pub fn qualified_name(prefix: Option<&str>, local: &str) -> String {
+    match prefix {
+        Some(prefix) => format!("{}:{}", prefix, local),
+        None => local.to_string(),
+    }
+}
+
+/// Атрибут по локальному имени, без учёта префикса (в реальных файлах префиксы бывают любые).
+fn attr<'a>(node: roxmltree::Node<'a, '_>, local: &str) -> Option<&'a str> {
+    node.attributes().find(|a| a.name() == local).map(|a| a.value())
+}
+
+fn child<'a, 'input>(node: roxmltree::Node<'a, 'input>, local: &str) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|n| n.is_element() && n.tag_name().name() == local)
+}
+
+/// Открывающий тег элемента (до первой `>` вне кавычек).
+fn start_tag(raw: &str) -> &str {
+    let mut quote: Option<char> = None;
+    for (i, c) in raw.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '>') => return &raw[..=i],
+            _ => {}
+        }
+    }
+    raw
+}
+
+/// Время `<p>` при экспорте абсолютное, поэтому `begin`/`end`/`dur` контейнеров
+/// и обёрток `<span>` не сохраняем.
+fn strip_timing(tag: &str) -> String {
+    match regex::Regex::new(r#"\s(?:begin|end|dur)\s*=\s*(?:"[^"]*"|'[^']*')"#) {
+        Ok(re) => re.replace_all(tag, "").into_owned(),
+        Err(_) => tag.to_string(),
+    }
+}
+
+fn inner_xml(raw: &str) -> &str {
+    let open = start_tag(raw);
+    if open.ends_with("/>") {
+        return "";
+    }
+    let rest = &raw[open.len()..];
+    match rest.rfind("</") {
+        Some(close) => &rest[..close],
+        None => rest,
+    }
+}
+
+/// Выражения времени TTML: `HH:MM:SS(.fff)`, `HH:MM:SS:FF(.sub)` и смещения
+/// `12.5s`, `1500ms`, `2m`, `1h`, `25f`, `10010000t`.
+fn parse_time(value: &str, base: &TimeBase) -> Option<f64> {
+    let v = value.trim();
+
+    if v.contains(':') {
+        let parts: Vec<&str> = v.split(':').collect();
+        let hours = parts.first()?.parse::<f64>().ok()?;
+        let minutes = parts.get(1)?.parse::<f64>().ok()?;
+        let seconds = parts.get(2)?.parse::<f64>().ok()?;
+        let mut total = hours * 3600.0 + minutes * 60.0 + seconds;
+        if let Some(frames) = parts.get(3) {
+            let (whole, sub) = frames.split_once('.').unwrap_or((frames, "0"));
+            let frames = whole.parse::<f64>().ok()?;
+            let sub_frames = sub.parse::<f64>().unwrap_or(0.0);
+            total += (frames + sub_frames / base.sub_frame_rate) / base.frame_rate;
+        }
+        return Some(total);
+    }
+
+    let split = v.find(|c: char| c.is_ascii_alphabetic())?;
+    let (number, unit) = v.split_at(split);
+    let n = number.parse::<f64>().ok()?;
+    match unit {
+        "h" => Some(n * 3600.0),
+        "m" => Some(n * 60.0),
+        "s" => Some(n),
+        "ms" => Some(n / 1000.0),
+        "f" => Some(n / base.frame_rate),
+        "t" => Some(n / base.tick_rate),
+        _ => None,
+    }
+}
+
+/// `HH:MM:SS.mmm` — допустимо и в IMSC1, и в EBU-TT-D
+pub fn format_time(time: Timestamp) -> String {
+    let (hours, minutes, seconds, millis) = time.hmsm();
+    format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, seconds, millis)
+}
+
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Обычный текст → содержимое `<p>`: экранирование и `<br/>` вместо переводов строк.
+/// `prefix` — префикс пространства имён TTML документа, если элементы пишутся с ним.
+pub fn text_to_content(text: &str, prefix: Option<&str>) -> String {
+    text.lines()
+        .map(escape_xml)
+        .collect::<Vec<_>>()
+        .join(&format!("<{}/>", qualified_name(prefix, "br")))
+}
+
+/// Текст, который дал бы `<p>` с данным содержимым (для проверки, редактировали ли строку
+/// в проектах, сохранённых без `source_text`). `namespaces` — объявления корня документа.
+pub fn content_to_text(raw_content: &str, namespaces: &[String]) -> Option<String> {
+    let mut declarations = namespaces.to_vec();
+    for (name, uri) in [("xmlns", "http://www.w3.org/ns/ttml"), ("xmlns:tts", "http://www.w3.org/ns/ttml#styling")] {
+        if !declarations.iter().any(|d| d.split('=').next().map(str::trim) == Some(name)) {
+            declarations.push(format!("{}=\"{}\"", name, uri));
+        }
+    }
+    let wrapped = format!("<p {}>{}</p>", declarations.join(" "), raw_content);
+    let doc = roxmltree::Document::parse(&wrapped).ok()?;
+    let mut out = String::new();
+    let mut spans = Vec::new();
+    collect_text(doc.root_element(), &mut out, &mut spans);
+    Some(normalize_whitespace(&out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMSC1: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<tt xmlns="http://www.w3.org/ns/ttml" xmlns:tts="http://www.w3.org/ns/ttml#styling"
+    xmlns:itts="http://www.w3.org/ns/ttml/profile/imsc1#styling" xmlns:ebutts="urn:ebu:tt:style" xml:lang="en">
+  <body><div>
+    <p begin="1s" end="2s"><span itts:forcedDisplay="true">Forced</span></p>
+    <p begin="3s" end="4s"><span ebutts:linePadding="0.5c" tts:color="red">Padded</span></p>
+  </div></body>
+</tt>"#;
+
+    fn ttml_meta(segment: &SubtitleSegment) -> &TtmlCueMeta {
+        match &segment.format_meta {
+            Some(SegmentFormatMeta::Ttml(meta)) => meta,
+            _ => panic!("нет TtmlCueMeta"),
+        }
+    }
+
+    #[test]
+    fn source_text_is_kept_for_spans_with_foreign_prefixes() {
+        let (segments, _, _) = parse(IMSC1).unwrap();
+        assert_eq!(segments.len(), 2);
+        for segment in &segments {
+            assert_eq!(ttml_meta(segment).source_text.as_deref(), Some(segment.text.as_str()));
+        }
+    }
+
+    #[test]
+    fn content_to_text_declares_root_namespaces() {
+        let (segments, document, _) = parse(IMSC1).unwrap();
+        let namespaces = document.namespace_declarations();
+        assert_eq!(namespaces.len(), 4);
+        for segment in &segments {
+            let meta = ttml_meta(segment);
+            assert_eq!(content_to_text(&meta.raw_content, &namespaces).as_deref(), Some(segment.text.as_str()));
+        }
+    }
+}
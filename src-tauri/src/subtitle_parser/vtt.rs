@@ -1,83 +1,207 @@
-use crate::project::SubtitleSegment;
+use crate::project::{SegmentFormatMeta, SubtitleSegment, Timestamp};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
-pub fn parse(content: &str) -> Result<Vec<SubtitleSegment>, String> {
-    let mut segments = Vec::new();
-    let lines: Vec<&str> = content.lines().collect();
-    let mut i = 0;
-    
-    // Пропускаем заголовок WebVTT
-    while i < lines.len() && lines[i].trim().is_empty() {
-        i += 1;
-    }
-    
-    if i < lines.len() && lines[i].contains("WEBVTT") {
-        i += 1;
-        // Пропускаем пустые строки после заголовка
-        while i < lines.len() && lines[i].trim().is_empty() {
-            i += 1;
+/// Заголовок WebVTT: всё, что стоит до первой реплики.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct VttHeader {
+    /// Текст после `WEBVTT` в первой строке (например, ` - Episode 1`)
+    pub header_text: String,
+    /// Строки метаданных сразу под `WEBVTT` (до первой пустой строки)
+    #[serde(default)]
+    pub header_lines: Vec<String>,
+    /// Блоки `NOTE`, `STYLE`, `REGION` до первой реплики — как есть, в исходном порядке
+    #[serde(default)]
+    pub blocks: Vec<String>,
+    /// Блоки `NOTE` после последней реплики
+    #[serde(default)]
+    pub trailing_notes: Vec<String>,
+}
+
+/// Данные реплики WebVTT помимо времени и текста.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct VttCueMeta {
+    /// Идентификатор реплики (строка перед таймингом)
+    pub identifier: Option<String>,
+    /// Настройки после тайминга: `line:0 position:50% align:start` и т.п.
+    pub settings: Option<String>,
+    /// Блоки `NOTE`, стоявшие непосредственно перед репликой
+    #[serde(default)]
+    pub notes_before: Vec<String>,
+}
+
+pub fn parse(content: &str) -> Result<(Vec<SubtitleSegment>, VttHeader), String> {
+    let normalized = content
+        .trim_start_matches('\u{feff}')
+        .replace("\r\n", "\n")
+        .replace('\r', "\n");
+
+    // Блоки разделяются одной или несколькими пустыми строками
+    let mut blocks: Vec<Vec<&str>> = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for line in normalized.lines() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                blocks.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(line);
         }
     }
-    
-    let time_regex = Regex::new(r"(\d{2}):(\d{2}):(\d{2})\.(\d{3})\s*-->\s*(\d{2}):(\d{2}):(\d{2})\.(\d{3})")
-        .map_err(|e| format!("Ошибка компиляции регулярного выражения: {}", e))?;
-    
-    while i < lines.len() {
-        let line = lines[i].trim();
-        
-        if line.is_empty() {
-            i += 1;
-            continue;
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+
+    let mut header = VttHeader::default();
+    let mut blocks_iter = blocks.into_iter().peekable();
+
+    if let Some(first) = blocks_iter.peek() {
+        if let Some(rest) = first[0].trim_end().strip_prefix("WEBVTT") {
+            header.header_text = rest.to_string();
+            header.header_lines = first[1..].iter().map(|l| l.to_string()).collect();
+            blocks_iter.next();
         }
-        
-        // Проверяем строку времени
-        if let Some(captures) = time_regex.captures(line) {
-            let start_hours = captures[1].parse::<u32>().unwrap_or(0);
-            let start_minutes = captures[2].parse::<u32>().unwrap_or(0);
-            let start_seconds = captures[3].parse::<u32>().unwrap_or(0);
-            let start_millis = captures[4].parse::<u32>().unwrap_or(0);
-            
-            let end_hours = captures[5].parse::<u32>().unwrap_or(0);
-            let end_minutes = captures[6].parse::<u32>().unwrap_or(0);
-            let end_seconds = captures[7].parse::<u32>().unwrap_or(0);
-            let end_millis = captures[8].parse::<u32>().unwrap_or(0);
-            
-            let start = (start_hours as f64 * 3600.0) + 
-                       (start_minutes as f64 * 60.0) + 
-                       (start_seconds as f64) + 
-                       (start_millis as f64 / 1000.0);
-            
-            let end = (end_hours as f64 * 3600.0) + 
-                     (end_minutes as f64 * 60.0) + 
-                     (end_seconds as f64) + 
-                     (end_millis as f64 / 1000.0);
-            
-            i += 1;
-            let mut text_lines = Vec::new();
-            
-            // Собираем текст сегмента
-            while i < lines.len() && !lines[i].trim().is_empty() {
-                text_lines.push(lines[i]);
-                i += 1;
+    }
+
+    // Часы необязательны: `MM:SS.mmm` — допустимая форма
+    let timing_regex = Regex::new(
+        r"^\s*((?:\d+:)?\d{1,2}:\d{2}\.\d{3})\s+-->\s+((?:\d+:)?\d{1,2}:\d{2}\.\d{3})(.*)$",
+    )
+    .map_err(|e| format!("Ошибка компиляции регулярного выражения: {}", e))?;
+
+    let mut segments = Vec::new();
+    let mut pending_notes: Vec<String> = Vec::new();
+
+    for block in blocks_iter {
+        let timing_pos = block.iter().take(2).position(|l| l.contains("-->"));
+
+        let Some(timing_pos) = timing_pos else {
+            let keyword = block[0].split_whitespace().next().unwrap_or("");
+            let raw = block.join("\n");
+            match keyword {
+                "NOTE" if segments.is_empty() => header.blocks.push(raw),
+                "NOTE" => pending_notes.push(raw),
+                // STYLE и REGION допустимы только до первой реплики
+                "STYLE" | "REGION" if segments.is_empty() => header.blocks.push(raw),
+                _ => {}
             }
-            
-            let text = text_lines.join("\n");
-            let id = segments.len() as u32 + 1;
-            
-            segments.push(SubtitleSegment {
-                id,
-                start,
-                end,
-                duration: end - start,
-                text,
-                translation: None,
-                flags: None,
-            });
+            continue;
+        };
+
+        let Some(captures) = timing_regex.captures(block[timing_pos]) else {
+            continue;
+        };
+        let (Some(start), Some(end)) = (parse_timestamp(&captures[1]), parse_timestamp(&captures[2])) else {
+            continue;
+        };
+
+        let settings = captures[3].trim();
+        let identifier = if timing_pos == 1 {
+            Some(block[0].to_string())
         } else {
-            // Пропускаем другие строки (идентификаторы, заметки и т.д.)
-            i += 1;
+            None
+        };
+
+        let text = block[timing_pos + 1..].join("\n");
+        let id = segments.len() as u32 + 1;
+
+        segments.push(SubtitleSegment {
+            id,
+            start,
+            end,
+            duration: end - start,
+            text,
+            translation: None,
+            flags: None,
+            words: None,
+            format_meta: Some(SegmentFormatMeta::Vtt(VttCueMeta {
+                identifier,
+                settings: if settings.is_empty() { None } else { Some(settings.to_string()) },
+                notes_before: std::mem::take(&mut pending_notes),
+            })),
+        });
+    }
+
+    header.trailing_notes = pending_notes;
+
+    Ok((segments, header))
+}
+
+/// `HH:MM:SS.mmm` или `MM:SS.mmm`
+fn parse_timestamp(s: &str) -> Option<Timestamp> {
+    let (clock, millis) = s.split_once('.')?;
+    let parts: Vec<&str> = clock.split(':').collect();
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [h, m, s] => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?, s.parse::<u32>().ok()?),
+        [m, s] => (0, m.parse::<u32>().ok()?, s.parse::<u32>().ok()?),
+        _ => return None,
+    };
+    if minutes > 59 || seconds > 59 {
+        return None;
+    }
+    if millis.len() != 3 {
+        return None;
+    }
+    let millis = millis.parse::<u32>().ok()?;
+
+    Some(Timestamp::from_hmsm(hours, minutes, seconds, millis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::files::generate_vtt;
+    use crate::project::{SegmentFormatMeta, SubtitleFileMeta};
+
+    const DOCUMENT: &str = "WEBVTT - Пример\nKind: captions\n\n\
+STYLE\n::cue { color: yellow }\n\n\
+NOTE перед первой\n\n\
+intro\n00:01.000 --> 00:02.500 line:0 align:start\n<v Боб>Привет\nвторая строка\n\n\
+00:00:03.250 --> 00:00:04.000\nВторая\n\n\
+01:02:03.456 --> 01:02:07.890 position:20%\nЧерез час\n";
+
+    /// Разбор → WebVTT с заголовком исходного файла → снова разбор
+    fn round_trip(content: &str) -> (Vec<SubtitleSegment>, Vec<SubtitleSegment>) {
+        let (segments, header) = parse(content).unwrap();
+        let generated = generate_vtt(&segments, Some(&SubtitleFileMeta::Vtt(header)));
+        let (reparsed, _) = parse(&generated).unwrap();
+        (segments, reparsed)
+    }
+
+    fn cue_meta(seg: &SubtitleSegment) -> Option<&VttCueMeta> {
+        match &seg.format_meta {
+            Some(SegmentFormatMeta::Vtt(meta)) => Some(meta),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn timings_and_text_survive_round_trip() {
+        let (segments, reparsed) = round_trip(DOCUMENT);
+        assert_eq!(reparsed.len(), 3);
+        for (before, after) in segments.iter().zip(&reparsed) {
+            assert_eq!((after.start, after.end), (before.start, before.end), "{:?}", before.text);
+            assert_eq!(after.text, before.text);
         }
+        assert_eq!(reparsed[2].start, Timestamp::from_hmsm(1, 2, 3, 456));
+        assert_eq!(reparsed[2].end, Timestamp::from_millis(3_727_890));
+    }
+
+    #[test]
+    fn cue_settings_and_identifiers_survive_round_trip() {
+        let (segments, reparsed) = round_trip(DOCUMENT);
+        let before = cue_meta(&segments[0]).unwrap();
+        let after = cue_meta(&reparsed[0]).unwrap();
+        assert_eq!(after.identifier.as_deref(), Some("intro"));
+        assert_eq!(after.settings, before.settings);
+        assert_eq!(after.notes_before, before.notes_before);
+        assert_eq!(cue_meta(&reparsed[2]).and_then(|m| m.settings.as_deref()), Some("position:20%"));
+    }
+
+    #[test]
+    fn short_timestamps_are_written_in_full_form() {
+        let (segments, _) = parse(DOCUMENT).unwrap();
+        let generated = generate_vtt(&segments, None);
+        assert!(generated.contains("00:00:01.000 --> 00:00:02.500"), "{}", generated);
     }
-    
-    Ok(segments)
-}
\ No newline at end of file
+}
@@ -0,0 +1,240 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use super::{whisper_json, TranscribeFuture, Transcript, TranscriptionBackend, TranscriptionEngine, TranscriptionRequest, TranscriptionSettings};
+use crate::project::{SubtitleSegment, SubtitleWord, Timestamp};
+use crate::segmentation;
+
+/// Распознавание локальным процессом: аудио никуда не отправляется
+pub struct LocalBackend {
+    engine: TranscriptionEngine,
+    executable: String,
+    model: Option<String>,
+    threads: Option<u32>,
+}
+
+impl LocalBackend {
+    pub fn new(settings: &TranscriptionSettings) -> Result<Self, String> {
+        let default_executable = match settings.engine {
+            TranscriptionEngine::WhisperCpp => "whisper-cli",
+            TranscriptionEngine::FasterWhisper => "whisper-ctranslate2",
+            TranscriptionEngine::OpenAi => return Err("OpenAI не локальный движок".to_string()),
+        };
+        let model = settings.model.clone().filter(|m| !m.trim().is_empty());
+        if settings.engine == TranscriptionEngine::WhisperCpp && model.is_none() {
+            return Err("Для whisper.cpp укажите файл модели (ggml-*.bin)".to_string());
+        }
+        Ok(Self {
+            engine: settings.engine,
+            executable: settings
+                .executable
+                .clone()
+                .filter(|e| !e.trim().is_empty())
+                .unwrap_or_else(|| default_executable.to_string()),
+            model,
+            threads: settings.threads,
+        })
+    }
+
+    async fn run(&self, request: &TranscriptionRequest<'_>, work_dir: &Path) -> Result<Transcript, String> {
+        match self.engine {
+            TranscriptionEngine::WhisperCpp => {
+                // whisper.cpp читает только WAV 16 кГц
+                let wav_path = work_dir.join("audio.wav");
+                extract_wav(request.audio_path, &wav_path).await?;
+
+                let out_base = work_dir.join("transcript");
+                let mut cmd = Command::new(&self.executable);
+                cmd.arg("-m")
+                    .arg(self.model.as_deref().unwrap_or_default())
+                    .arg("-f")
+                    .arg(&wav_path)
+                    .arg("-l")
+                    .arg(request.language)
+                    // JSON с токенами: из них собираются слова с таймингами
+                    .arg("-oj")
+                    .arg("-ojf")
+                    .arg("-of")
+                    .arg(&out_base);
+                if let Some(threads) = self.threads {
+                    cmd.arg("-t").arg(threads.to_string());
+                }
+                if let Some(prompt) = request.prompt.filter(|p| !p.trim().is_empty()) {
+                    cmd.arg("--prompt").arg(prompt);
+                }
+                run_process(cmd, self.name()).await?;
+                parse_whisper_cpp_json(&read_json(&out_base.with_extension("json"))?)
+            }
+            TranscriptionEngine::FasterWhisper => {
+                let mut cmd = Command::new(&self.executable);
+                cmd.arg(request.audio_path)
+                    .arg("--language")
+                    .arg(request.language)
+                    .arg("--word_timestamps")
+                    .arg("True")
+                    .arg("--output_format")
+                    .arg("json")
+                    .arg("--output_dir")
+                    .arg(work_dir);
+                if let Some(model) = &self.model {
+                    cmd.arg("--model").arg(model);
+                }
+                if let Some(threads) = self.threads {
+                    cmd.arg("--threads").arg(threads.to_string());
+                }
+                if let Some(prompt) = request.prompt.filter(|p| !p.trim().is_empty()) {
+                    cmd.arg("--initial_prompt").arg(prompt);
+                }
+                run_process(cmd, self.name()).await?;
+                // Имя файла повторяет имя входного, поэтому берём единственный JSON в каталоге
+                whisper_json::parse_verbose_json(&read_json(&find_json(work_dir)?)?)
+            }
+            TranscriptionEngine::OpenAi => Err("OpenAI не локальный движок".to_string()),
+        }
+    }
+}
+
+impl TranscriptionBackend for LocalBackend {
+    fn name(&self) -> &'static str {
+        match self.engine {
+            TranscriptionEngine::FasterWhisper => "faster-whisper",
+            _ => "whisper.cpp",
+        }
+    }
+
+    fn max_upload_bytes(&self) -> Option<u64> {
+        None
+    }
+
+    fn transcribe<'a>(&'a self, request: TranscriptionRequest<'a>) -> TranscribeFuture<'a> {
+        Box::pin(async move {
+            let work_dir = std::env::temp_dir().join(format!("subtitle_studio_local_{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&work_dir)
+                .map_err(|e| format!("Не удалось создать временный каталог: {}", e))?;
+            let result = self.run(&request, &work_dir).await;
+            let _ = std::fs::remove_dir_all(&work_dir);
+            result
+        })
+    }
+}
+
+async fn extract_wav(input_path: &Path, wav_path: &Path) -> Result<(), String> {
+    let output = Command::new("ffmpeg")
+        .arg("-y")
+        .arg("-i")
+        .arg(input_path)
+        .arg("-vn")
+        .arg("-ac")
+        .arg("1")
+        .arg("-ar")
+        .arg("16000")
+        .arg("-c:a")
+        .arg("pcm_s16le")
+        .arg(wav_path)
+        .output()
+        .await
+        .map_err(|e| format!("Не удалось запустить ffmpeg: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "ffmpeg подготовка WAV завершилась с ошибкой: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(())
+}
+
+async fn run_process(mut cmd: Command, name: &str) -> Result<(), String> {
+    println!("Локальное распознавание ({}): {:?}", name, cmd.as_std());
+    let output = cmd
+        .output()
+        .await
+        .map_err(|e| format!("Не удалось запустить {}: {}", name, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} завершился с ошибкой: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(())
+}
+
+fn read_json(path: &Path) -> Result<Value, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Не найден результат распознавания {:?}: {}", path, e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Ошибка разбора результата распознавания: {}", e))
+}
+
+fn find_json(dir: &Path) -> Result<PathBuf, String> {
+    std::fs::read_dir(dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .find(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .ok_or_else(|| "Локальный движок не записал JSON с результатом".to_string())
+}
+
+fn offset_ms(v: &Value) -> Timestamp {
+    Timestamp::from_millis(v.as_i64().unwrap_or(0))
+}
+
+/// JSON whisper.cpp (`-oj -ojf`): `transcription[]` с `offsets` в мс и токенами
+fn parse_whisper_cpp_json(value: &Value) -> Result<Transcript, String> {
+    let items = value["transcription"]
+        .as_array()
+        .ok_or("Нет сегментов в ответе whisper.cpp".to_string())?;
+
+    let segments = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let text = item["text"].as_str().unwrap_or("").trim().to_string();
+            let start = offset_ms(&item["offsets"]["from"]);
+            let end = offset_ms(&item["offsets"]["to"]).max(start);
+            let words = item["tokens"]
+                .as_array()
+                .map(|tokens| segmentation::align_words(&text, start, end, &whisper_cpp_words(tokens)));
+            SubtitleSegment {
+                id: (i + 1) as u32,
+                start,
+                end,
+                duration: end - start,
+                text,
+                translation: None,
+                flags: None,
+                words,
+                format_meta: None,
+            }
+        })
+        .collect();
+
+    Ok(Transcript { segments, words: Vec::new() })
+}
+
+/// Токены → слова: токен с пробелом в начале открывает слово, остальные дописываются
+/// к текущему; служебные `[_BEG_]`, `[_TT_…]` пропускаются. Уверенность слова —
+/// наименьшая по его токенам.
+fn whisper_cpp_words(tokens: &[Value]) -> Vec<SubtitleWord> {
+    let mut words: Vec<SubtitleWord> = Vec::new();
+    for token in tokens {
+        let Some(text) = token["text"].as_str() else { continue };
+        if text.is_empty() || text.starts_with("[_") {
+            continue;
+        }
+        let start = offset_ms(&token["offsets"]["from"]);
+        let end = offset_ms(&token["offsets"]["to"]).max(start);
+        let confidence = token["p"].as_f64();
+        match words.last_mut() {
+            Some(word) if !text.starts_with(' ') => {
+                word.text.push_str(text);
+                word.end = word.end.max(end);
+                word.confidence = match (word.confidence, confidence) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+            }
+            _ => words.push(SubtitleWord { text: text.trim().to_string(), start, end, confidence }),
+        }
+    }
+    words.retain(|w| !w.text.is_empty());
+    words
+}
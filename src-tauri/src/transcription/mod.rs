@@ -0,0 +1,64 @@
+// Движки распознавания речи. OpenAI Whisper по сети или локальный процесс
+// (whisper.cpp, faster-whisper) — для материалов, которые нельзя отправлять наружу.
+// Движок выбирается в настройках проекта.
+
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use crate::project::{SubtitleSegment, SubtitleWord};
+
+pub mod local;
+pub mod openai;
+mod whisper_json;
+
+pub use local::LocalBackend;
+pub use openai::OpenAiBackend;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TranscriptionEngine {
+    #[default]
+    OpenAi,
+    /// `whisper-cli` из whisper.cpp, модель — файл ggml
+    WhisperCpp,
+    /// `whisper-ctranslate2` (CLI к faster-whisper)
+    FasterWhisper,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TranscriptionSettings {
+    pub engine: TranscriptionEngine,
+    /// Путь к исполняемому файлу локального движка; по умолчанию ищется в PATH
+    pub executable: Option<String>,
+    /// Модель локального движка: файл ggml для whisper.cpp, имя или каталог для faster-whisper
+    pub model: Option<String>,
+    /// Потоков для локального движка
+    pub threads: Option<u32>,
+}
+
+/// Один файл на распознавание
+pub struct TranscriptionRequest<'a> {
+    pub audio_path: &'a Path,
+    pub language: &'a str,
+    pub prompt: Option<&'a str>,
+}
+
+/// Результат распознавания: сегменты (со словами, если движок их отдаёт внутри сегментов)
+/// и общий список слов, если слова идут отдельно
+pub struct Transcript {
+    pub segments: Vec<SubtitleSegment>,
+    pub words: Vec<SubtitleWord>,
+}
+
+pub type TranscribeFuture<'a> = Pin<Box<dyn Future<Output = Result<Transcript, String>> + Send + 'a>>;
+
+pub trait TranscriptionBackend: Send + Sync {
+    /// Название для прогресса и логов
+    fn name(&self) -> &'static str;
+
+    /// Лимит размера файла за один вызов; файлы больше распознаются по кускам
+    fn max_upload_bytes(&self) -> Option<u64>;
+
+    fn transcribe<'a>(&'a self, request: TranscriptionRequest<'a>) -> TranscribeFuture<'a>;
+}
@@ -0,0 +1,140 @@
+use reqwest::multipart;
+use std::path::Path;
+use super::{whisper_json, TranscribeFuture, TranscriptionBackend, TranscriptionRequest};
+use crate::commands::ai::log_debug_block;
+
+const WHISPER_MAX_UPLOAD_BYTES: u64 = 25 * 1024 * 1024;
+const WHISPER_TARGET_UPLOAD_BYTES: u64 = 24 * 1024 * 1024;
+
+/// OpenAI `/v1/audio/transcriptions`, модель `whisper-1`
+pub struct OpenAiBackend {
+    client: reqwest::Client,
+    api_key: String,
+}
+
+impl OpenAiBackend {
+    pub fn new(api_key: String) -> Self {
+        Self { client: reqwest::Client::new(), api_key }
+    }
+}
+
+impl TranscriptionBackend for OpenAiBackend {
+    fn name(&self) -> &'static str {
+        "OpenAI Whisper"
+    }
+
+    fn max_upload_bytes(&self) -> Option<u64> {
+        Some(WHISPER_TARGET_UPLOAD_BYTES)
+    }
+
+    fn transcribe<'a>(&'a self, request: TranscriptionRequest<'a>) -> TranscribeFuture<'a> {
+        Box::pin(async move {
+            let file_data = std::fs::read(request.audio_path)
+                .map_err(|e| format!("Ошибка чтения файла: {}", e))?;
+            if file_data.len() as u64 > WHISPER_MAX_UPLOAD_BYTES {
+                return Err(format!(
+                    "Файл {} байт больше лимита OpenAI Whisper ({} байт)",
+                    file_data.len(),
+                    WHISPER_MAX_UPLOAD_BYTES
+                ));
+            }
+            let response = request_whisper(
+                &self.client,
+                &self.api_key,
+                file_data,
+                infer_audio_mime(request.audio_path),
+                request.language,
+                request.prompt,
+            )
+            .await?;
+            whisper_json::parse_verbose_json(&response)
+        })
+    }
+}
+
+fn infer_audio_mime(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "mp3" => "audio/mpeg",
+        "m4a" | "mp4" => "audio/mp4",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "webm" => "audio/webm",
+        "flac" => "audio/flac",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Один запрос к Whisper (`verbose_json` с таймингами сегментов и слов)
+async fn request_whisper(
+    client: &reqwest::Client,
+    api_key: &str,
+    file_data: Vec<u8>,
+    file_mime: &str,
+    language_code: &str,
+    prompt: Option<&str>,
+) -> Result<serde_json::Value, String> {
+    let file_size_bytes = file_data.len();
+    let file_part = multipart::Part::bytes(file_data)
+        .file_name("audio_upload")
+        .mime_str(file_mime)
+        .map_err(|e| e.to_string())?;
+
+    let mut form = multipart::Form::new()
+        .text("model", "whisper-1")
+        .text("language", language_code.to_string())
+        // 0 — детерминированнее, ниже шанс «фантазий» в тишине (см. доку OpenAI /temperature)
+        .text("temperature", "0")
+        .text("response_format", "verbose_json")
+        .text("timestamp_granularities[]", "segment")
+        .text("timestamp_granularities[]", "word")
+        .part("file", file_part);
+
+    let whisper_prompt_log = prompt.filter(|p| !p.trim().is_empty());
+    if let Some(prompt_text) = whisper_prompt_log {
+        form = form.text("prompt", prompt_text.to_string());
+    }
+
+    log_debug_block(
+        "whisper: параметры и prompt",
+        &format!(
+            "model: whisper-1\n\
+language: {language_code}\n\
+temperature: 0\n\
+response_format: verbose_json\n\
+timestamp_granularities: segment, word\n\
+file: (бинарное содержимое аудио, размер {} байт)\n\
+mime: {}\n\
+\n\
+prompt (опционально):\n{}",
+            file_size_bytes,
+            file_mime,
+            whisper_prompt_log.unwrap_or("(не задан)")
+        ),
+    );
+
+    let res = client
+        .post("https://api.openai.com/v1/audio/transcriptions")
+        .bearer_auth(api_key)
+        .multipart(form)
+        .send()
+        .await
+        .map_err(|e| format!("Ошибка запроса к OpenAI: {}", e))?;
+
+    if !res.status().is_success() {
+        let status = res.status();
+        let error_text = res.text().await.unwrap_or_else(|_| "Неизвестная ошибка".to_string());
+        return Err(format!("OpenAI ошибка ({}): {}", status, error_text));
+    }
+
+    let response: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+    let response_pretty =
+        serde_json::to_string_pretty(&response).unwrap_or_else(|e| e.to_string());
+    log_debug_block("whisper: ответ API (verbose_json)", &response_pretty);
+    Ok(response)
+}
@@ -0,0 +1,101 @@
+// Ответ Whisper в формате `verbose_json`: так отвечает OpenAI, и так же пишут JSON
+// openai-whisper и faster-whisper (у них слова лежат внутри сегментов).
+
+use super::Transcript;
+use crate::project::{SubtitleSegment, SubtitleWord, Timestamp};
+use crate::segmentation;
+
+pub fn parse_verbose_json(response: &serde_json::Value) -> Result<Transcript, String> {
+    Ok(Transcript {
+        segments: whisper_segments(response)?,
+        words: whisper_words(response),
+    })
+}
+
+fn json_seconds(v: &serde_json::Value) -> f64 {
+    v.as_f64()
+        .or_else(|| v.as_u64().map(|n| n as f64))
+        .or_else(|| v.as_i64().map(|n| n as f64))
+        .unwrap_or(0.0)
+}
+
+/// Слова Whisper (`word`, `start`, `end`, у локальных моделей ещё `probability`)
+fn whisper_word_list(items: &[serde_json::Value]) -> Vec<SubtitleWord> {
+    items
+        .iter()
+        .filter_map(|w| {
+            let text = w["word"].as_str()?.trim().to_string();
+            let start = Timestamp::from_seconds(json_seconds(&w["start"]));
+            let end = Timestamp::from_seconds(json_seconds(&w["end"]));
+            let confidence = w["probability"].as_f64();
+            Some(SubtitleWord { text, start, end: end.max(start), confidence })
+        })
+        .collect()
+}
+
+/// Слова с таймингами: общий массив `words` (OpenAI) или `words` внутри сегментов
+fn whisper_words(response: &serde_json::Value) -> Vec<SubtitleWord> {
+    if let Some(words) = response["words"].as_array() {
+        return whisper_word_list(words);
+    }
+    response["segments"]
+        .as_array()
+        .map(|segments| {
+            segments
+                .iter()
+                .filter_map(|seg| seg["words"].as_array())
+                .flat_map(|words| whisper_word_list(words))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn whisper_segments(response: &serde_json::Value) -> Result<Vec<SubtitleSegment>, String> {
+    let segments = response["segments"]
+        .as_array()
+        .ok_or("Нет сегментов в ответе".to_string())?;
+    
+    let result: Vec<SubtitleSegment> = segments
+        .iter()
+        .enumerate()
+        .map(|(i, seg)| {
+            let id = (i + 1) as u32;
+            let mut start = json_seconds(&seg["start"]);
+            let mut end = json_seconds(&seg["end"]);
+            let text = seg["text"].as_str().unwrap_or("").trim().to_string();
+
+            if let Some(words) = seg["words"].as_array() {
+                if let (Some(first), Some(last)) = (words.first(), words.last()) {
+                    let ws = json_seconds(&first["start"]);
+                    let we = json_seconds(&last["end"]);
+                    if we > ws {
+                        start = ws;
+                        end = we;
+                    }
+                }
+            }
+
+            let start = Timestamp::from_seconds(start);
+            let end = Timestamp::from_seconds(end);
+            let duration = (end - start).max(Timestamp::ZERO);
+            // Слова сегмента привязываем к его тексту, чтобы сохранить пунктуацию
+            let words = seg["words"]
+                .as_array()
+                .map(|items| segmentation::align_words(&text, start, end, &whisper_word_list(items)));
+            
+            SubtitleSegment {
+                id,
+                start,
+                end,
+                duration,
+                text,
+                translation: None,
+                flags: None,
+                words,
+                format_meta: None,
+            }
+        })
+        .collect();
+    
+    Ok(result)
+}
@@ -0,0 +1,291 @@
+use super::{auth_header, trimmed_base_url, AuthScheme, ChatRequest, ProviderFuture, TranslationProvider, TranslationProviderKind, TranslationRequest, TranslationSettings};
+use crate::commands::ai::log_debug_block;
+use crate::types::TranslationResult;
+
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const OPENAI_DEFAULT_MODEL: &str = "gpt-5.4-mini";
+/// Ollama по умолчанию; у vLLM обычно `:8000/v1`, у LM Studio `:1234/v1`
+const COMPATIBLE_BASE_URL: &str = "http://localhost:11434/v1";
+const TRANSLATION_MAX_TOKENS: u32 = 16384;
+
+/// `POST {base_url}/chat/completions`: OpenAI и совместимые с ним серверы
+pub struct ChatCompletionsProvider {
+    client: reqwest::Client,
+    kind: TranslationProviderKind,
+    base_url: String,
+    model: String,
+    auth: AuthScheme,
+    api_key: Option<String>,
+}
+
+impl ChatCompletionsProvider {
+    pub fn new(settings: &TranslationSettings, api_key: Option<String>) -> Result<Self, String> {
+        let model = settings.model.as_deref().map(str::trim).filter(|m| !m.is_empty());
+        let (base_url, model) = match settings.provider {
+            TranslationProviderKind::OpenAi => (
+                trimmed_base_url(settings.base_url.as_deref(), OPENAI_BASE_URL),
+                model.unwrap_or(OPENAI_DEFAULT_MODEL),
+            ),
+            TranslationProviderKind::OpenAiCompatible => (
+                trimmed_base_url(settings.base_url.as_deref(), COMPATIBLE_BASE_URL),
+                model.ok_or("Для OpenAI-совместимого сервера укажите модель (например, qwen2.5:14b)")?,
+            ),
+            TranslationProviderKind::DeepL => return Err("DeepL не использует chat/completions".to_string()),
+        };
+        Ok(Self {
+            client: reqwest::Client::new(),
+            kind: settings.provider,
+            base_url,
+            model: model.to_string(),
+            auth: settings.auth_scheme(),
+            api_key,
+        })
+    }
+
+    async fn chat(&self, request: ChatRequest<'_>) -> Result<String, String> {
+        let mut body = serde_json::json!({
+            "model": self.model,
+            "messages": [
+                { "role": "system", "content": request.system_prompt },
+                { "role": "user", "content": request.user_content }
+            ],
+            "temperature": request.temperature,
+        });
+        if self.kind == TranslationProviderKind::OpenAi {
+            body["response_format"] = serde_json::json!({ "type": "json_object" });
+            body["max_completion_tokens"] = request.max_tokens.into();
+        } else {
+            // `json_object` понимают не все серверы (LM Studio отвечает ошибкой) — JSON просим в промпте
+            body["max_tokens"] = request.max_tokens.into();
+        }
+
+        let mut builder = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(&body);
+        if let Some(header) = auth_header(self.auth, self.api_key.as_deref())? {
+            builder = builder.header(reqwest::header::AUTHORIZATION, header);
+        }
+        let res = builder
+            .send()
+            .await
+            .map_err(|e| format!("Ошибка запроса к {}: {}", self.name(), e))?;
+
+        if !res.status().is_success() {
+            let status = res.status();
+            let error_text = res.text().await.unwrap_or_else(|_| "Неизвестная ошибка".to_string());
+            return Err(format!("{} ошибка ({}): {}", self.name(), status, error_text));
+        }
+
+        let response: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+        let content = response["choices"][0]["message"]["content"]
+            .as_str()
+            .ok_or("Нет контента в ответе".to_string())?;
+        Ok(normalize_json_text(content))
+    }
+}
+
+impl TranslationProvider for ChatCompletionsProvider {
+    fn name(&self) -> &'static str {
+        match self.kind {
+            TranslationProviderKind::OpenAi => "OpenAI",
+            _ => "OpenAI-совместимый сервер",
+        }
+    }
+
+    fn cache_id(&self) -> String {
+        format!("{:?}|{}|{}", self.kind, self.base_url, self.model)
+    }
+
+    fn translate<'a>(&'a self, request: TranslationRequest<'a>) -> ProviderFuture<'a, Vec<TranslationResult>> {
+        Box::pin(async move {
+            let segments_text = serde_json::json!({
+                "segments": request.segments.iter().map(|s| {
+                    serde_json::json!({
+                        "id": s.id,
+                        "text": s.text,
+                        "start": s.start,
+                        "end": s.end
+                    })
+                }).collect::<Vec<_>>()
+            });
+
+            let user_content = serde_json::to_string(&segments_text).map_err(|e| e.to_string())?;
+            let log_label = request.log_label;
+
+            log_debug_block(
+                &format!("перевод [{log_label}]: запрос"),
+                &format!(
+                    "provider: {}\n\
+model: {}\n\
+temperature: 0.3\n\
+max_tokens: {TRANSLATION_MAX_TOKENS}\n\
+\n\
+--- system ---\n\
+{}\n\
+\n\
+--- user (JSON, {} симв.) ---\n\
+{user_content}",
+                    self.name(),
+                    self.model,
+                    request.system_prompt,
+                    user_content.len()
+                ),
+            );
+
+            let content = self
+                .chat(ChatRequest {
+                    system_prompt: request.system_prompt,
+                    user_content: &user_content,
+                    temperature: 0.3,
+                    max_tokens: TRANSLATION_MAX_TOKENS,
+                })
+                .await?;
+            log_debug_block(&format!("перевод [{log_label}]: ответ {}", self.name()), &content);
+            parse_translation_response(&content)
+        })
+    }
+
+    fn complete_json<'a>(&'a self, request: ChatRequest<'a>) -> ProviderFuture<'a, String> {
+        Box::pin(self.chat(request))
+    }
+}
+
+/// Переводы из ответа модели: массив `{id, translated_text}` под любым из привычных
+/// ключей или объект `{"id": "текст"}`
+fn parse_translation_response(content: &str) -> Result<Vec<TranslationResult>, String> {
+    let normalized_content = normalize_json_text(content);
+    let parsed: serde_json::Value = serde_json::from_str(&normalized_content)
+        .map_err(|e| format!("Ошибка парсинга JSON: {}", e))?;
+
+    // 1) Пробуем извлечь напрямую/по известным ключам или рекурсивно в глубину
+    if let Some(candidate_array) = find_translation_array(&parsed) {
+        let mut results = parse_translation_items(candidate_array);
+        if !results.is_empty() {
+            results.sort_by_key(|item| item.id);
+            return Ok(results);
+        }
+    }
+
+    // 2) Fallback: объект формата { "1": "text", "2": "text" } где угодно во вложенности
+    if let Some(map_obj) = find_id_text_map_object(&parsed) {
+        let mut results = map_obj
+            .iter()
+            .filter_map(|(key, value)| {
+                let id = key.parse::<u32>().ok()?;
+                let translated_text = value.as_str()?.trim().to_string();
+                if translated_text.is_empty() {
+                    return None;
+                }
+                Some(TranslationResult { id, translated_text })
+            })
+            .collect::<Vec<_>>();
+
+        if !results.is_empty() {
+            results.sort_by_key(|item| item.id);
+            return Ok(results);
+        }
+    }
+
+    Err(format!(
+        "Не удалось распознать формат перевода в ответе модели. Ответ: {}",
+        normalized_content.chars().take(400).collect::<String>()
+    ))
+}
+
+fn json_u32_from_value(v: &serde_json::Value) -> Option<u32> {
+    if let Some(n) = v.as_u64() {
+        return u32::try_from(n).ok();
+    }
+    if let Some(n) = v.as_i64() {
+        return u32::try_from(n).ok();
+    }
+    v.as_str().and_then(|s| s.trim().parse().ok())
+}
+
+fn normalize_json_text(content: &str) -> String {
+    let trimmed = content.trim();
+    if trimmed.starts_with("```") {
+        let without_fence = trimmed
+            .trim_start_matches("```json")
+            .trim_start_matches("```")
+            .trim_end_matches("```")
+            .trim();
+        without_fence.to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+fn parse_translation_items(items: &[serde_json::Value]) -> Vec<TranslationResult> {
+    items
+        .iter()
+        .filter_map(|item| {
+            let id = item.get("id").and_then(json_u32_from_value).unwrap_or(0);
+            let translated_text = item
+                .get("translated_text")
+                .or_else(|| item.get("translatedText"))
+                .or_else(|| item.get("translation"))
+                .or_else(|| item.get("translated"))
+                .or_else(|| item.get("text"))
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .trim()
+                .to_string();
+
+            if id == 0 || translated_text.is_empty() {
+                None
+            } else {
+                Some(TranslationResult { id, translated_text })
+            }
+        })
+        .collect()
+}
+
+fn find_translation_array(value: &serde_json::Value) -> Option<&Vec<serde_json::Value>> {
+    if let Some(arr) = value.as_array() {
+        return Some(arr);
+    }
+
+    if let Some(obj) = value.as_object() {
+        let priority_keys = ["translations", "results", "items", "data", "output", "response"];
+        for key in priority_keys {
+            if let Some(v) = obj.get(key) {
+                if let Some(arr) = v.as_array() {
+                    return Some(arr);
+                }
+                if let Some(found) = find_translation_array(v) {
+                    return Some(found);
+                }
+            }
+        }
+
+        for v in obj.values() {
+            if let Some(found) = find_translation_array(v) {
+                return Some(found);
+            }
+        }
+    }
+
+    None
+}
+
+fn find_id_text_map_object(
+    value: &serde_json::Value,
+) -> Option<&serde_json::Map<String, serde_json::Value>> {
+    if let Some(obj) = value.as_object() {
+        let maybe_map = obj
+            .iter()
+            .all(|(k, v)| k.parse::<u32>().is_ok() && v.as_str().is_some());
+        if maybe_map && !obj.is_empty() {
+            return Some(obj);
+        }
+
+        for v in obj.values() {
+            if let Some(found) = find_id_text_map_object(v) {
+                return Some(found);
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,109 @@
+use super::{auth_header, trimmed_base_url, AuthScheme, ChatRequest, ProviderFuture, TranslationProvider, TranslationRequest, TranslationSettings};
+use crate::types::TranslationResult;
+
+const DEEPL_BASE_URL: &str = "https://api.deepl.com";
+/// Ключи бесплатного тарифа оканчиваются на `:fx` и работают только с этим адресом
+const DEEPL_FREE_BASE_URL: &str = "https://api-free.deepl.com";
+
+/// DeepL `POST /v2/translate`. Стиль и глоссарий из промпта не передаются —
+/// глоссарий применяется к готовому переводу, как и у остальных провайдеров.
+pub struct DeepLProvider {
+    client: reqwest::Client,
+    base_url: String,
+    model_type: Option<String>,
+    auth: AuthScheme,
+    api_key: Option<String>,
+}
+
+impl DeepLProvider {
+    pub fn new(settings: &TranslationSettings, api_key: Option<String>) -> Self {
+        let default_url = match &api_key {
+            Some(key) if key.ends_with(":fx") => DEEPL_FREE_BASE_URL,
+            _ => DEEPL_BASE_URL,
+        };
+        Self {
+            client: reqwest::Client::new(),
+            base_url: trimmed_base_url(settings.base_url.as_deref(), default_url),
+            model_type: settings.model.clone().filter(|m| !m.trim().is_empty()),
+            auth: settings.auth_scheme(),
+            api_key,
+        }
+    }
+}
+
+impl TranslationProvider for DeepLProvider {
+    fn name(&self) -> &'static str {
+        "DeepL"
+    }
+
+    fn cache_id(&self) -> String {
+        format!("DeepL|{}|{}", self.base_url, self.model_type.as_deref().unwrap_or("default"))
+    }
+
+    fn translate<'a>(&'a self, request: TranslationRequest<'a>) -> ProviderFuture<'a, Vec<TranslationResult>> {
+        Box::pin(async move {
+            let mut body = serde_json::json!({
+                "text": request.segments.iter().map(|s| s.text.as_str()).collect::<Vec<_>>(),
+                "target_lang": deepl_language_code(request.target_language)?,
+                "preserve_formatting": true,
+            });
+            if let Some(model_type) = &self.model_type {
+                body["model_type"] = model_type.clone().into();
+            }
+            println!("[translate] DeepL [{}]: {} реплик", request.log_label, request.segments.len());
+
+            let mut builder = self.client.post(format!("{}/v2/translate", self.base_url)).json(&body);
+            if let Some(header) = auth_header(self.auth, self.api_key.as_deref())? {
+                builder = builder.header(reqwest::header::AUTHORIZATION, header);
+            }
+            let res = builder
+                .send()
+                .await
+                .map_err(|e| format!("Ошибка запроса к DeepL: {}", e))?;
+
+            if !res.status().is_success() {
+                let status = res.status();
+                let error_text = res.text().await.unwrap_or_else(|_| "Неизвестная ошибка".to_string());
+                return Err(format!("DeepL ошибка ({}): {}", status, error_text));
+            }
+
+            let response: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+            let translations = response["translations"]
+                .as_array()
+                .ok_or("Нет переводов в ответе DeepL".to_string())?;
+            // Переводы приходят в порядке текстов запроса
+            Ok(request
+                .segments
+                .iter()
+                .zip(translations)
+                .filter_map(|(segment, item)| {
+                    let translated_text = item["text"].as_str()?.trim().to_string();
+                    (!translated_text.is_empty()).then_some(TranslationResult { id: segment.id, translated_text })
+                })
+                .collect())
+        })
+    }
+
+    fn complete_json<'a>(&'a self, _request: ChatRequest<'a>) -> ProviderFuture<'a, String> {
+        Box::pin(async { Err("DeepL переводит только текст: генерация глоссария недоступна".to_string()) })
+    }
+}
+
+/// Код языка DeepL: `ru` → `RU`, английский и португальский — с вариантом (`EN-US`, `PT-BR`).
+/// Принимает и названия языков из мастера (`Russian`).
+fn deepl_language_code(language: &str) -> Result<String, String> {
+    let code = match language.trim().to_lowercase().as_str() {
+        "english" | "en" => "EN-US".to_string(),
+        "portuguese" | "pt" => "PT-BR".to_string(),
+        "russian" => "RU".to_string(),
+        "spanish" => "ES".to_string(),
+        "french" => "FR".to_string(),
+        "german" => "DE".to_string(),
+        "italian" => "IT".to_string(),
+        "japanese" => "JA".to_string(),
+        "chinese" => "ZH".to_string(),
+        other if other.len() == 2 || (other.len() == 5 && other.as_bytes()[2] == b'-') => other.to_uppercase(),
+        other => return Err(format!("DeepL: неизвестный язык перевода «{}», укажите код ISO 639-1", other)),
+    };
+    Ok(code)
+}
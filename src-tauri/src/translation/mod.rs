@@ -0,0 +1,108 @@
+// Провайдеры машинного перевода. OpenAI, любой сервер с OpenAI-совместимым
+// `/chat/completions` (Ollama, vLLM, LM Studio) или DeepL. Провайдер выбирается
+// в настройках проекта.
+
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use crate::project::SubtitleSegment;
+use crate::types::TranslationResult;
+
+pub mod chat;
+pub mod deepl;
+
+pub use chat::ChatCompletionsProvider;
+pub use deepl::DeepLProvider;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TranslationProviderKind {
+    #[default]
+    OpenAi,
+    /// Сервер с OpenAI-совместимым API: Ollama, vLLM, LM Studio
+    OpenAiCompatible,
+    DeepL,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AuthScheme {
+    /// `Authorization: Bearer <ключ>`
+    Bearer,
+    /// `Authorization: DeepL-Auth-Key <ключ>`
+    DeepLAuthKey,
+    /// Без авторизации (локальный сервер)
+    NoAuth,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TranslationSettings {
+    pub provider: TranslationProviderKind,
+    /// Адрес API; по умолчанию официальный у OpenAI и DeepL, Ollama на localhost у совместимых
+    pub base_url: Option<String>,
+    /// Модель; у DeepL — `model_type` (например, `quality_optimized`)
+    pub model: Option<String>,
+    /// Схема авторизации; по умолчанию своя у каждого провайдера
+    pub auth: Option<AuthScheme>,
+}
+
+impl TranslationSettings {
+    pub fn auth_scheme(&self) -> AuthScheme {
+        self.auth.unwrap_or(match self.provider {
+            TranslationProviderKind::OpenAi => AuthScheme::Bearer,
+            TranslationProviderKind::OpenAiCompatible => AuthScheme::NoAuth,
+            TranslationProviderKind::DeepL => AuthScheme::DeepLAuthKey,
+        })
+    }
+}
+
+/// Пакет реплик на перевод
+pub struct TranslationRequest<'a> {
+    /// Системный промпт со стилем и глоссарием (для LLM)
+    pub system_prompt: &'a str,
+    pub target_language: &'a str,
+    pub segments: &'a [SubtitleSegment],
+    /// Подпись пакета для логов
+    pub log_label: &'a str,
+}
+
+/// Запрос к LLM с ответом в виде JSON-объекта
+pub struct ChatRequest<'a> {
+    pub system_prompt: &'a str,
+    pub user_content: &'a str,
+    pub temperature: f64,
+    pub max_tokens: u32,
+}
+
+pub type ProviderFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+pub trait TranslationProvider: Send + Sync {
+    /// Название для логов и ошибок
+    fn name(&self) -> &'static str;
+
+    /// Провайдер, адрес и модель — часть ключа кэша перевода
+    fn cache_id(&self) -> String;
+
+    fn translate<'a>(&'a self, request: TranslationRequest<'a>) -> ProviderFuture<'a, Vec<TranslationResult>>;
+
+    /// Текст ответа модели (JSON) на системный и пользовательский промпт
+    fn complete_json<'a>(&'a self, request: ChatRequest<'a>) -> ProviderFuture<'a, String>;
+}
+
+/// Адрес без завершающего `/`
+fn trimmed_base_url(base_url: Option<&str>, default: &str) -> String {
+    base_url
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .unwrap_or(default)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+fn auth_header(auth: AuthScheme, api_key: Option<&str>) -> Result<Option<String>, String> {
+    let key = || api_key.ok_or_else(|| "Не задан API ключ провайдера перевода".to_string());
+    Ok(match auth {
+        AuthScheme::Bearer => Some(format!("Bearer {}", key()?)),
+        AuthScheme::DeepLAuthKey => Some(format!("DeepL-Auth-Key {}", key()?)),
+        AuthScheme::NoAuth => None,
+    })
+}
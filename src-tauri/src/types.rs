@@ -1,3 +1,4 @@
+use crate::project::Timestamp;
 use serde::{Deserialize, Serialize};
 
 // use crate::project::{SubtitleSegment, GlossaryEntry};
@@ -25,6 +26,6 @@ pub struct ProjectStructure {
 pub struct SegmentUpdates {
     pub text: Option<String>,
     pub translation: Option<String>,
-    pub start: Option<f64>,
-    pub end: Option<f64>,
+    pub start: Option<Timestamp>,
+    pub end: Option<Timestamp>,
 }
\ No newline at end of file
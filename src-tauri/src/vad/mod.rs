@@ -0,0 +1,354 @@
+// Детектор речи (VAD) по энергии и спектру кадров. Общий для синхронизации,
+// авторазбивки и проверок качества: на входе WAV или уже декодированный сигнал,
+// на выходе интервалы речи в секундах.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub mod wav;
+
+pub use wav::{read_wav_file, PcmAudio};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct VadOptions {
+    /// Длина окна анализа, мс
+    pub frame_ms: u32,
+    /// Шаг между окнами, мс
+    pub hop_ms: u32,
+    /// Насколько кадр должен быть громче текущего уровня шума, дБ
+    pub threshold_db: f64,
+    /// Тише этого (дБ FS) речь не ищем, даже при очень тихом шуме
+    pub min_level_db: f64,
+    /// Скорость, с которой уровень шума догоняет громкий сигнал, дБ/с
+    pub noise_rise_db_per_sec: f64,
+    /// Минимальная доля энергии в полосе речи 300–3400 Гц
+    pub min_band_ratio: f64,
+    /// Максимальная спектральная плоскостность (у белого шума ≈0.56, у голоса заметно ниже)
+    pub max_flatness: f64,
+    /// Минимальный разброс громкости внутри фрагмента, дБ: речь прерывистая, музыка ровнее
+    pub min_modulation_db: f64,
+    /// Паузы короче этого не разрывают речь, мс
+    pub hangover_ms: u32,
+    /// Более короткие фрагменты речи отбрасываются, мс
+    pub min_speech_ms: u32,
+}
+
+impl Default for VadOptions {
+    fn default() -> Self {
+        Self {
+            frame_ms: 30,
+            hop_ms: 10,
+            threshold_db: 9.0,
+            min_level_db: -55.0,
+            noise_rise_db_per_sec: 1.0,
+            min_band_ratio: 0.5,
+            max_flatness: 0.4,
+            min_modulation_db: 4.0,
+            hangover_ms: 300,
+            min_speech_ms: 200,
+        }
+    }
+}
+
+/// Признаки одного кадра
+#[derive(Debug, Clone, Copy)]
+struct FrameFeatures {
+    level_db: f64,
+    band_ratio: f64,
+    flatness: f64,
+}
+
+/// Речь в WAV-файле (например, извлечённом ffmpeg в 16 кГц моно)
+pub fn detect_speech_in_wav(path: &Path, options: &VadOptions) -> Result<Vec<(f64, f64)>, String> {
+    let audio = read_wav_file(path)?;
+    Ok(detect_speech(&audio, options))
+}
+
+/// Интервалы речи `(начало, конец)` в секундах, по возрастанию
+pub fn detect_speech(audio: &PcmAudio, options: &VadOptions) -> Vec<(f64, f64)> {
+    let rate = audio.sample_rate.max(1) as usize;
+    let hop = (rate * options.hop_ms.max(1) as usize / 1000).max(1);
+    let frame_len = (rate * options.frame_ms.max(1) as usize / 1000).max(2);
+    let fft_len = frame_len.next_power_of_two();
+    if audio.samples.len() < frame_len {
+        return Vec::new();
+    }
+
+    let window = hann(frame_len);
+    let frames: Vec<FrameFeatures> = (0..=(audio.samples.len() - frame_len) / hop)
+        .map(|i| frame_features(&audio.samples[i * hop..i * hop + frame_len], &window, fft_len, rate))
+        .collect();
+
+    let hop_sec = hop as f64 / rate as f64;
+    let active = classify_frames(&frames, hop_sec, options);
+    smooth_regions(&frames, &active, hop_sec, frame_len as f64 / rate as f64, options)
+}
+
+/// Решение по каждому кадру: громче адаптивного шума, энергия в полосе голоса и спектр не шумовой
+fn classify_frames(frames: &[FrameFeatures], hop_sec: f64, options: &VadOptions) -> Vec<bool> {
+    // Начальный уровень шума — 10-й перцентиль громкости первых двух секунд
+    let warmup = ((2.0 / hop_sec) as usize).clamp(1, frames.len());
+    let mut head: Vec<f64> = frames[..warmup].iter().map(|f| f.level_db).collect();
+    head.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let mut noise_floor = head[head.len() / 10];
+    let rise = options.noise_rise_db_per_sec * hop_sec;
+
+    frames
+        .iter()
+        .map(|frame| {
+            // Шум быстро опускается за тихими кадрами и медленно поднимается за громкими
+            noise_floor = if frame.level_db < noise_floor {
+                frame.level_db
+            } else {
+                (noise_floor + rise).min(frame.level_db)
+            };
+            frame.level_db > options.min_level_db
+                && frame.level_db > noise_floor + options.threshold_db
+                && frame.band_ratio >= options.min_band_ratio
+                && frame.flatness <= options.max_flatness
+        })
+        .collect()
+}
+
+/// Кадры → интервалы: паузы короче `hangover_ms` склеиваются, короткие всплески отбрасываются.
+/// Интервал с ровной громкостью (стандартное отклонение уровня меньше `min_modulation_db`)
+/// считается музыкой или гулом: у речи громкость скачет от слога к слогу.
+fn smooth_regions(
+    frames: &[FrameFeatures],
+    active: &[bool],
+    hop_sec: f64,
+    frame_sec: f64,
+    options: &VadOptions,
+) -> Vec<(f64, f64)> {
+    let hangover = (options.hangover_ms as f64 / 1000.0 / hop_sec).round() as usize;
+    let min_speech = options.min_speech_ms as f64 / 1000.0;
+
+    let mut regions = Vec::new();
+    let mut current: Option<(usize, usize)> = None;
+    for (i, &is_speech) in active.iter().enumerate() {
+        if !is_speech {
+            continue;
+        }
+        current = match current {
+            Some((start, last)) if i - last <= hangover + 1 => Some((start, i)),
+            Some(done) => {
+                regions.push(done);
+                Some((i, i))
+            }
+            None => Some((i, i)),
+        };
+    }
+    regions.extend(current);
+
+    let modulation = |start: usize, last: usize| {
+        let levels: Vec<f64> = frames[start..=last]
+            .iter()
+            .map(|f| f.level_db.max(options.min_level_db))
+            .collect();
+        let n = levels.len() as f64;
+        let mean = levels.iter().sum::<f64>() / n;
+        (levels.iter().map(|l| (l - mean) * (l - mean)).sum::<f64>() / n).sqrt()
+    };
+
+    regions
+        .into_iter()
+        .filter(|&(start, last)| modulation(start, last) >= options.min_modulation_db)
+        .map(|(start, last)| (start as f64 * hop_sec, last as f64 * hop_sec + frame_sec))
+        .filter(|(start, end)| end - start >= min_speech)
+        .collect()
+}
+
+fn hann(len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| (0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / (len - 1) as f64).cos()) as f32)
+        .collect()
+}
+
+fn frame_features(samples: &[f32], window: &[f32], fft_len: usize, rate: usize) -> FrameFeatures {
+    let energy = samples.iter().map(|&s| (s as f64) * (s as f64)).sum::<f64>() / samples.len() as f64;
+    let level_db = 10.0 * (energy + 1e-12).log10();
+
+    let mut re: Vec<f64> = vec![0.0; fft_len];
+    let mut im: Vec<f64> = vec![0.0; fft_len];
+    for (i, (&s, &w)) in samples.iter().zip(window).enumerate() {
+        re[i] = (s * w) as f64;
+    }
+    fft(&mut re, &mut im);
+
+    let bin_hz = rate as f64 / fft_len as f64;
+    let mut total = 0.0;
+    let mut band = 0.0;
+    let mut log_sum = 0.0;
+    let bins = fft_len / 2;
+    for k in 1..bins {
+        let power = re[k] * re[k] + im[k] * im[k] + 1e-18;
+        let hz = k as f64 * bin_hz;
+        total += power;
+        if (300.0..=3400.0).contains(&hz) {
+            band += power;
+        }
+        log_sum += power.ln();
+    }
+    let count = (bins - 1).max(1) as f64;
+    FrameFeatures {
+        level_db,
+        band_ratio: if total > 0.0 { band / total } else { 0.0 },
+        flatness: if total > 0.0 { (log_sum / count).exp() / (total / count) } else { 1.0 },
+    }
+}
+
+/// Итеративное БПФ по основанию 2 на месте; длина — степень двойки
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    let mut j = 0usize;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * std::f64::consts::PI / len as f64;
+        let (w_re, w_im) = (angle.cos(), angle.sin());
+        for start in (0..n).step_by(len) {
+            let (mut cur_re, mut cur_im) = (1.0, 0.0);
+            for k in 0..len / 2 {
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * cur_re - im[b] * cur_im;
+                let t_im = re[b] * cur_im + im[b] * cur_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+                let next_re = cur_re * w_re - cur_im * w_im;
+                cur_im = cur_re * w_im + cur_im * w_re;
+                cur_re = next_re;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16000;
+    const HOP: f64 = 0.01;
+
+    /// Детерминированный белый шум в [-1, 1]
+    struct Noise(u64);
+
+    impl Noise {
+        fn next(&mut self) -> f32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % 20001) as f32 / 10000.0 - 1.0
+        }
+    }
+
+    fn background(seconds: f64, level: f32) -> Vec<f32> {
+        let mut noise = Noise(0x1234_5678);
+        (0..(seconds * RATE as f64) as usize).map(|_| noise.next() * level).collect()
+    }
+
+    fn at(seconds: f64) -> usize {
+        (seconds * RATE as f64) as usize
+    }
+
+    /// «Речь»: гармоники 150 Гц в полосе 300–3000 Гц, слоги по 150 мс с провалами
+    /// громкости на 14 дБ между ними
+    fn add_speech(samples: &mut [f32], from: f64, to: f64) {
+        for (i, sample) in samples.iter_mut().enumerate().take(at(to)).skip(at(from)) {
+            let t = i as f64 / RATE as f64;
+            let tone: f64 = (2..=20)
+                .map(|h| (2.0 * std::f64::consts::PI * 150.0 * h as f64 * t).sin() / h as f64)
+                .sum();
+            let syllable = if (t - from) % 0.2 < 0.15 { 1.0 } else { 0.2 };
+            *sample += (0.1 * tone * syllable) as f32;
+        }
+    }
+
+    fn detect(samples: Vec<f32>, options: &VadOptions) -> Vec<(f64, f64)> {
+        detect_speech(&PcmAudio { sample_rate: RATE, samples }, options)
+    }
+
+    fn assert_close(found: &[(f64, f64)], expected: &[(f64, f64)]) {
+        assert_eq!(found.len(), expected.len(), "{:?} vs {:?}", found, expected);
+        for (&(start, end), &(from, to)) in found.iter().zip(expected) {
+            assert!((start - from).abs() <= 2.0 * HOP + 1e-9, "начало {} vs {}", start, from);
+            assert!((end - to).abs() <= 2.0 * HOP + 1e-9, "конец {} vs {}", end, to);
+        }
+    }
+
+    #[test]
+    fn silence_has_no_speech() {
+        assert!(detect(vec![0.0; at(5.0)], &VadOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn white_noise_is_not_speech() {
+        let mut samples = background(6.0, 0.001);
+        let mut noise = Noise(42);
+        for s in &mut samples[at(3.0)..at(5.0)] {
+            *s += noise.next() * 0.2;
+        }
+        assert!(detect(samples, &VadOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn steady_tone_is_not_speech() {
+        let mut samples = background(8.0, 0.001);
+        for (i, sample) in samples.iter_mut().enumerate().take(at(7.0)).skip(at(3.0)) {
+            let t = i as f64 / RATE as f64;
+            let chord: f64 = [261.6, 329.6, 392.0, 523.2]
+                .iter()
+                .map(|f| (2.0 * std::f64::consts::PI * f * t).sin())
+                .sum();
+            *sample += (0.05 * chord) as f32;
+        }
+        assert!(detect(samples, &VadOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn speech_bursts_are_found_at_their_bounds() {
+        let bursts = [(3.0, 4.5), (6.0, 8.0), (11.0, 12.2)];
+        let mut samples = background(14.0, 0.001);
+        for &(from, to) in &bursts {
+            add_speech(&mut samples, from, to);
+        }
+        assert_close(&detect(samples, &VadOptions::default()), &bursts);
+    }
+
+    #[test]
+    fn short_pause_is_bridged_by_hangover() {
+        let mut samples = background(8.0, 0.001);
+        add_speech(&mut samples, 3.0, 4.0);
+        add_speech(&mut samples, 4.2, 5.0);
+        assert_close(&detect(samples.clone(), &VadOptions::default()), &[(3.0, 5.0)]);
+
+        let options = VadOptions { hangover_ms: 100, ..VadOptions::default() };
+        assert_close(&detect(samples, &options), &[(3.0, 4.0), (4.2, 5.0)]);
+    }
+
+    #[test]
+    fn bursts_shorter_than_min_speech_are_dropped() {
+        let mut samples = background(6.0, 0.001);
+        add_speech(&mut samples, 3.0, 3.12);
+        // Один слог без перепадов громкости — отключаем проверку модуляции
+        let options = VadOptions { min_modulation_db: 0.0, ..VadOptions::default() };
+        assert!(detect(samples.clone(), &options).is_empty());
+
+        let options = VadOptions { min_speech_ms: 100, ..options };
+        assert_close(&detect(samples, &options), &[(3.0, 3.12)]);
+    }
+}
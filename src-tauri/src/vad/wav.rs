@@ -0,0 +1,154 @@
+use std::path::Path;
+
+/// Моно-сигнал в диапазоне [-1, 1]
+#[derive(Debug, Clone)]
+pub struct PcmAudio {
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+pub fn read_wav_file(path: &Path) -> Result<PcmAudio, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Ошибка чтения аудиофайла: {}", e))?;
+    read_wav(&bytes)
+}
+
+/// Разбор RIFF/WAVE по чанкам: заголовок и служебные чанки (`LIST` и т.п.) не попадают
+/// в сэмплы. PCM 8/16/24/32 бит и float 32; каналы сводятся в моно.
+pub fn read_wav(bytes: &[u8]) -> Result<PcmAudio, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("Файл не в формате WAV (нет заголовка RIFF/WAVE)".to_string());
+    }
+
+    let mut format: Option<(u16, u16, u32, u16)> = None;
+    let mut pos = 12usize;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = u32::from_le_bytes([bytes[pos + 4], bytes[pos + 5], bytes[pos + 6], bytes[pos + 7]]) as usize;
+        let body = pos + 8;
+        match id {
+            b"fmt " => {
+                if size < 16 || body + 16 > bytes.len() {
+                    return Err("Повреждён чанк fmt в WAV".to_string());
+                }
+                let field = |at: usize| u16::from_le_bytes([bytes[body + at], bytes[body + at + 1]]);
+                let mut tag = field(0);
+                let channels = field(2);
+                let sample_rate = u32::from_le_bytes([bytes[body + 4], bytes[body + 5], bytes[body + 6], bytes[body + 7]]);
+                let bits = field(14);
+                // WAVE_FORMAT_EXTENSIBLE: настоящий формат — первые два байта GUID подформата
+                if tag == WAVE_FORMAT_EXTENSIBLE && size >= 26 && body + 26 <= bytes.len() {
+                    tag = field(24);
+                }
+                format = Some((tag, channels, sample_rate, bits));
+            }
+            b"data" => {
+                let (tag, channels, sample_rate, bits) =
+                    format.ok_or_else(|| "В WAV чанк data идёт раньше fmt".to_string())?;
+                // ffmpeg при записи в поток оставляет размер 0 или 0xFFFFFFFF — читаем до конца
+                let end = if size == 0 || body + size > bytes.len() { bytes.len() } else { body + size };
+                let samples = decode_samples(&bytes[body..end], tag, channels, bits)?;
+                return Ok(PcmAudio { sample_rate, samples });
+            }
+            _ => {}
+        }
+        pos = body + size + (size & 1);
+    }
+    Err("В WAV нет аудиоданных (чанк data)".to_string())
+}
+
+fn decode_samples(data: &[u8], tag: u16, channels: u16, bits: u16) -> Result<Vec<f32>, String> {
+    let channels = channels.max(1) as usize;
+    let width = (bits as usize).div_ceil(8);
+    let sample: fn(&[u8]) -> f32 = match (tag, bits) {
+        (WAVE_FORMAT_PCM, 8) => |b| (b[0] as f32 - 128.0) / 128.0,
+        (WAVE_FORMAT_PCM, 16) => |b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+        (WAVE_FORMAT_PCM, 24) => |b| i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2_147_483_648.0,
+        (WAVE_FORMAT_PCM, 32) => |b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0,
+        (WAVE_FORMAT_IEEE_FLOAT, 32) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        _ => return Err(format!("Неподдерживаемый формат WAV: тег {}, {} бит", tag, bits)),
+    };
+    Ok(data
+        .chunks_exact(width * channels)
+        .map(|frame| frame.chunks_exact(width).map(sample).sum::<f32>() / channels as f32)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav_bytes(tag: u16, channels: u16, bits: u16, extra_chunk: bool, data: &[u8], data_size: u32) -> Vec<u8> {
+        let rate = 8000u32;
+        let block = channels * bits / 8;
+        let mut out = b"RIFF".to_vec();
+        out.extend(0u32.to_le_bytes());
+        out.extend(b"WAVEfmt ");
+        out.extend(16u32.to_le_bytes());
+        out.extend(tag.to_le_bytes());
+        out.extend(channels.to_le_bytes());
+        out.extend(rate.to_le_bytes());
+        out.extend((rate * block as u32).to_le_bytes());
+        out.extend(block.to_le_bytes());
+        out.extend(bits.to_le_bytes());
+        if extra_chunk {
+            // Нечётный размер: чанк выравнивается байтом-заполнителем
+            out.extend(b"LIST");
+            out.extend(13u32.to_le_bytes());
+            out.extend(b"INFOISFTabcde\0");
+        }
+        out.extend(b"data");
+        out.extend(data_size.to_le_bytes());
+        out.extend(data);
+        out
+    }
+
+    fn pcm16(samples: &[i16]) -> Vec<u8> {
+        samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn list_chunk_before_data_is_skipped() {
+        let data = pcm16(&[0, 16384, -16384]);
+        let audio = read_wav(&wav_bytes(WAVE_FORMAT_PCM, 1, 16, true, &data, data.len() as u32)).unwrap();
+        assert_eq!(audio.sample_rate, 8000);
+        assert_eq!(audio.samples, vec![0.0, 0.5, -0.5]);
+    }
+
+    #[test]
+    fn zero_data_size_reads_to_end() {
+        let data = pcm16(&[16384, 16384, -32768, 0]);
+        let audio = read_wav(&wav_bytes(WAVE_FORMAT_PCM, 2, 16, false, &data, 0)).unwrap();
+        // Стерео сводится в моно
+        assert_eq!(audio.samples, vec![0.5, -0.5]);
+    }
+
+    #[test]
+    fn eight_bit_pcm() {
+        let audio = read_wav(&wav_bytes(WAVE_FORMAT_PCM, 1, 8, false, &[128, 192, 64], 3)).unwrap();
+        assert_eq!(audio.samples, vec![0.0, 0.5, -0.5]);
+    }
+
+    #[test]
+    fn twenty_four_bit_pcm() {
+        let data = [0x00, 0x00, 0x40, 0x00, 0x00, 0xC0];
+        let audio = read_wav(&wav_bytes(WAVE_FORMAT_PCM, 1, 24, false, &data, 6)).unwrap();
+        assert_eq!(audio.samples, vec![0.5, -0.5]);
+    }
+
+    #[test]
+    fn float_32() {
+        let data: Vec<u8> = [0.25f32, -1.0].iter().flat_map(|s| s.to_le_bytes()).collect();
+        let audio = read_wav(&wav_bytes(WAVE_FORMAT_IEEE_FLOAT, 1, 32, false, &data, 8)).unwrap();
+        assert_eq!(audio.samples, vec![0.25, -1.0]);
+    }
+
+    #[test]
+    fn not_a_wav() {
+        assert!(read_wav(b"RIFF\0\0\0\0WAVEjunk").is_err());
+        assert!(read_wav(b"OggS").is_err());
+    }
+}
@@ -23,6 +23,7 @@ import {
 	insertEmptySegment,
 	splitSegmentAt
 } from './utils/subtitleSegmentsLocal';
+import { importSubtitlesWithNotice } from './utils/importDiagnostics';
 
 import iconNewProject from './assets/icons/new-project.svg';
 import iconNewFile from './assets/icons/new-file.svg';
@@ -705,7 +706,7 @@ export default function App() {
 	const exportSrtForProject = useCallback(async (project: ProjectData, fileId: string) => {
 		const stem = getSourceVideoStem(project, fileId);
 		const out = joinProjectPath(project.path, 'subtitles', `${stem}.srt`);
-		await projectService.exportSubtitles(project.path, fileId, 'srt', out);
+		await projectService.exportSubtitles(project.path, fileId, 'srt', out, { allow_untranslated: true });
 	}, []);
 
 	const handleSaveProject = useCallback(async (): Promise<boolean> => {
@@ -939,7 +940,7 @@ export default function App() {
 					currentProjectRef.current = mid;
 					setCurrentProject(mid);
 					await projectService.save(mid);
-					await projectService.importExistingSubtitles(abs, cp.path, subId);
+					await importSubtitlesWithNotice(abs, cp.path, subId);
 					const opened = await projectService.open(cp.path);
 					currentProjectRef.current = opened;
 					setCurrentProject(opened);
@@ -957,7 +958,7 @@ export default function App() {
 					let segs = file.subtitle_segments ?? [];
 					if (!segs.length && !file.id.startsWith('disk:')) {
 						const abs = joinProjectPath(cp.path, file.path.replace(/\\/g, '/'));
-						segs = await projectService.importExistingSubtitles(abs, cp.path, file.id);
+						segs = await importSubtitlesWithNotice(abs, cp.path, file.id);
 						const opened = await projectService.open(cp.path);
 						currentProjectRef.current = opened;
 						setCurrentProject(opened);
@@ -982,7 +983,7 @@ export default function App() {
 							let segs = sub.subtitle_segments ?? [];
 							if (!segs.length) {
 								const abs = joinProjectPath(cp.path, sub.path.replace(/\\/g, '/'));
-								segs = await projectService.importExistingSubtitles(abs, cp.path, sub.id);
+								segs = await importSubtitlesWithNotice(abs, cp.path, sub.id);
 								const opened = await projectService.open(cp.path);
 								currentProjectRef.current = opened;
 								setCurrentProject(opened);
@@ -3434,7 +3435,8 @@ export default function App() {
 												project.path,
 												withSeg.id,
 												'srt',
-												joinProjectPath(project.path, 'subtitles', `${stem}.srt`)
+												joinProjectPath(project.path, 'subtitles', `${stem}.srt`),
+												{ allow_untranslated: true }
 											)
 											.catch((e) => console.error('Initial SRT export failed', e));
 									}
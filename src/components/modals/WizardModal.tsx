@@ -8,6 +8,7 @@ import {
   GlossaryEntry,
   GlossaryTermGenerated
 } from '../../services/projectService';
+import { importSubtitlesWithNotice } from '../../utils/importDiagnostics';
 
 function joinProjectPath(base: string, ...parts: string[]): string {
   const a = base.replace(/[/\\]+$/, '');
@@ -57,7 +58,8 @@ async function finalizeEpisodePairInProject(
     projectPath,
     subId,
     'srt',
-    joinProjectPath(projectPath, subPath)
+    joinProjectPath(projectPath, subPath),
+    { allow_untranslated: true }
   );
   return { project: updated, subtitleFileId: subId };
 }
@@ -207,7 +209,7 @@ export const WizardModal: React.FC<WizardModalProps> = ({ onClose, projectPath,
       multiple: false,
       directory: false,
       title: 'Select subtitle file',
-      filters: [{ name: 'Subtitles', extensions: ['srt', 'vtt', 'ass', 'ssa', 'txt'] }]
+      filters: [{ name: 'Subtitles', extensions: ['srt', 'vtt', 'ass', 'ssa', 'ttml', 'dfxp', 'xml', 'stl', 'sub', 'scc', 'txt'] }]
     });
     if (selected && typeof selected === 'string') {
       setSubtitlePath(selected);
@@ -232,8 +234,9 @@ export const WizardModal: React.FC<WizardModalProps> = ({ onClose, projectPath,
 
       let segments: SubtitleSegment[] = [];
       if (sourceType === 'ai') {
-        const hasApiKey = await projectService.getApiKeyStatus();
-        if (!hasApiKey) {
+        const projectForPrompt = await projectService.open(projectPath!);
+        const engine = projectForPrompt.transcription?.engine ?? 'OpenAi';
+        if (engine === 'OpenAi' && !(await projectService.getApiKeyStatus())) {
           throw new Error('OpenAI API key is not set. Please activate the app first.');
         }
 
@@ -242,17 +245,16 @@ export const WizardModal: React.FC<WizardModalProps> = ({ onClose, projectPath,
         const audioPath = await projectService.extractAudioFromVideo(videoPath, outputAudioPath);
 
         const whisperLanguage = whisperLanguageCodes[sourceLanguage] ?? 'en';
-        const projectForPrompt = await projectService.open(projectPath!);
         const whisperPrompt = buildTranscriptionPrompt(contextPrompt, projectForPrompt.glossary);
         console.log('[Wizard] Whisper language:', whisperLanguage);
-        console.log('[Wizard] Calling OpenAI Whisper');
-        segments = await projectService.transcribeAudio(audioPath, whisperLanguage, whisperPrompt);
+        console.log('[Wizard] Transcribing with', engine);
+        segments = await projectService.transcribeAudio(audioPath, whisperLanguage, whisperPrompt, undefined, projectPath!);
       } else {
         if (!subtitlePath) {
           throw new Error('����� ������� ������� ���� ���������');
         }
         console.log('[Wizard] Importing existing subtitles');
-        segments = await projectService.importExistingSubtitles(subtitlePath, projectPath!, importedVideo.id);
+        segments = await importSubtitlesWithNotice(subtitlePath, projectPath!, importedVideo.id);
       }
 
       const { project: pairedProject, subtitleFileId } = await finalizeEpisodePairInProject(
@@ -273,7 +275,7 @@ export const WizardModal: React.FC<WizardModalProps> = ({ onClose, projectPath,
           max_terms: 45,
           target_language: targetIso,
           contextPrompt: contextPrompt
-        });
+        }, projectPath!);
         if (suggested.length > 0) {
           const opened = await projectService.open(projectPath!);
           const merged = mergeAutoGlossary(opened.glossary, suggested);
@@ -316,7 +318,8 @@ export const WizardModal: React.FC<WizardModalProps> = ({ onClose, projectPath,
         workingSegments,
         targetLanguage,
         prompt,
-        projectForGlossary.glossary
+        projectForGlossary.glossary,
+        projectPath!
       );
 
       const translatedSegments = workingSegments.map((segment) => {
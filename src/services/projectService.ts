@@ -6,6 +6,14 @@ export interface RecentProject {
   last_opened: string;
 }
 
+/** Слово реплики с таймингом распознавания. */
+export interface SubtitleWord {
+  text: string;
+  start: number;
+  end: number;
+  confidence?: number | null;
+}
+
 export interface SubtitleSegment {
   id: number;
   start: number;
@@ -13,6 +21,8 @@ export interface SubtitleSegment {
   duration: number;
   text: string;
   translation?: string | null;
+  /** Слова с таймингами (после распознавания Whisper). */
+  words?: SubtitleWord[] | null;
 }
 
 export interface ProjectFile {
@@ -24,6 +34,8 @@ export interface ProjectFile {
   subtitle_segments?: SubtitleSegment[] | null;
   /** Связанный файл эпизода: у субтитров — id видео, у видео — id субтитров. */
   linked_file_id?: string | null;
+  /** Частота кадров видео точной дробью (23.976 — 24000/1001). */
+  frame_rate?: { numerator: number; denominator: number } | null;
   created_at: string;
   updated_at: string;
 }
@@ -43,10 +55,41 @@ export interface ProjectData {
   target_language: string;
   files: ProjectFile[];
   glossary: GlossaryEntry[];
+  /** Движок распознавания речи проекта (по умолчанию OpenAI). */
+  transcription?: TranscriptionSettings;
+  /** Провайдер машинного перевода проекта (по умолчанию OpenAI). */
+  translation?: TranslationSettings;
   created_at: string;
   updated_at: string;
 }
 
+/** Локальные движки работают офлайн: whisper.cpp (`whisper-cli`) или faster-whisper (`whisper-ctranslate2`). */
+export type TranscriptionEngine = 'OpenAi' | 'WhisperCpp' | 'FasterWhisper';
+
+export interface TranscriptionSettings {
+  engine: TranscriptionEngine;
+  /** Путь к исполняемому файлу; по умолчанию ищется в PATH. */
+  executable?: string | null;
+  /** Файл модели ggml для whisper.cpp, имя или каталог для faster-whisper. */
+  model?: string | null;
+  threads?: number | null;
+}
+
+/** OpenAiCompatible — любой сервер с `/chat/completions`: Ollama, vLLM, LM Studio. */
+export type TranslationProviderKind = 'OpenAi' | 'OpenAiCompatible' | 'DeepL';
+
+export type TranslationAuthScheme = 'Bearer' | 'DeepLAuthKey' | 'NoAuth';
+
+export interface TranslationSettings {
+  provider: TranslationProviderKind;
+  /** Адрес API; по умолчанию официальный у OpenAI и DeepL, Ollama на localhost у совместимых. */
+  base_url?: string | null;
+  /** Модель; у DeepL — model_type. */
+  model?: string | null;
+  /** Схема авторизации; по умолчанию своя у каждого провайдера. */
+  auth?: TranslationAuthScheme | null;
+}
+
 /** Ответ `auto_generate_glossary` (черновые термины перед слиянием в проект). */
 export interface GlossaryTermGenerated {
   source: string;
@@ -70,6 +113,155 @@ export interface TranslationResult {
   translated_text: string;
 }
 
+/** Замечание парсера при импорте: строка исходного файла и что с ней сделано. */
+export interface ParseDiagnostic {
+  line: number;
+  kind: 'Repaired' | 'Skipped';
+  message: string;
+}
+
+export interface SubtitleImportResult {
+  segments: SubtitleSegment[];
+  diagnostics: ParseDiagnostic[];
+  /** Кодировка, в которой прочитан файл (`UTF-8`, `windows-1251`, …). */
+  encoding: string;
+  /** Формат, определённый по содержимому (или заданный явно), и уверенность 0..1. */
+  detected_format: { format: string; confidence: number };
+}
+
+/** Дорожка субтитров внутри видеофайла (MKV/MP4). */
+export interface EmbeddedSubtitleTrack {
+  index: number;
+  codec_name: string;
+  language: string | null;
+  title: string | null;
+  /** Текстовая дорожка (subrip, ass, mov_text, webvtt); PGS/VobSub извлечь нельзя. */
+  extractable: boolean;
+}
+
+/** Дорожка для вшивания в видео: файл субтитров проекта и его параметры в контейнере. */
+export interface MuxTrack {
+  file_id: string;
+  /** По умолчанию — язык перевода проекта (`und` при экспорте оригинала). */
+  language?: string;
+  title?: string;
+  default?: boolean;
+  forced?: boolean;
+}
+
+/** Оформление для вшивания субтитров без стилей ASS. */
+export interface BurnInOptions {
+  font_name?: string;
+  font_size?: number;
+  outline?: number;
+  crf?: number;
+}
+
+/** Результат вшивания субтитров в видео. */
+export interface VideoSubtitlesResult {
+  output_path: string;
+  /** Реплики без перевода, выведенные с исходным текстом или пометкой, по файлам субтитров. */
+  untranslated: UntranslatedSegments[];
+}
+
+export interface UntranslatedSegments {
+  file_id: string;
+  fallback_segment_ids: number[];
+}
+
+/** Оформление одного языка в двуязычном экспорте. */
+export interface BilingualStyle {
+  font_name?: string;
+  font_size?: number;
+  /** `#RRGGBB` */
+  color?: string;
+  italic?: boolean;
+}
+
+export interface BilingualOptions {
+  /** Обе строки в одной реплике или две дорожки со своими стилями (только ASS). */
+  layout?: 'SingleCue' | 'SeparateTracks';
+  order?: 'OriginalFirst' | 'TranslationFirst';
+  original_style?: BilingualStyle;
+  translation_style?: BilingualStyle;
+}
+
+export interface ExportOptions {
+  encoding?: string;
+  bilingual?: BilingualOptions;
+  /** Оригинал, только перевод или перевод с подстановкой оригинала (по умолчанию). */
+  text_source?: 'Original' | 'Translation' | 'Fallback';
+  /** Разрешить `Fallback`, когда у части реплик нет перевода. */
+  allow_untranslated?: boolean;
+  /** Для `Translation`: текст вместо отсутствующего перевода. */
+  untranslated_marker?: string;
+}
+
+export interface ExportResult {
+  output_path: string;
+  /** Реплики без перевода, выгруженные с исходным текстом или пометкой. */
+  fallback_segment_ids: number[];
+  /** Реплики SCC, выведенные позже начала: эфир занят предыдущим титром. */
+  delayed_segments: DelayedSegment[];
+  /** Реплики EBU STL, символы которых не нашлись в кодовой таблице и заменены на `?`. */
+  unmapped_segments: UnmappedSegment[];
+}
+
+export interface DelayedSegment {
+  id: number;
+  /** Задержка в кадрах 29.97. */
+  delay_frames: number;
+}
+
+export interface UnmappedSegment {
+  id: number;
+  /** Заменённые символы, без повторов. */
+  characters: string;
+}
+
+export interface BatchExportOptions {
+  formats: string[];
+  /** `{project}`, `{file}`, `{video_stem}`, `{lang}`, `{format}`, `{ext}`; по умолчанию `{project}_{video_stem}_{lang}.{ext}`. Без расширения формата в конце оно дописывается. */
+  name_template?: string;
+  export?: ExportOptions;
+  zip?: boolean;
+}
+
+export interface BatchExportItem {
+  file_id: string;
+  file_name: string;
+  format: string;
+  output_path: string | null;
+  error: string | null;
+  fallback_segment_ids: number[];
+  delayed_segments: DelayedSegment[];
+  unmapped_segments: UnmappedSegment[];
+}
+
+export interface BatchExportResult {
+  items: BatchExportItem[];
+  archive_path: string | null;
+}
+
+/** Ручная правка таймингов: сдвиг, растяжение, смена частоты кадров или подгонка по двум репликам. */
+export type TimingTransform =
+  | { kind: 'Shift'; offset_ms: number }
+  | { kind: 'Scale'; ratio: number; origin?: number }
+  | { kind: 'FrameRate'; from: string; to: string }
+  | { kind: 'TwoPoint'; first_id: number; first_time: number; second_id: number; second_time: number };
+
+/** Лимиты разбивки распознанной речи на реплики (по умолчанию 42×2, 7 с, 17 симв./с). */
+export interface SegmentationOptions {
+  max_chars_per_line?: number;
+  max_lines?: number;
+  max_duration?: number;
+  min_duration?: number;
+  min_gap_ms?: number;
+  max_cps?: number;
+  /** Пауза в речи (с), после которой всегда начинается новая реплика. */
+  pause_threshold?: number;
+}
+
 export interface SegmentUpdates {
   text?: string;
   translation?: string;
@@ -86,6 +278,11 @@ export const projectService = {
     return await invoke('save_api_key', { key });
   },
 
+  /** Ключ провайдера перевода (DeepL, совместимый сервер); у OpenAI — общий ключ. */
+  saveProviderApiKey: async (provider: TranslationProviderKind, key: string): Promise<void> => {
+    return await invoke('save_provider_api_key', { provider, key });
+  },
+
   // Get recent projects for welcome modal
   getRecent: async (): Promise<RecentProject[]> => {
     return await invoke('list_recent_projects');
@@ -113,12 +310,82 @@ export const projectService = {
     return await invoke('import_media', { projectPath, filePath });
   },
 
+  listSubtitleTracks: async (videoPath: string): Promise<EmbeddedSubtitleTrack[]> => {
+    return await invoke('list_subtitle_tracks', { videoPath });
+  },
+
+  /** Извлекает дорожку из видео проекта в новый файл субтитров, связанный с этим видео. */
+  importEmbeddedSubtitles: async (
+    projectPath: string,
+    videoFileId: string,
+    streamIndex: number
+  ): Promise<ProjectFile> => {
+    return await invoke('import_embedded_subtitles', { projectPath, videoFileId, streamIndex });
+  },
+
+  /**
+   * Копия видео с мягкими дорожками субтитров (видео и аудио не перекодируются).
+   * Текст дорожек выбирается по `exportOptions`, как при экспорте в файл.
+   */
+  muxSubtitles: async (
+    projectPath: string,
+    videoFileId: string,
+    tracks: MuxTrack[],
+    outputPath: string,
+    exportOptions?: ExportOptions
+  ): Promise<VideoSubtitlesResult> => {
+    return await invoke('mux_subtitles', {
+      projectPath,
+      videoFileId,
+      tracks,
+      outputPath,
+      exportOptions: exportOptions ?? null
+    });
+  },
+
+  /**
+   * Рендер связанного видео с вшитыми субтитрами. Прогресс — события `ai_progress`
+   * с operation_id `burn_in_<subtitleFileId>`. Текст выбирается по `exportOptions`,
+   * как при экспорте в файл.
+   */
+  burnInSubtitles: async (
+    projectPath: string,
+    subtitleFileId: string,
+    outputPath: string,
+    options?: BurnInOptions,
+    exportOptions?: ExportOptions
+  ): Promise<VideoSubtitlesResult> => {
+    return await invoke('burn_in_subtitles', {
+      projectPath,
+      subtitleFileId,
+      outputPath,
+      options: options ?? null,
+      exportOptions: exportOptions ?? null
+    });
+  },
+
+  cancelBurnIn: async (subtitleFileId: string): Promise<boolean> => {
+    return await invoke('cancel_burn_in', { operationId: `burn_in_${subtitleFileId}` });
+  },
+
   extractAudioFromVideo: async (videoPath: string, outputPath: string): Promise<string> => {
     return await invoke('extract_audio_from_video', { videoPath, outputPath });
   },
 
-  transcribeAudio: async (filePath: string, language?: string, prompt?: string): Promise<SubtitleSegment[]> => {
-    return await invoke('transcribe_audio', { filePath, language, prompt });
+  transcribeAudio: async (
+    filePath: string,
+    language?: string,
+    prompt?: string,
+    segmentation?: SegmentationOptions,
+    projectPath?: string
+  ): Promise<SubtitleSegment[]> => {
+    return await invoke('transcribe_audio', {
+      filePath,
+      language,
+      prompt,
+      segmentation: segmentation ?? null,
+      projectPath: projectPath ?? null
+    });
   },
 
   importExistingSubtitles: async (
@@ -126,7 +393,24 @@ export const projectService = {
     projectPath: string,
     fileId: string
   ): Promise<SubtitleSegment[]> => {
-    return await invoke('import_existing_subtitles', { subtitlePath, format: null, projectPath, fileId });
+    const result = await projectService.importExistingSubtitlesWithReport(subtitlePath, projectPath, fileId);
+    return result.segments;
+  },
+
+  /** Импорт с диагностикой парсера (исправленные и пропущенные реплики). */
+  importExistingSubtitlesWithReport: async (
+    subtitlePath: string,
+    projectPath: string,
+    fileId: string,
+    encoding?: string
+  ): Promise<SubtitleImportResult> => {
+    return await invoke('import_existing_subtitles', {
+      subtitlePath,
+      format: null,
+      projectPath,
+      fileId,
+      encoding: encoding ?? null
+    });
   },
 
   getGlossary: async (projectPath: string): Promise<GlossaryEntry[]> => {
@@ -137,13 +421,23 @@ export const projectService = {
     return await invoke('update_glossary', { projectPath, entries });
   },
 
-  /** Черновой глоссарий по частым словам + GPT (нужен API key). */
+  updateTranscriptionSettings: async (projectPath: string, settings: TranscriptionSettings): Promise<void> => {
+    return await invoke('update_transcription_settings', { projectPath, settings });
+  },
+
+  updateTranslationSettings: async (projectPath: string, settings: TranslationSettings): Promise<void> => {
+    return await invoke('update_translation_settings', { projectPath, settings });
+  },
+
+  /** Черновой глоссарий по частым словам + LLM провайдера перевода проекта (нужен API key). */
   autoGenerateGlossary: async (
     segments: SubtitleSegment[],
-    options: AutoGlossaryOptions
+    options: AutoGlossaryOptions,
+    projectPath?: string
   ): Promise<GlossaryTermGenerated[]> => {
     return await invoke('auto_generate_glossary', {
       segments,
+      projectPath: projectPath ?? null,
       options: {
         min_frequency: options.min_frequency ?? 2,
         max_terms: options.max_terms ?? 45,
@@ -159,13 +453,15 @@ export const projectService = {
     segments: SubtitleSegment[],
     targetLanguage: string,
     stylePrompt: string,
-    glossary: GlossaryEntry[] = []
+    glossary: GlossaryEntry[] = [],
+    projectPath?: string
   ): Promise<TranslationResult[]> => {
     return await invoke('translate_batch', {
       segments,
       targetLanguage,
       glossary,
-      stylePrompt
+      stylePrompt,
+      projectPath: projectPath ?? null
     });
   },
 
@@ -201,13 +497,47 @@ export const projectService = {
     return await invoke('delete_subtitle_segment', { projectPath, fileId, segmentId });
   },
 
+  /** Применяет преобразование к репликам `segmentIds` (по умолчанию ко всем) и сохраняет файл. */
+  transformSubtitleTiming: async (
+    projectPath: string,
+    fileId: string,
+    transform: TimingTransform,
+    segmentIds?: number[]
+  ): Promise<SubtitleSegment[]> => {
+    return await invoke('transform_subtitle_timing', {
+      projectPath,
+      fileId,
+      transform,
+      segmentIds: segmentIds ?? null
+    });
+  },
+
+  /** Заново разбить файл на реплики по лимитам (файл без перевода). */
+  resegmentSubtitles: async (
+    projectPath: string,
+    fileId: string,
+    options?: SegmentationOptions
+  ): Promise<SubtitleSegment[]> => {
+    return await invoke('resegment_subtitles', { projectPath, fileId, options: options ?? null });
+  },
+
   exportSubtitles: async (
     projectPath: string,
     fileId: string,
     format: string,
-    outputPath: string
-  ): Promise<string> => {
-    return await invoke('export_subtitles', { projectPath, fileId, format, outputPath });
+    outputPath: string,
+    options?: ExportOptions
+  ): Promise<ExportResult> => {
+    return await invoke('export_subtitles', { projectPath, fileId, format, outputPath, options: options ?? null });
+  },
+
+  /** Все файлы субтитров проекта во все форматы; ошибки — по каждому файлу в результате. */
+  batchExportSubtitles: async (
+    projectPath: string,
+    outputDir: string,
+    options: BatchExportOptions
+  ): Promise<BatchExportResult> => {
+    return await invoke('batch_export_subtitles', { projectPath, outputDir, options });
   },
 
   generateWaveform: async (
@@ -0,0 +1,34 @@
+import { message } from '@tauri-apps/plugin-dialog';
+import { projectService } from '../services/projectService';
+import type { ParseDiagnostic, SubtitleSegment } from '../services/projectService';
+
+/** Сколько замечаний парсера перечислять в уведомлении, остальные — одной строкой. */
+const MAX_LISTED_DIAGNOSTICS = 10;
+
+function formatImportDiagnostics(diagnostics: ParseDiagnostic[]): string {
+	const skipped = diagnostics.filter((d) => d.kind === 'Skipped').length;
+	const repaired = diagnostics.length - skipped;
+	const lines = diagnostics
+		.slice(0, MAX_LISTED_DIAGNOSTICS)
+		.map((d) => `${d.kind === 'Skipped' ? 'Пропущено' : 'Исправлено'}, строка ${d.line}: ${d.message}`);
+	if (diagnostics.length > MAX_LISTED_DIAGNOSTICS) {
+		lines.push(`…и ещё ${diagnostics.length - MAX_LISTED_DIAGNOSTICS}`);
+	}
+	return `Исправлено: ${repaired}, пропущено: ${skipped}.\n\n${lines.join('\n')}`;
+}
+
+/** Импорт субтитров в файл проекта; если парсер что-то исправил или пропустил — показывает это пользователю. */
+export async function importSubtitlesWithNotice(
+	subtitlePath: string,
+	projectPath: string,
+	fileId: string
+): Promise<SubtitleSegment[]> {
+	const report = await projectService.importExistingSubtitlesWithReport(subtitlePath, projectPath, fileId);
+	if (report.diagnostics.length > 0) {
+		await message(formatImportDiagnostics(report.diagnostics), {
+			title: 'Импорт субтитров',
+			kind: 'warning'
+		});
+	}
+	return report.segments;
+}